@@ -0,0 +1,443 @@
+//! Replays a log of executed funds distributor messages on an in-memory contract, and prints the
+//! resulting rewards of every user that appears in the log.
+//!
+//! Usage: `cargo run --example event-replay -- <log.json>`
+//!
+//! `examples/event-replay.sample.json` is a sample log, which the example's test replays.
+//!
+//! The log is a JSON object of the following shape:
+//!
+//! ```json
+//! {
+//!   "bech32_prefix": "terra",
+//!   "contract_address": "terra1...",
+//!   "weight_sources": { "terra1squad...": [{ "addr": "terra1...", "weight": 10 }] },
+//!   "instantiate": { "block": { "height": 100, "time": 1700000000 }, "sender": "terra1...", "msg": { ... } },
+//!   "messages": [
+//!     { "block": { "height": 101, "time": 1700000006 }, "sender": "terra1...", "funds": [], "msg": { ... } }
+//!   ]
+//! }
+//! ```
+//!
+//! `weight_sources` holds the members (or stakers) that cw4 (or cw20-stake) contracts return
+//! when queried, at instantiation or when added as weight sources. Only `bech32_prefix`,
+//! `instantiate` and `messages` are required, and `time` is in seconds.
+//!
+//! Messages are executed in order, without executing any messages they send, and replaying stops
+//! at the first message that fails.
+
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+use std::{env, fs, process};
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{mock_env, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    from_json, to_json_binary, Addr, Coin, ContractResult, Env, MessageInfo, OwnedDeps,
+    SystemError, SystemResult, Timestamp, Uint128, WasmQuery,
+};
+use cw4::{Member, MemberListResponse};
+use funds_distributor::contract::{execute, instantiate, query};
+use funds_distributor_api::api::{
+    AddWeightSourceMsg, NativeReward, StakeChangedHookMsg, UserRewardsParams, UserRewardsResponse,
+    UserWeightAtParams, UserWeightResponse, WeightSourceKind,
+};
+use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct ReplayLog {
+    bech32_prefix: String,
+    contract_address: Option<String>,
+    #[serde(default)]
+    weight_sources: HashMap<String, Vec<Member>>,
+    instantiate: LoggedInstantiate,
+    messages: Vec<LoggedMessage>,
+}
+
+#[derive(Deserialize)]
+struct LoggedBlock {
+    height: u64,
+    time: u64,
+}
+
+#[derive(Deserialize)]
+struct LoggedInstantiate {
+    block: LoggedBlock,
+    sender: String,
+    msg: InstantiateMsg,
+}
+
+#[derive(Deserialize)]
+struct LoggedMessage {
+    block: LoggedBlock,
+    sender: String,
+    #[serde(default)]
+    funds: Vec<Coin>,
+    msg: ExecuteMsg,
+}
+
+#[derive(Serialize)]
+struct ReplayOutput {
+    height: u64,
+    messages_replayed: usize,
+    users: Vec<UserState>,
+}
+
+#[derive(Serialize)]
+struct UserState {
+    user: String,
+    weight: Uint128,
+    effective_weight: Uint128,
+    rewards: Vec<NativeReward>,
+}
+
+/// Query messages of the cw4 and cw20-stake weight sources.
+#[cw_serde]
+enum WeightSourceQueryMsg {
+    ListMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ListStakers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+struct ListStakersResponse {
+    stakers: Vec<StakerBalance>,
+}
+
+#[cw_serde]
+struct StakerBalance {
+    address: String,
+    balance: Uint128,
+}
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("Usage: event-replay <log.json>");
+        process::exit(1);
+    });
+
+    let contents = fs::read_to_string(&path).unwrap_or_else(|e| fail(&format!("{path}: {e}")));
+    let log: ReplayLog =
+        serde_json::from_str(&contents).unwrap_or_else(|e| fail(&format!("{path}: {e}")));
+
+    let output = replay(log);
+
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+fn replay(log: ReplayLog) -> ReplayOutput {
+    // members of the squad contract start out with their weights at instantiation
+    let mut users: BTreeSet<String> = log
+        .weight_sources
+        .get(&log.instantiate.msg.squad_contract)
+        .into_iter()
+        .flatten()
+        .map(|member| member.addr.clone())
+        .collect();
+    let messages_replayed = log.messages.len();
+
+    let api = MockApi::default().with_prefix(Box::leak(log.bech32_prefix.into_boxed_str()));
+    let mut deps = mock_deps(api, log.weight_sources.clone());
+
+    let mut env = mock_env();
+    if let Some(contract_address) = log.contract_address {
+        env.contract.address = Addr::unchecked(contract_address);
+    }
+
+    set_block(&mut env, &log.instantiate.block);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&log.instantiate.sender, vec![]),
+        log.instantiate.msg,
+    )
+    .unwrap_or_else(|e| fail(&format!("instantiate failed: {e}")));
+
+    for (i, message) in log.messages.into_iter().enumerate() {
+        set_block(&mut env, &message.block);
+        users.extend(message_users(&message.msg, &log.weight_sources));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            message_info(&message.sender, message.funds),
+            message.msg,
+        )
+        .unwrap_or_else(|e| {
+            fail(&format!(
+                "message {i} at height {} failed: {e}",
+                message.block.height
+            ))
+        });
+    }
+
+    ReplayOutput {
+        height: env.block.height,
+        messages_replayed,
+        users: users
+            .into_iter()
+            .map(|user| user_state(&deps, &env, user))
+            .collect(),
+    }
+}
+
+fn mock_deps(
+    api: MockApi,
+    weight_sources: HashMap<String, Vec<Member>>,
+) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut querier = MockQuerier::new(&[]);
+    querier.update_wasm(move |query| {
+        let WasmQuery::Smart { contract_addr, msg } = query else {
+            return SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "non-smart wasm query".to_string(),
+            });
+        };
+        let Some(members) = weight_sources.get(contract_addr) else {
+            return SystemResult::Err(SystemError::NoSuchContract {
+                addr: contract_addr.clone(),
+            });
+        };
+
+        let mut members = members.clone();
+        members.sort_by(|a, b| a.addr.cmp(&b.addr));
+        let members_after = |start_after: Option<String>| {
+            members
+                .iter()
+                .filter(|member| start_after.as_ref().is_none_or(|it| &member.addr > it))
+                .cloned()
+                .collect::<Vec<Member>>()
+        };
+
+        let response = match from_json(msg) {
+            Ok(WeightSourceQueryMsg::ListMembers { start_after, .. }) => {
+                to_json_binary(&MemberListResponse {
+                    members: members_after(start_after),
+                })
+            }
+            Ok(WeightSourceQueryMsg::ListStakers { start_after, .. }) => {
+                to_json_binary(&ListStakersResponse {
+                    stakers: members_after(start_after)
+                        .into_iter()
+                        .map(|member| StakerBalance {
+                            address: member.addr,
+                            balance: member.weight.into(),
+                        })
+                        .collect(),
+                })
+            }
+            Err(e) => return SystemResult::Ok(ContractResult::Err(e.to_string())),
+        };
+        SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+    });
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api,
+        querier,
+        custom_query_type: PhantomData,
+    }
+}
+
+fn set_block(env: &mut Env, block: &LoggedBlock) {
+    env.block.height = block.height;
+    env.block.time = Timestamp::from_seconds(block.time);
+}
+
+fn message_info(sender: &str, funds: Vec<Coin>) -> MessageInfo {
+    MessageInfo {
+        sender: Addr::unchecked(sender),
+        funds,
+    }
+}
+
+/// Users whose rewards can be affected by the message.
+fn message_users(msg: &ExecuteMsg, weight_sources: &HashMap<String, Vec<Member>>) -> Vec<String> {
+    let source_members = |source: &str| {
+        weight_sources
+            .get(source)
+            .into_iter()
+            .flatten()
+            .map(|member| member.addr.clone())
+            .collect()
+    };
+
+    match msg {
+        ExecuteMsg::MemberChangedHook(msg) => {
+            msg.diffs.iter().map(|diff| diff.key.clone()).collect()
+        }
+        ExecuteMsg::StakeChangeHook(
+            StakeChangedHookMsg::Stake { addr, .. } | StakeChangedHookMsg::Unstake { addr, .. },
+        ) => vec![addr.to_string()],
+        ExecuteMsg::UpdateNativeStakeWeights(msg) => msg.users.clone(),
+        ExecuteMsg::ClaimRewards(msg) => vec![msg.user.clone()],
+        // delegates are credited commissions on the users' rewards
+        ExecuteMsg::SetDelegate(msg) => [
+            Some(&msg.user),
+            msg.delegate.as_ref().map(|it| &it.delegate),
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect(),
+        ExecuteMsg::AddWeightSource(AddWeightSourceMsg {
+            source: WeightSourceKind::Cw4 { addr } | WeightSourceKind::Cw20Stake { addr },
+            ..
+        }) => source_members(addr),
+        // weights are reconciled with the sources' members, so the old ones leave as well
+        ExecuteMsg::RemoveWeightSource(msg) => source_members(&msg.addr),
+        ExecuteMsg::ChangeWeightSource(msg) => {
+            let mut users = source_members(&msg.old_source);
+            users.extend(source_members(&msg.new_source));
+            users
+        }
+        ExecuteMsg::UpdateGroupMembers(msg) => msg.add.clone(),
+        // imported users have rewards without ever having had a weight in the contract
+        ExecuteMsg::ImportState(msg) => msg
+            .users
+            .iter()
+            .flat_map(|user| {
+                [
+                    Some(&user.user),
+                    user.delegate_commission
+                        .as_ref()
+                        .map(|commission| &commission.delegate),
+                ]
+            })
+            .flatten()
+            .cloned()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn user_state(
+    deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: &Env,
+    user: String,
+) -> UserState {
+    let rewards: UserRewardsResponse = query_json(
+        deps,
+        env,
+        QueryMsg::UserRewards(UserRewardsParams {
+            user: user.clone(),
+            native_denoms: None,
+        }),
+    );
+    let weight: UserWeightResponse = query_json(
+        deps,
+        env,
+        QueryMsg::UserWeightAt(UserWeightAtParams {
+            user: user.clone(),
+            height: env.block.height + 1,
+        }),
+    );
+
+    UserState {
+        user,
+        weight: weight.weight,
+        effective_weight: weight.effective_weight,
+        rewards: rewards.native_rewards,
+    }
+}
+
+fn query_json<T: serde::de::DeserializeOwned>(
+    deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: &Env,
+    msg: QueryMsg,
+) -> T {
+    query(deps.as_ref(), env.clone(), msg)
+        .and_then(|response| Ok(from_json(response)?))
+        .unwrap_or_else(|e| fail(&format!("query failed: {e}")))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use funds_distributor_api::api::{
+        ExportedCommission, ExportedUser, ImportStateMsg, RemoveWeightSourceMsg,
+    };
+
+    #[test]
+    fn sample_log_is_replayed() {
+        let log: ReplayLog =
+            serde_json::from_str(include_str!("event-replay.sample.json")).unwrap();
+
+        let output = replay(log);
+
+        assert_eq!(output.height, 106);
+        assert_eq!(output.messages_replayed, 6);
+
+        let users = output
+            .users
+            .iter()
+            .map(|user| (user.user.as_str(), user))
+            .collect::<HashMap<&str, &UserState>>();
+
+        // the delegate and the members of the changed weight source are reported as well
+        let delegate = users["terra1duskuv79e7ddm8tsulxcc28rmtlz0emj865njx9j03s0zxzny92qyggx4m"];
+        assert_eq!(
+            delegate.rewards,
+            vec![NativeReward {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(20),
+            }]
+        );
+
+        let new_member = users["terra1tps04uptd0rzy2a94jjjx4s0pcmyenvtv7lwfph730muq82f9n9salwx4d"];
+        assert_eq!(new_member.weight, Uint128::new(4));
+        assert_eq!(
+            new_member.rewards,
+            vec![NativeReward {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(400),
+            }]
+        );
+
+        assert_eq!(output.users.len(), 4);
+    }
+
+    #[test]
+    fn imported_users_and_members_of_removed_sources_are_reported() {
+        let weight_sources = HashMap::from([(
+            "stakers".to_string(),
+            vec![Member {
+                addr: "staker".to_string(),
+                weight: 1,
+            }],
+        )]);
+
+        let users = message_users(
+            &ExecuteMsg::RemoveWeightSource(RemoveWeightSourceMsg {
+                addr: "stakers".to_string(),
+            }),
+            &weight_sources,
+        );
+        assert_eq!(users, vec!["staker".to_string()]);
+
+        let users = message_users(
+            &ExecuteMsg::ImportState(ImportStateMsg {
+                users: vec![ExportedUser {
+                    user: "user".to_string(),
+                    rewards: vec![],
+                    delegate_commission: Some(ExportedCommission {
+                        delegate: "delegate".to_string(),
+                        commission: vec![],
+                    }),
+                }],
+            }),
+            &weight_sources,
+        );
+        assert_eq!(users, vec!["user".to_string(), "delegate".to_string()]);
+    }
+}
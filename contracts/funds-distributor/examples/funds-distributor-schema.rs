@@ -1,7 +1,14 @@
 use std::{env::current_dir, fs::create_dir_all};
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
-use funds_distributor_api::api::{MinimumEligibleWeightResponse, UserRewardsResponse};
+use funds_distributor_api::api::{
+    AuthorizedDistributorsResponse, ClaimOperatorsResponse, CompoundingTargetsResponse,
+    DelegateResponse, DenomAllowlistResponse, DistributionStatsResponse,
+    EligibleMemberCountResponse, ExportStateResponse, FundsDistributorHookMsg,
+    GroupMembersResponse, HooksResponse, MerkleClaimedResponse, MerkleDistributionResponse,
+    MinimumEligibleWeightResponse, ProtocolFeesResponse, RewardRecipientResponse,
+    RewardSplitResponse, RewardTiersResponse, UserRewardsResponse, WeightSourcesResponse,
+};
 use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
 fn main() {
@@ -16,4 +23,22 @@ fn main() {
     export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(UserRewardsResponse), &out_dir);
     export_schema(&schema_for!(MinimumEligibleWeightResponse), &out_dir);
+    export_schema(&schema_for!(CompoundingTargetsResponse), &out_dir);
+    export_schema(&schema_for!(ClaimOperatorsResponse), &out_dir);
+    export_schema(&schema_for!(RewardRecipientResponse), &out_dir);
+    export_schema(&schema_for!(RewardSplitResponse), &out_dir);
+    export_schema(&schema_for!(DelegateResponse), &out_dir);
+    export_schema(&schema_for!(HooksResponse), &out_dir);
+    export_schema(&schema_for!(FundsDistributorHookMsg), &out_dir);
+    export_schema(&schema_for!(WeightSourcesResponse), &out_dir);
+    export_schema(&schema_for!(EligibleMemberCountResponse), &out_dir);
+    export_schema(&schema_for!(GroupMembersResponse), &out_dir);
+    export_schema(&schema_for!(RewardTiersResponse), &out_dir);
+    export_schema(&schema_for!(DenomAllowlistResponse), &out_dir);
+    export_schema(&schema_for!(AuthorizedDistributorsResponse), &out_dir);
+    export_schema(&schema_for!(ProtocolFeesResponse), &out_dir);
+    export_schema(&schema_for!(DistributionStatsResponse), &out_dir);
+    export_schema(&schema_for!(MerkleDistributionResponse), &out_dir);
+    export_schema(&schema_for!(MerkleClaimedResponse), &out_dir);
+    export_schema(&schema_for!(ExportStateResponse), &out_dir);
 }
@@ -0,0 +1,142 @@
+//! Builds a Merkle distribution from a list of (address, amount) entries.
+//!
+//! Usage: `cargo run --example merkle-tree-builder -- <entries.csv|entries.json>`
+//!
+//! CSV files have one `address,amount` entry per line, optionally preceded by a header line.
+//! JSON files contain an array of `{ "address": ..., "amount": ... }` objects.
+//!
+//! Addresses have to be valid bech32, and are lowercased before being hashed, so that they match
+//! the addresses claiming on chain.
+//!
+//! Prints the root, the total to fund the distribution with, and each entry's index and proof
+//! as JSON, ready to be used in `RegisterMerkleDistribution` and `ClaimMerkle` messages.
+
+use std::{env, fs, process};
+
+use cosmwasm_std::Uint128;
+use funds_distributor_api::merkle::{build_tree, encode_hash, leaf_hash, tree_proof, tree_root};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct Entry {
+    address: String,
+    amount: Uint128,
+}
+
+#[derive(Serialize)]
+struct MerkleTreeOutput {
+    root: String,
+    total: Uint128,
+    claims: Vec<Claim>,
+}
+
+#[derive(Serialize)]
+struct Claim {
+    index: u32,
+    address: String,
+    amount: Uint128,
+    proof: Vec<String>,
+}
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("Usage: merkle-tree-builder <entries.csv|entries.json>");
+        process::exit(1);
+    });
+
+    let contents = fs::read_to_string(&path).unwrap_or_else(|e| fail(&format!("{path}: {e}")));
+
+    let entries = if path.ends_with(".json") {
+        serde_json::from_str(&contents).unwrap_or_else(|e| fail(&format!("{path}: {e}")))
+    } else {
+        parse_csv(&contents)
+    };
+
+    if entries.is_empty() {
+        fail("no entries found");
+    }
+
+    // claims are indexed by u32, so larger lists cannot be claimed from
+    if u32::try_from(entries.len()).is_err() {
+        fail(&format!(
+            "too many entries: {}, at most {}",
+            entries.len(),
+            u32::MAX
+        ));
+    }
+
+    let entries = entries
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| Entry {
+            address: normalize_address(&entry.address)
+                .unwrap_or_else(|e| fail(&format!("entry {index}: {e}"))),
+            amount: entry.amount,
+        })
+        .collect::<Vec<Entry>>();
+
+    let leaves = entries
+        .iter()
+        .zip(0u32..)
+        .map(|(entry, index)| leaf_hash(index, &entry.address, entry.amount))
+        .collect();
+    let levels = build_tree(leaves);
+
+    let claims = entries
+        .into_iter()
+        .zip(0u32..)
+        .map(|(entry, index)| Claim {
+            index,
+            proof: tree_proof(&levels, index).iter().map(encode_hash).collect(),
+            address: entry.address,
+            amount: entry.amount,
+        })
+        .collect::<Vec<Claim>>();
+
+    let output = MerkleTreeOutput {
+        root: encode_hash(&tree_root(&levels).unwrap()),
+        total: claims.iter().map(|claim| claim.amount).sum(),
+        claims,
+    };
+
+    println!("{}", serde_json::to_string_pretty(&output).unwrap());
+}
+
+fn parse_csv(contents: &str) -> Vec<Entry> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .filter_map(|(line_no, line)| {
+            let (address, amount) = line
+                .split_once(',')
+                .unwrap_or_else(|| fail(&format!("line {}: expected address,amount", line_no + 1)));
+
+            match amount.trim().parse::<u128>() {
+                Ok(amount) => Some(Entry {
+                    address: address.trim().to_string(),
+                    amount: Uint128::from(amount),
+                }),
+                // skip the header line
+                Err(_) if line_no == 0 => None,
+                Err(e) => fail(&format!("line {}: {e}", line_no + 1)),
+            }
+        })
+        .collect()
+}
+
+/// Lowercases the address, which is how bech32 addresses are represented on chain, and checks
+/// that it is valid bech32.
+fn normalize_address(address: &str) -> Result<String, String> {
+    let address = address.trim().to_lowercase();
+
+    bech32::decode(&address).map_err(|e| format!("invalid address {address}: {e}"))?;
+
+    Ok(address)
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    process::exit(1);
+}
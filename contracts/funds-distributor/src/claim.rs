@@ -1,21 +1,52 @@
-use crate::native_distributions::{NativeDistribution, NATIVE_DISTRIBUTIONS};
-use crate::rewards::calculate_user_reward;
+use crate::compounding::compound_msg;
+use crate::equal_distributions::settle_user_per_member_rewards;
+use crate::groups::settle_user_group_rewards;
+use crate::hooks::hook_submsgs;
+use crate::native_distributions::{
+    settle_user_native_reward, NativeDistribution, NATIVE_DISTRIBUTIONS,
+};
+use crate::operators::{assert_user_or_operator, load_reward_recipient};
+use crate::reward_splits::split_claimed_coins;
+use crate::snapshot_distributions::{settle_snapshot_distributions, MAX_SNAPSHOT_SETTLEMENTS};
 use crate::state::NATIVE_GLOBAL_INDICES;
+use crate::state_transfer::subtract_claimed_rewards;
+use crate::tiers::settle_user_tier_rewards;
 use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
 use common::cw::Context;
-use cosmwasm_std::{coin, BankMsg, Order, Response, Uint128};
+use cosmwasm_std::{coin, BankMsg, Coin, CosmosMsg, Order, Response, Uint128};
 use cw_utils::NativeBalance;
-use funds_distributor_api::api::ClaimRewardsMsg;
+use funds_distributor_api::api::{
+    ClaimHookMsg, ClaimRecipient, ClaimRewardsMsg, FundsDistributorHookMsg,
+};
 use funds_distributor_api::error::DistributorResult;
 
 /// Attempt to claim rewards for the given parameters.
 ///
 /// Calculates rewards currently available to the user, and marks them as claimed.
 ///
-/// Returns a Response containing submessages that will send available rewards to the user.
+/// Returns a Response containing submessages that will send available rewards to the user's
+/// reward recipient, or split them between the user's split recipients, or to the compounding
+/// target if one was given.
 pub fn claim_rewards(ctx: &mut Context, msg: ClaimRewardsMsg) -> DistributorResult<Response> {
     let user = ctx.deps.api.addr_validate(&msg.user)?;
 
+    // only the user or their operators can decide to compound their rewards somewhere
+    if msg.compound_to.is_some() {
+        assert_user_or_operator(ctx, &user)?;
+    }
+
+    // move user's shares of snapshot distributions into their pending rewards
+    settle_snapshot_distributions(ctx.deps.branch(), user.clone(), MAX_SNAPSHOT_SETTLEMENTS)?;
+
+    // move user's rewards from group distributions into their pending rewards
+    settle_user_group_rewards(ctx.deps.branch(), user.clone())?;
+
+    // move user's rewards from equal split distributions into their pending rewards
+    settle_user_per_member_rewards(ctx.deps.branch(), user.clone())?;
+
+    // move user's rewards from tiered distributions into their pending rewards
+    settle_user_tier_rewards(ctx.deps.branch(), user.clone())?;
+
     let user_weight = EFFECTIVE_USER_WEIGHTS
         .may_load(ctx.deps.storage, user.clone())?
         .unwrap_or_default();
@@ -32,20 +63,20 @@ pub fn claim_rewards(ctx: &mut Context, msg: ClaimRewardsMsg) -> DistributorResu
     let mut coins = NativeBalance(vec![]);
 
     for denom in denoms {
-        let distribution =
-            NATIVE_DISTRIBUTIONS().may_load(ctx.deps.storage, (user.clone(), denom.clone()))?;
-        let global_index = NATIVE_GLOBAL_INDICES
-            .may_load(ctx.deps.storage, denom.clone())?
-            .unwrap_or_default();
+        let reward =
+            settle_user_native_reward(ctx.deps.branch(), &user, denom.clone(), user_weight)?;
 
         // if no rewards for the given asset, just skip
-        if global_index.is_zero() {
+        if reward.is_zero() {
             continue;
         }
 
-        let reward = calculate_user_reward(global_index, distribution, user_weight);
         coins += coin(reward.u128(), denom.clone());
 
+        let global_index = NATIVE_GLOBAL_INDICES
+            .may_load(ctx.deps.storage, denom.clone())?
+            .unwrap_or_default();
+
         NATIVE_DISTRIBUTIONS().save(
             ctx.deps.storage,
             (user.clone(), denom.clone()),
@@ -61,11 +92,66 @@ pub fn claim_rewards(ctx: &mut Context, msg: ClaimRewardsMsg) -> DistributorResu
     coins.normalize();
     let coins = coins.into_vec();
 
-    Ok(Response::new()
+    subtract_claimed_rewards(ctx.deps.storage, &coins)?;
+
+    let payouts: Vec<(String, Vec<Coin>, Option<CosmosMsg>)> = match msg.compound_to {
+        None => {
+            let recipients = match split_claimed_coins(ctx.deps.as_ref(), &user, &coins)? {
+                Some(split_coins) => split_coins,
+                None => vec![(load_reward_recipient(ctx, &user)?, coins.clone())],
+            };
+
+            recipients
+                .into_iter()
+                .map(|(recipient, coins)| {
+                    // empty bank sends are rejected, so there is nothing to send if nothing
+                    // was claimed
+                    let bank_msg = (!coins.is_empty()).then(|| {
+                        BankMsg::Send {
+                            to_address: recipient.to_string(),
+                            amount: coins.clone(),
+                        }
+                        .into()
+                    });
+
+                    (recipient.to_string(), coins, bank_msg)
+                })
+                .collect()
+        }
+        Some(target) => {
+            let compound_to = target.contract.clone();
+            let compound_msg = compound_msg(ctx, target, coins.clone())?;
+
+            // the target is still validated, but nothing is compounded if nothing was claimed
+            let compound_msg = (!coins.is_empty()).then_some(compound_msg);
+
+            vec![(compound_to, coins.clone(), compound_msg)]
+        }
+    };
+
+    let mut response = Response::new()
         .add_attribute("action", "claim_rewards")
-        .add_attribute("user", user.to_string())
-        .add_message(BankMsg::Send {
-            to_address: user.to_string(),
-            amount: coins,
-        }))
+        .add_attribute("user", user.to_string());
+
+    let mut recipients: Vec<ClaimRecipient> = vec![];
+
+    for (recipient, funds, payout_msg) in payouts {
+        response = response
+            .add_attribute("recipient", recipient.clone())
+            .add_messages(payout_msg);
+
+        recipients.push(ClaimRecipient { recipient, funds });
+    }
+
+    // hooks are notified of the claim once, even if it was split between multiple recipients
+    let hook_submsgs = hook_submsgs(
+        ctx.deps.as_ref(),
+        FundsDistributorHookMsg::ClaimHook(ClaimHookMsg {
+            user: user.to_string(),
+            recipients,
+            funds: coins,
+        }),
+    )?;
+
+    Ok(response.add_submessages(hook_submsgs))
 }
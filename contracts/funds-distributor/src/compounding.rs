@@ -0,0 +1,71 @@
+use crate::state::SQUAD_CONTRACT;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Order, Response, StdResult, WasmMsg};
+use cw_storage_plus::Map;
+use funds_distributor_api::api::{
+    CompoundTarget, CompoundingTargetsResponse, UpdateCompoundingTargetsMsg,
+};
+use funds_distributor_api::error::DistributorError::{CompoundingTargetNotAllowed, Unauthorized};
+use funds_distributor_api::error::DistributorResult;
+
+/// Contracts that users are allowed to compound their claimed rewards into.
+pub const COMPOUNDING_TARGETS: Map<Addr, ()> = Map::new("compounding_targets");
+
+pub fn update_compounding_targets(
+    ctx: &mut Context,
+    msg: UpdateCompoundingTargetsMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    for target in msg.add {
+        let target = ctx.deps.api.addr_validate(&target)?;
+        COMPOUNDING_TARGETS.save(ctx.deps.storage, target, &())?;
+    }
+
+    for target in msg.remove {
+        let target = ctx.deps.api.addr_validate(&target)?;
+        COMPOUNDING_TARGETS.remove(ctx.deps.storage, target);
+    }
+
+    Ok(Response::new().add_attribute("action", "update_compounding_targets"))
+}
+
+/// Creates a message sending the claimed rewards to the compounding target, along with the
+/// target's execute message.
+///
+/// Fails if the target is not one of the allowed compounding targets.
+pub fn compound_msg(
+    ctx: &Context,
+    target: CompoundTarget,
+    funds: Vec<Coin>,
+) -> DistributorResult<CosmosMsg> {
+    let contract = ctx.deps.api.addr_validate(&target.contract)?;
+
+    if !COMPOUNDING_TARGETS.has(ctx.deps.storage, contract.clone()) {
+        return Err(CompoundingTargetNotAllowed {
+            contract: contract.to_string(),
+        });
+    }
+
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.to_string(),
+        msg: target.msg,
+        funds,
+    }
+    .into())
+}
+
+pub fn query_compounding_targets(
+    qctx: QueryContext,
+) -> DistributorResult<CompoundingTargetsResponse> {
+    let targets = COMPOUNDING_TARGETS
+        .keys(qctx.deps.storage, None, None, Order::Ascending)
+        .map(|target| target.map(|target| target.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(CompoundingTargetsResponse { targets })
+}
@@ -1,16 +1,51 @@
 use crate::claim::claim_rewards;
+use crate::compounding::{query_compounding_targets, update_compounding_targets};
+use crate::delegates::{query_delegate, set_delegate};
+use crate::denoms::{query_denom_allowlist, retire_denom, update_denom_allowlist};
 use crate::distributing::distribute_native;
-use crate::eligibility::{
-    execute_update_minimum_eligible_weight, query_minimum_eligible_weight, MINIMUM_ELIGIBLE_WEIGHT,
+use crate::distributors::{query_authorized_distributors, update_authorized_distributors};
+use crate::eligibility::{execute_update_minimum_eligible_weight, query_minimum_eligible_weight};
+use crate::equal_distributions::{distribute_native_equally, query_eligible_member_count};
+use crate::fees::{query_distribution_stats, query_protocol_fees, update_protocol_fees};
+use crate::groups::{distribute_native_to_group, query_group_members, update_group_members};
+use crate::hooks::{add_hook, query_hooks, remove_hook, reply_hook_failed, HOOK_REPLY_ID};
+use crate::merkle_distributions::{
+    claim_merkle, query_merkle_claimed, query_merkle_distribution, register_merkle_distribution,
+    sweep_merkle_distribution,
 };
+use crate::migrations::migrate_state;
+use crate::operators::{
+    grant_claim_operator, query_claim_operators, query_reward_recipient, revoke_claim_operator,
+    set_reward_recipient,
+};
+use crate::reward_splits::{query_reward_split, set_reward_split};
 use crate::rewards::query_user_rewards;
-use crate::state::SQUAD_CONTRACT;
-use crate::user_weights::{get_initial_weights, save_initial_weights, update_user_weights};
+use crate::snapshot_distributions::distribute_native_at_height;
+use crate::stake_weights::{update_native_stake_weights, update_stake_weights};
+use crate::state::{DISTRIBUTION_SEQ, SQUAD_CONTRACT};
+use crate::state_transfer::{
+    assert_state_import_closed, close_state_import, import_state, query_export_state,
+    STATE_IMPORT_OPEN,
+};
+use crate::tiers::{
+    default_reward_tiers, distribute_native_tiered, query_reward_tiers, update_reward_tiers,
+    REWARD_TIERS,
+};
+use crate::user_weights::{
+    get_initial_weights, query_total_weight_at, query_user_weight_at, save_initial_weights,
+    update_user_weights,
+};
+use crate::weight_sources::{
+    add_weight_source, change_weight_source, query_weight_sources, remove_weight_source,
+    start_weight_source_change, WeightSourceConfig, WEIGHT_SOURCES,
+};
 use common::cw::{Context, QueryContext};
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response,
+    entry_point, to_json_binary, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Reply, Response,
 };
 use cw2::set_contract_version;
+use funds_distributor_api::api::WeightSourceKind;
+use funds_distributor_api::error::DistributorError::UnknownReplyId;
 use funds_distributor_api::error::DistributorResult;
 use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 
@@ -31,12 +66,34 @@ pub fn instantiate(
     SQUAD_CONTRACT.save(deps.storage, &squad_contract)?;
 
     let minimum_eligible_weight = msg.minimum_eligible_weight.unwrap_or_default();
-    MINIMUM_ELIGIBLE_WEIGHT.save(deps.storage, &minimum_eligible_weight)?;
+    REWARD_TIERS.save(deps.storage, &default_reward_tiers(minimum_eligible_weight))?;
+
+    DISTRIBUTION_SEQ.save(deps.storage, &0)?;
 
-    let initial_weights = get_initial_weights(deps.as_ref(), squad_contract).unwrap();
+    if msg.state_import.unwrap_or_default() {
+        STATE_IMPORT_OPEN.save(deps.storage, &true)?;
+    }
+
+    WEIGHT_SOURCES.save(
+        deps.storage,
+        squad_contract.clone(),
+        &WeightSourceConfig {
+            kind: WeightSourceKind::Cw4 {
+                addr: squad_contract.to_string(),
+            },
+            scale: Decimal::one(),
+        },
+    )?;
+
+    let initial_weights = get_initial_weights(deps.as_ref(), squad_contract.clone())?;
     let mut ctx = Context { deps, env, info };
 
-    save_initial_weights(&mut ctx, initial_weights, minimum_eligible_weight)?;
+    save_initial_weights(
+        &mut ctx,
+        squad_contract,
+        initial_weights,
+        minimum_eligible_weight,
+    )?;
 
     Ok(Response::new().add_attribute("action", "instantiate"))
 }
@@ -49,13 +106,57 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> DistributorResult<Response> {
     let ctx = &mut Context { deps, env, info };
+
+    // imports are only validated against the contract's balance while it holds nothing else
+    if matches!(
+        msg,
+        ExecuteMsg::DistributeNative {}
+            | ExecuteMsg::DistributeNativeAtHeight { .. }
+            | ExecuteMsg::DistributeNativeEqually {}
+            | ExecuteMsg::DistributeNativeTiered {}
+            | ExecuteMsg::DistributeNativeToGroup { .. }
+            | ExecuteMsg::RegisterMerkleDistribution(_)
+    ) {
+        assert_state_import_closed(ctx.deps.storage)?;
+    }
+
     match msg {
         ExecuteMsg::MemberChangedHook(msg) => update_user_weights(ctx, msg),
+        ExecuteMsg::StakeChangeHook(msg) => update_stake_weights(ctx, msg),
+        ExecuteMsg::UpdateNativeStakeWeights(msg) => update_native_stake_weights(ctx, msg),
         ExecuteMsg::UpdateMinimumEligibleWeight(msg) => {
             execute_update_minimum_eligible_weight(ctx, msg)
         }
         ExecuteMsg::DistributeNative {} => distribute_native(ctx),
+        ExecuteMsg::DistributeNativeAtHeight { height } => distribute_native_at_height(ctx, height),
+        ExecuteMsg::DistributeNativeEqually {} => distribute_native_equally(ctx),
+        ExecuteMsg::DistributeNativeTiered {} => distribute_native_tiered(ctx),
+        ExecuteMsg::DistributeNativeToGroup { group_id } => {
+            distribute_native_to_group(ctx, group_id)
+        }
         ExecuteMsg::ClaimRewards(msg) => claim_rewards(ctx, msg),
+        ExecuteMsg::UpdateCompoundingTargets(msg) => update_compounding_targets(ctx, msg),
+        ExecuteMsg::GrantClaimOperator(msg) => grant_claim_operator(ctx, msg),
+        ExecuteMsg::RevokeClaimOperator(msg) => revoke_claim_operator(ctx, msg),
+        ExecuteMsg::SetRewardRecipient(msg) => set_reward_recipient(ctx, msg),
+        ExecuteMsg::SetRewardSplit(msg) => set_reward_split(ctx, msg),
+        ExecuteMsg::SetDelegate(msg) => set_delegate(ctx, msg),
+        ExecuteMsg::AddHook(msg) => add_hook(ctx, msg),
+        ExecuteMsg::RemoveHook(msg) => remove_hook(ctx, msg),
+        ExecuteMsg::AddWeightSource(msg) => add_weight_source(ctx, msg),
+        ExecuteMsg::RemoveWeightSource(msg) => remove_weight_source(ctx, msg),
+        ExecuteMsg::ChangeWeightSource(msg) => change_weight_source(ctx, msg),
+        ExecuteMsg::UpdateGroupMembers(msg) => update_group_members(ctx, msg),
+        ExecuteMsg::UpdateRewardTiers(msg) => update_reward_tiers(ctx, msg),
+        ExecuteMsg::UpdateDenomAllowlist(msg) => update_denom_allowlist(ctx, msg),
+        ExecuteMsg::RetireDenom(msg) => retire_denom(ctx, msg),
+        ExecuteMsg::UpdateAuthorizedDistributors(msg) => update_authorized_distributors(ctx, msg),
+        ExecuteMsg::UpdateProtocolFees(msg) => update_protocol_fees(ctx, msg),
+        ExecuteMsg::RegisterMerkleDistribution(msg) => register_merkle_distribution(ctx, msg),
+        ExecuteMsg::ClaimMerkle(msg) => claim_merkle(ctx, msg),
+        ExecuteMsg::SweepMerkleDistribution(msg) => sweep_merkle_distribution(ctx, msg),
+        ExecuteMsg::ImportState(msg) => import_state(ctx, msg),
+        ExecuteMsg::CloseStateImport {} => close_state_import(ctx),
     }
 }
 
@@ -68,15 +169,65 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> DistributorResult<Binary> {
         QueryMsg::MinimumEligibleWeight {} => {
             to_json_binary(&query_minimum_eligible_weight(qctx)?)?
         }
+        QueryMsg::CompoundingTargets {} => to_json_binary(&query_compounding_targets(qctx)?)?,
+        QueryMsg::ClaimOperators(params) => to_json_binary(&query_claim_operators(qctx, params)?)?,
+        QueryMsg::RewardRecipient(params) => {
+            to_json_binary(&query_reward_recipient(qctx, params)?)?
+        }
+        QueryMsg::RewardSplit(params) => to_json_binary(&query_reward_split(qctx, params)?)?,
+        QueryMsg::Delegate(params) => to_json_binary(&query_delegate(qctx, params)?)?,
+        QueryMsg::Hooks {} => to_json_binary(&query_hooks(qctx)?)?,
+        QueryMsg::WeightSources {} => to_json_binary(&query_weight_sources(qctx)?)?,
+        QueryMsg::UserWeightAt(params) => to_json_binary(&query_user_weight_at(qctx, params)?)?,
+        QueryMsg::TotalWeightAt(params) => to_json_binary(&query_total_weight_at(qctx, params)?)?,
+        QueryMsg::EligibleMemberCount {} => to_json_binary(&query_eligible_member_count(qctx)?)?,
+        QueryMsg::GroupMembers(params) => to_json_binary(&query_group_members(qctx, params)?)?,
+        QueryMsg::RewardTiers {} => to_json_binary(&query_reward_tiers(qctx)?)?,
+        QueryMsg::DenomAllowlist {} => to_json_binary(&query_denom_allowlist(qctx)?)?,
+        QueryMsg::AuthorizedDistributors {} => {
+            to_json_binary(&query_authorized_distributors(qctx)?)?
+        }
+        QueryMsg::ProtocolFees {} => to_json_binary(&query_protocol_fees(qctx)?)?,
+        QueryMsg::DistributionStats(params) => {
+            to_json_binary(&query_distribution_stats(qctx, params)?)?
+        }
+        QueryMsg::MerkleDistribution(params) => {
+            to_json_binary(&query_merkle_distribution(qctx, params)?)?
+        }
+        QueryMsg::MerkleClaimed(params) => to_json_binary(&query_merkle_claimed(qctx, params)?)?,
+        QueryMsg::ExportState(params) => to_json_binary(&query_export_state(qctx, params)?)?,
     };
     Ok(response)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> DistributorResult<Response> {
-    SQUAD_CONTRACT.save(deps.storage, &deps.api.addr_validate(&msg.new_hook_src)?)?;
+pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> DistributorResult<Response> {
+    match msg.id {
+        HOOK_REPLY_ID => reply_hook_failed(msg),
+        id => Err(UnknownReplyId { id }),
+    }
+}
 
-    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, _env: Env, msg: MigrateMsg) -> DistributorResult<Response> {
+    // the state is migrated first, so that older weights are attributed to the previous admin
+    let previous_version = migrate_state(deps.branch(), CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    // the new hook source takes the old one's place as a weight source, with the users' weights
+    // resynced from it by the new admin
+    if let Some(new_hook_src) = msg.new_hook_src {
+        let new_hook_src = deps.api.addr_validate(&new_hook_src)?;
+        let old_hook_src = SQUAD_CONTRACT.load(deps.storage)?;
+
+        if new_hook_src != old_hook_src {
+            start_weight_source_change(deps.branch(), old_hook_src, new_hook_src.clone())?;
+
+            SQUAD_CONTRACT.save(deps.storage, &new_hook_src)?;
+        }
+    }
 
-    Ok(Response::new().add_attribute("action", "migrate"))
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("previous_version", previous_version)
+        .add_attribute("new_version", CONTRACT_VERSION))
 }
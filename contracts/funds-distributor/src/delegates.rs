@@ -0,0 +1,140 @@
+use crate::native_distributions::{load_user_distribution, update_user_native_distributions};
+use crate::operators::assert_user_or_operator;
+use crate::rewards::calculate_new_user_reward;
+use crate::state::NATIVE_GLOBAL_INDICES;
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{coin, Addr, Coin, Deps, Response, StdResult, Uint128};
+use cw_storage_plus::Map;
+use cw_utils::NativeBalance;
+use funds_distributor_api::api::{
+    DelegateCommission, DelegateParams, DelegateResponse, SetDelegateMsg,
+};
+use funds_distributor_api::error::DistributorError::{InvalidDelegateCommission, SelfDelegation};
+use funds_distributor_api::error::DistributorResult;
+
+const MAX_COMMISSION_BPS: u16 = 10_000;
+
+/// Delegates credited a commission on the users' rewards, along with the commission in basis
+/// points.
+///
+/// Only the rewards of regular distributions, which accrue through the users' reward indices,
+/// are subject to the commission. Snapshot, group, equal split and tiered distributions are
+/// paid to the users in full.
+pub const USER_DELEGATES: Map<Addr, (Addr, u16)> = Map::new("user_delegates");
+
+pub fn set_delegate(ctx: &mut Context, msg: SetDelegateMsg) -> DistributorResult<Response> {
+    let user = ctx.deps.api.addr_validate(&msg.user)?;
+
+    assert_user_or_operator(ctx, &user)?;
+
+    // rewards accrued until now are settled under the previous commission
+    let user_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(ctx.deps.storage, user.clone())?
+        .unwrap_or_default();
+    update_user_native_distributions(ctx.deps.branch(), user.clone(), user_weight)?;
+
+    let response = Response::new()
+        .add_attribute("action", "set_delegate")
+        .add_attribute("user", user.to_string());
+
+    match msg.delegate {
+        None => {
+            USER_DELEGATES.remove(ctx.deps.storage, user);
+
+            Ok(response)
+        }
+        Some(DelegateCommission {
+            delegate,
+            commission_bps,
+        }) => {
+            let delegate = ctx.deps.api.addr_validate(&delegate)?;
+
+            if delegate == user {
+                return Err(SelfDelegation);
+            }
+            if commission_bps > MAX_COMMISSION_BPS {
+                return Err(InvalidDelegateCommission { commission_bps });
+            }
+
+            USER_DELEGATES.save(ctx.deps.storage, user, &(delegate.clone(), commission_bps))?;
+
+            Ok(response
+                .add_attribute("delegate", delegate.to_string())
+                .add_attribute("commission_bps", commission_bps.to_string()))
+        }
+    }
+}
+
+/// Calculates the commission that the user's delegate is owed on the given newly accrued reward.
+///
+/// Returns None if the user has no delegate, or the commission is zero.
+pub fn delegate_commission(
+    deps: Deps,
+    user: &Addr,
+    reward: Uint128,
+) -> StdResult<Option<(Addr, Uint128)>> {
+    let commission = USER_DELEGATES
+        .may_load(deps.storage, user.clone())?
+        .map(|(delegate, commission_bps)| {
+            (
+                delegate,
+                reward.multiply_ratio(commission_bps, MAX_COMMISSION_BPS),
+            )
+        })
+        .filter(|(_, commission)| !commission.is_zero());
+
+    Ok(commission)
+}
+
+/// Calculates the commission that the user's delegate is owed on the user's rewards accrued
+/// since their last settlement, which is not credited to the delegate yet.
+///
+/// Returns None if the user has no delegate.
+pub fn unsettled_delegate_commission(
+    deps: Deps,
+    user: &Addr,
+    denoms: &[String],
+) -> StdResult<Option<(Addr, Vec<Coin>)>> {
+    let Some((delegate, _)) = USER_DELEGATES.may_load(deps.storage, user.clone())? else {
+        return Ok(None);
+    };
+
+    let user_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    let mut commissions = NativeBalance(vec![]);
+
+    for denom in denoms {
+        let global_index = NATIVE_GLOBAL_INDICES
+            .may_load(deps.storage, denom.clone())?
+            .unwrap_or_default();
+        let (user_index, _) = load_user_distribution(deps, user, denom)?;
+
+        let new_reward = calculate_new_user_reward(global_index, user_index, user_weight);
+
+        if let Some((_, commission)) = delegate_commission(deps, user, new_reward)? {
+            commissions += coin(commission.u128(), denom.clone());
+        }
+    }
+
+    Ok(Some((delegate, commissions.into_vec())))
+}
+
+pub fn query_delegate(
+    qctx: QueryContext,
+    params: DelegateParams,
+) -> DistributorResult<DelegateResponse> {
+    let user = qctx.deps.api.addr_validate(&params.user)?;
+
+    let delegate =
+        USER_DELEGATES
+            .may_load(qctx.deps.storage, user)?
+            .map(|(delegate, commission_bps)| DelegateCommission {
+                delegate: delegate.to_string(),
+                commission_bps,
+            });
+
+    Ok(DelegateResponse { delegate })
+}
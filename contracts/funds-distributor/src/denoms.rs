@@ -0,0 +1,433 @@
+use crate::delegates::unsettled_delegate_commission;
+use crate::equal_distributions::{NATIVE_PER_MEMBER_INDICES, USER_PER_MEMBER_INDICES};
+use crate::groups::{GROUP_GLOBAL_INDICES, GROUP_USER_INDICES, USER_GROUPS};
+use crate::native_distributions::NATIVE_DISTRIBUTIONS;
+use crate::rewards::calculate_user_rewards;
+use crate::snapshot_distributions::USER_SNAPSHOT_CURSORS;
+use crate::state::{
+    DENOMS_BY_LAST_DISTRIBUTION, NATIVE_GLOBAL_INDICES, NATIVE_INDEX_HISTORY, SQUAD_CONTRACT,
+};
+use crate::tiers::{TIER_GLOBAL_INDICES, USER_TIER_INDICES};
+use crate::user_weights::{EFFECTIVE_USER_WEIGHTS, USER_WEIGHTS};
+use common::cw::{Context, QueryContext};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    Addr, BankMsg, Coin, Deps, DepsMut, Order, Response, StdResult, Storage, Uint128,
+};
+use cw_storage_plus::{Bound, Item, Map, PrefixBound};
+use funds_distributor_api::api::{
+    AllowedDenom, DenomAllowlistResponse, RetireDenomMsg, UpdateDenomAllowlistMsg,
+};
+use funds_distributor_api::error::DistributorError::{
+    DenomNotDistributed, DenomNotFullyClaimed, DenomRetirementInProgress, NoFundsToDistribute,
+    Unauthorized,
+};
+use funds_distributor_api::error::DistributorResult;
+use itertools::Itertools;
+
+/// Whether distributions are restricted to the denoms in the allowlist.
+pub const DENOM_ALLOWLIST_ENABLED: Item<bool> = Item::new("denom_allowlist_enabled");
+
+/// Denoms that can be distributed while the allowlist is enabled, along with the minimum amount
+/// that has to be distributed at once.
+pub const DENOM_ALLOWLIST: Map<String, Uint128> = Map::new("denom_allowlist");
+
+/// Number of users (or index history entries) gone through by a single retirement call, unless
+/// specified otherwise.
+const DEFAULT_RETIREMENT_LIMIT: u32 = 30;
+
+#[cw_serde]
+pub enum RetirementStage {
+    CheckingRewards,
+    RemovingUserState,
+    RemovingIndexHistory,
+}
+
+/// Progress of the retirement of a denom.
+#[cw_serde]
+pub struct DenomRetirement {
+    pub denom: String,
+    pub stage: RetirementStage,
+    /// Last user gone through in the current stage
+    pub start_after: Option<Addr>,
+}
+
+/// Retirement of a denom that was started, but not finished yet.
+pub const DENOM_RETIREMENT: Item<DenomRetirement> = Item::new("denom_retirement");
+
+pub fn update_denom_allowlist(
+    ctx: &mut Context,
+    msg: UpdateDenomAllowlistMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    if let Some(enabled) = msg.enabled {
+        DENOM_ALLOWLIST_ENABLED.save(ctx.deps.storage, &enabled)?;
+    }
+
+    for denom in msg.remove {
+        DENOM_ALLOWLIST.remove(ctx.deps.storage, denom);
+    }
+
+    for allowed_denom in msg.add {
+        DENOM_ALLOWLIST.save(
+            ctx.deps.storage,
+            allowed_denom.denom,
+            &allowed_denom.min_amount,
+        )?;
+    }
+
+    Ok(Response::new().add_attribute("action", "update_denom_allowlist"))
+}
+
+/// Splits the funds sent for a distribution into the ones that can be distributed, and the ones
+/// that should be refunded to the sender.
+///
+/// While the allowlist is enabled, only allowlisted denoms sent in at least their minimum amount
+/// can be distributed. Denoms being retired cannot be distributed.
+///
+/// Fails if none of the funds can be distributed.
+pub fn split_distribution_funds(
+    deps: Deps,
+    funds: Vec<Coin>,
+) -> DistributorResult<(Vec<Coin>, Vec<Coin>)> {
+    let allowlist_enabled = DENOM_ALLOWLIST_ENABLED
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+
+    let retiring_denom = DENOM_RETIREMENT
+        .may_load(deps.storage)?
+        .map(|retirement| retirement.denom);
+
+    let mut accepted: Vec<Coin> = vec![];
+    let mut rejected: Vec<Coin> = vec![];
+
+    for fund in funds {
+        // retired denoms can only be distributed again once all of their state is removed
+        if retiring_denom.as_ref() == Some(&fund.denom) {
+            rejected.push(fund);
+            continue;
+        }
+
+        if !allowlist_enabled {
+            accepted.push(fund);
+            continue;
+        }
+
+        match DENOM_ALLOWLIST.may_load(deps.storage, fund.denom.clone())? {
+            Some(min_amount) if fund.amount >= min_amount => accepted.push(fund),
+            _ => rejected.push(fund),
+        }
+    }
+
+    if accepted.is_empty() {
+        return Err(NoFundsToDistribute);
+    }
+
+    Ok((accepted, rejected))
+}
+
+/// Adds a message refunding the rejected funds to the sender, if there are any.
+pub fn add_refund(response: Response, sender: &Addr, rejected: Vec<Coin>) -> Response {
+    if rejected.is_empty() {
+        return response;
+    }
+
+    let refunded = rejected
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+
+    response
+        .add_attribute("refunded", refunded)
+        .add_message(BankMsg::Send {
+            to_address: sender.to_string(),
+            amount: rejected,
+        })
+}
+
+/// Removes all the state kept for a denom whose rewards were fully claimed, and removes it from
+/// the allowlist.
+///
+/// Goes through at most `limit` users (or index history entries) per call, resuming where the
+/// previous call stopped, in stages:
+/// 1. users' rewards in the denom are checked to be fully claimed,
+/// 2. users' state for the denom is removed,
+/// 3. the denom's index history, and finally its global index, are removed.
+///
+/// The denom cannot be distributed while it is being retired.
+pub fn retire_denom(ctx: &mut Context, msg: RetireDenomMsg) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let denom = msg.denom;
+    let limit = msg.limit.unwrap_or(DEFAULT_RETIREMENT_LIMIT).max(1) as usize;
+
+    let mut retirement = match DENOM_RETIREMENT.may_load(ctx.deps.storage)? {
+        Some(retirement) if retirement.denom != denom => {
+            return Err(DenomRetirementInProgress {
+                denom: retirement.denom,
+            });
+        }
+        Some(retirement) => retirement,
+        None => {
+            if !NATIVE_GLOBAL_INDICES.has(ctx.deps.storage, denom.clone()) {
+                return Err(DenomNotDistributed { denom });
+            }
+
+            DENOM_ALLOWLIST.remove(ctx.deps.storage, denom.clone());
+
+            DenomRetirement {
+                denom: denom.clone(),
+                stage: RetirementStage::CheckingRewards,
+                start_after: None,
+            }
+        }
+    };
+
+    // stages are moved through in a single call for as long as the limit allows
+    let mut remaining = limit;
+
+    while remaining > 0 {
+        match retirement.stage {
+            RetirementStage::CheckingRewards => {
+                let users =
+                    load_users_page(ctx.deps.as_ref(), retirement.start_after.clone(), remaining)?;
+
+                for user in &users {
+                    let rewards =
+                        calculate_user_rewards(ctx.deps.as_ref(), user, vec![denom.clone()])?;
+
+                    // the user's delegate is owed a commission on rewards the user did not settle
+                    let unsettled_commission = unsettled_delegate_commission(
+                        ctx.deps.as_ref(),
+                        user,
+                        std::slice::from_ref(&denom),
+                    )?
+                    .map(|(_, commission)| commission)
+                    .unwrap_or_default();
+
+                    // shares of snapshot distributions that were not gone through can be owed
+                    if rewards.snapshot_settlement_pending
+                        || rewards
+                            .native_rewards
+                            .iter()
+                            .any(|reward| !reward.amount.is_zero())
+                        || unsettled_commission
+                            .iter()
+                            .any(|commission| !commission.amount.is_zero())
+                    {
+                        return Err(DenomNotFullyClaimed { denom });
+                    }
+                }
+
+                remaining -= users.len();
+                retirement.start_after = users.last().cloned();
+
+                if remaining > 0 {
+                    remove_denom_distribution_indices(ctx.deps.branch(), &denom)?;
+
+                    retirement.stage = RetirementStage::RemovingUserState;
+                    retirement.start_after = None;
+                }
+            }
+            RetirementStage::RemovingUserState => {
+                let users =
+                    load_users_page(ctx.deps.as_ref(), retirement.start_after.clone(), remaining)?;
+
+                for user in &users {
+                    remove_user_denom_state(ctx.deps.branch(), user, &denom)?;
+                }
+
+                remaining -= users.len();
+                retirement.start_after = users.last().cloned();
+
+                if remaining > 0 {
+                    retirement.stage = RetirementStage::RemovingIndexHistory;
+                    retirement.start_after = None;
+                }
+            }
+            RetirementStage::RemovingIndexHistory => {
+                let index_history = NATIVE_INDEX_HISTORY
+                    .prefix(denom.clone())
+                    .keys(ctx.deps.storage, None, None, Order::Ascending)
+                    .take(remaining)
+                    .collect::<StdResult<Vec<u64>>>()?;
+
+                for distribution_seq in &index_history {
+                    NATIVE_INDEX_HISTORY
+                        .remove(ctx.deps.storage, (denom.clone(), *distribution_seq));
+                    DENOMS_BY_LAST_DISTRIBUTION
+                        .remove(ctx.deps.storage, (*distribution_seq, denom.clone()));
+                }
+
+                remaining -= index_history.len();
+
+                if remaining > 0 {
+                    NATIVE_GLOBAL_INDICES.remove(ctx.deps.storage, denom.clone());
+                    DENOM_RETIREMENT.remove(ctx.deps.storage);
+
+                    return Ok(Response::new()
+                        .add_attribute("action", "retire_denom")
+                        .add_attribute("denom", denom)
+                        .add_attribute("retired", "true"));
+                }
+            }
+        }
+    }
+
+    DENOM_RETIREMENT.save(ctx.deps.storage, &retirement)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "retire_denom")
+        .add_attribute("denom", denom)
+        .add_attribute("retired", "false"))
+}
+
+/// Whether the users' state for the denom is being removed as part of its retirement.
+///
+/// The denom's global index can no longer change at that point, and its index history is about
+/// to be removed, so users' indices that were not stored are the global index.
+pub fn is_denom_state_removed(storage: &dyn Storage, denom: &str) -> StdResult<bool> {
+    let removed = DENOM_RETIREMENT
+        .may_load(storage)?
+        .is_some_and(|retirement| {
+            retirement.denom == denom && retirement.stage != RetirementStage::CheckingRewards
+        });
+
+    Ok(removed)
+}
+
+/// Removes the indices of the other distribution modes for the denom, along with their totals.
+fn remove_denom_distribution_indices(deps: DepsMut, denom: &str) -> StdResult<()> {
+    let group_indices = GROUP_GLOBAL_INDICES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, String)>>>()?;
+    for key in group_indices
+        .into_iter()
+        .filter(|(_, key_denom)| key_denom == denom)
+    {
+        GROUP_GLOBAL_INDICES.remove(deps.storage, key);
+    }
+
+    let tier_indices = TIER_GLOBAL_INDICES
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(u32, String)>>>()?;
+    for key in tier_indices
+        .into_iter()
+        .filter(|(_, key_denom)| key_denom == denom)
+    {
+        TIER_GLOBAL_INDICES.remove(deps.storage, key);
+    }
+
+    NATIVE_PER_MEMBER_INDICES.remove(deps.storage, denom.to_string());
+
+    Ok(())
+}
+
+fn remove_user_denom_state(deps: DepsMut, user: &Addr, denom: &str) -> StdResult<()> {
+    NATIVE_DISTRIBUTIONS().remove(deps.storage, (user.clone(), denom.to_string()))?;
+    USER_PER_MEMBER_INDICES.remove(deps.storage, (user.clone(), denom.to_string()));
+    USER_TIER_INDICES.remove(deps.storage, (user.clone(), denom.to_string()));
+
+    let groups = USER_GROUPS
+        .prefix(user.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for group_id in groups {
+        GROUP_USER_INDICES.remove(deps.storage, (group_id, user.clone(), denom.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Loads a page of the users that currently have, or ever had, a weight, rewards, or a group,
+/// ordered by their addresses.
+pub fn load_users_page(
+    deps: Deps,
+    start_after: Option<Addr>,
+    limit: usize,
+) -> StdResult<Vec<Addr>> {
+    let bound = start_after.clone().map(Bound::exclusive);
+    let prefix_bound = || start_after.clone().map(PrefixBound::exclusive);
+
+    let pages = vec![
+        first_users(
+            USER_WEIGHTS.keys(deps.storage, bound.clone(), None, Order::Ascending),
+            limit,
+        )?,
+        first_users(
+            EFFECTIVE_USER_WEIGHTS.keys(deps.storage, bound.clone(), None, Order::Ascending),
+            limit,
+        )?,
+        // users without a weight anymore only show up in the weights' history
+        first_users(
+            EFFECTIVE_USER_WEIGHTS
+                .changelog()
+                .prefix_range(deps.storage, prefix_bound(), None, Order::Ascending)
+                .map(|entry| entry.map(|((user, _), _)| user)),
+            limit,
+        )?,
+        first_users(
+            NATIVE_DISTRIBUTIONS()
+                .prefix_range(deps.storage, prefix_bound(), None, Order::Ascending)
+                .map(|entry| entry.map(|((user, _), _)| user)),
+            limit,
+        )?,
+        first_users(
+            USER_SNAPSHOT_CURSORS.keys(deps.storage, bound, None, Order::Ascending),
+            limit,
+        )?,
+        first_users(
+            USER_GROUPS
+                .prefix_range(deps.storage, prefix_bound(), None, Order::Ascending)
+                .map(|entry| entry.map(|((user, _), _)| user)),
+            limit,
+        )?,
+    ];
+
+    Ok(pages.into_iter().kmerge().dedup().take(limit).collect())
+}
+
+/// Collects the first `limit` distinct users from users ordered by their addresses.
+fn first_users(users: impl Iterator<Item = StdResult<Addr>>, limit: usize) -> StdResult<Vec<Addr>> {
+    let mut page: Vec<Addr> = vec![];
+
+    for user in users {
+        let user = user?;
+
+        if page.last() == Some(&user) {
+            continue;
+        }
+        if page.len() == limit {
+            break;
+        }
+
+        page.push(user);
+    }
+
+    Ok(page)
+}
+
+pub fn query_denom_allowlist(qctx: QueryContext) -> DistributorResult<DenomAllowlistResponse> {
+    let enabled = DENOM_ALLOWLIST_ENABLED
+        .may_load(qctx.deps.storage)?
+        .unwrap_or_default();
+
+    let denoms = DENOM_ALLOWLIST
+        .range(qctx.deps.storage, None, None, Order::Ascending)
+        .map(|allowed_denom| {
+            allowed_denom.map(|(denom, min_amount)| AllowedDenom { denom, min_amount })
+        })
+        .collect::<StdResult<Vec<AllowedDenom>>>()?;
+
+    Ok(DenomAllowlistResponse { enabled, denoms })
+}
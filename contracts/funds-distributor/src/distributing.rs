@@ -1,22 +1,39 @@
+use crate::denoms::{add_refund, split_distribution_funds};
+use crate::distributors::assert_authorized_distributor;
+use crate::fees::{add_protocol_fees, take_protocol_fees};
+use crate::hooks::hook_submsgs;
+use crate::native_distributions::save_native_global_index;
 use crate::state::NATIVE_GLOBAL_INDICES;
-use crate::state::TOTAL_WEIGHT;
+use crate::state::{DISTRIBUTION_SEQ, TOTAL_WEIGHT};
 use common::cw::Context;
-use cosmwasm_std::{Decimal, Response, Uint128};
+use cosmwasm_std::{Decimal, Response, StdResult, Uint128};
+use funds_distributor_api::api::{DistributionHookMsg, FundsDistributorHookMsg};
 use funds_distributor_api::error::DistributorError::ZeroTotalWeight;
 use funds_distributor_api::error::DistributorResult;
 use std::ops::Add;
 
 /// Distributes new rewards for a native asset, using funds found in MessageInfo.
 /// Will increase global index for each of the assets being distributed.
+/// Protocol fees are taken off the funds before distributing them.
+/// Funds that are not allowed to be distributed are refunded to the sender.
 pub fn distribute_native(ctx: &mut Context) -> DistributorResult<Response> {
-    let funds = ctx.info.funds.clone();
+    assert_authorized_distributor(ctx.deps.as_ref(), &ctx.info.sender)?;
+
+    let (funds, rejected_funds) =
+        split_distribution_funds(ctx.deps.as_ref(), ctx.info.funds.clone())?;
 
     let total_weight = TOTAL_WEIGHT.load(ctx.deps.storage)?;
     if total_weight == Uint128::zero() {
         return Err(ZeroTotalWeight);
     }
 
-    for fund in funds {
+    let (funds, protocol_fees) = take_protocol_fees(ctx.deps.storage, funds)?;
+
+    DISTRIBUTION_SEQ.update(ctx.deps.storage, |distribution_seq| -> StdResult<_> {
+        Ok(distribution_seq + 1)
+    })?;
+
+    for fund in funds.clone() {
         let global_index = NATIVE_GLOBAL_INDICES
             .may_load(ctx.deps.storage, fund.denom.clone())?
             .unwrap_or(Decimal::zero());
@@ -25,14 +42,30 @@ pub fn distribute_native(ctx: &mut Context) -> DistributorResult<Response> {
         // and add that to the global index for the asset
         let index_increment = Decimal::from_ratio(fund.amount, total_weight);
 
-        NATIVE_GLOBAL_INDICES.save(
+        save_native_global_index(
             ctx.deps.storage,
             fund.denom,
-            &global_index.add(index_increment),
+            global_index.add(index_increment),
         )?;
     }
 
-    Ok(Response::new()
+    let hook_submsgs = hook_submsgs(
+        ctx.deps.as_ref(),
+        FundsDistributorHookMsg::DistributionHook(DistributionHookMsg {
+            distributor: ctx.info.sender.to_string(),
+            funds,
+            total_weight,
+            snapshot_height: None,
+            group_id: None,
+            eligible_member_count: None,
+        }),
+    )?;
+
+    let response = Response::new()
         .add_attribute("action", "distribute_native")
-        .add_attribute("total_weight", total_weight.to_string()))
+        .add_attribute("total_weight", total_weight.to_string())
+        .add_submessages(hook_submsgs);
+    let response = add_protocol_fees(ctx.deps.storage, response, protocol_fees)?;
+
+    Ok(add_refund(response, &ctx.info.sender, rejected_funds))
 }
@@ -0,0 +1,56 @@
+use crate::state::SQUAD_CONTRACT;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, Deps, Order, Response, StdResult};
+use cw_storage_plus::Map;
+use funds_distributor_api::api::{AuthorizedDistributorsResponse, UpdateAuthorizedDistributorsMsg};
+use funds_distributor_api::error::DistributorError::Unauthorized;
+use funds_distributor_api::error::DistributorResult;
+
+/// Addresses allowed to fund distributions. Anyone can fund distributions if empty.
+pub const AUTHORIZED_DISTRIBUTORS: Map<Addr, ()> = Map::new("authorized_distributors");
+
+pub fn update_authorized_distributors(
+    ctx: &mut Context,
+    msg: UpdateAuthorizedDistributorsMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    for distributor in msg.remove {
+        let distributor = ctx.deps.api.addr_validate(&distributor)?;
+        AUTHORIZED_DISTRIBUTORS.remove(ctx.deps.storage, distributor);
+    }
+
+    for distributor in msg.add {
+        let distributor = ctx.deps.api.addr_validate(&distributor)?;
+        AUTHORIZED_DISTRIBUTORS.save(ctx.deps.storage, distributor, &())?;
+    }
+
+    Ok(Response::new().add_attribute("action", "update_authorized_distributors"))
+}
+
+/// Ensures that the sender can fund distributions, i.e. that either no authorized distributors
+/// are configured, or the sender is one of them.
+pub fn assert_authorized_distributor(deps: Deps, sender: &Addr) -> DistributorResult<()> {
+    if AUTHORIZED_DISTRIBUTORS.is_empty(deps.storage)
+        || AUTHORIZED_DISTRIBUTORS.has(deps.storage, sender.clone())
+    {
+        Ok(())
+    } else {
+        Err(Unauthorized)
+    }
+}
+
+pub fn query_authorized_distributors(
+    qctx: QueryContext,
+) -> DistributorResult<AuthorizedDistributorsResponse> {
+    let distributors = AUTHORIZED_DISTRIBUTORS
+        .keys(qctx.deps.storage, None, None, Order::Ascending)
+        .map(|distributor| distributor.map(|distributor| distributor.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(AuthorizedDistributorsResponse { distributors })
+}
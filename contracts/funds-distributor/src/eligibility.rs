@@ -1,18 +1,35 @@
+use crate::equal_distributions::update_user_member_eligibility;
+use crate::groups::update_user_group_weights;
 use crate::native_distributions::update_user_native_distributions;
 use crate::state::{SQUAD_CONTRACT, TOTAL_WEIGHT};
+use crate::tiers::{update_user_tier, REWARD_TIERS, REWARD_TIERS_UPDATE};
 use crate::user_weights::{EFFECTIVE_USER_WEIGHTS, USER_WEIGHTS};
 use common::cw::{Context, QueryContext};
-use cosmwasm_std::{Addr, DepsMut, Order, Response, StdResult, Uint128};
+use cosmwasm_std::{Addr, DepsMut, Order, Response, StdResult, Storage, Uint128};
 use cw_storage_plus::Item;
 use funds_distributor_api::api::{MinimumEligibleWeightResponse, UpdateMinimumEligibleWeightMsg};
-use funds_distributor_api::error::DistributorError::Unauthorized;
+use funds_distributor_api::error::DistributorError::{
+    InvalidRewardTiers, RewardTiersUpdateInProgress, Unauthorized,
+};
 use funds_distributor_api::error::DistributorResult;
 use itertools::Itertools;
 use std::ops::Range;
 
-/// Minimum weight that a user should have to be eligible for receiving rewards.
+/// Minimum weight that a user should have to be eligible for receiving rewards, as stored before
+/// it became the lowest reward tier's minimum weight. Only kept for migrating older state.
 pub const MINIMUM_ELIGIBLE_WEIGHT: Item<Uint128> = Item::new("minimum_eligible_weight");
 
+/// Minimum weight that a user should have to be eligible for receiving rewards, which is the
+/// minimum weight of the lowest reward tier.
+pub fn load_minimum_eligible_weight(storage: &dyn Storage) -> StdResult<Uint128> {
+    let tiers = REWARD_TIERS.load(storage)?;
+
+    Ok(tiers
+        .first()
+        .map(|tier| tier.min_weight)
+        .unwrap_or_default())
+}
+
 pub fn execute_update_minimum_eligible_weight(
     ctx: &mut Context,
     msg: UpdateMinimumEligibleWeightMsg,
@@ -23,10 +40,37 @@ pub fn execute_update_minimum_eligible_weight(
         return Err(Unauthorized);
     }
 
-    let old_minimum_weight = MINIMUM_ELIGIBLE_WEIGHT.load(ctx.deps.storage)?;
+    // the update compares the stored tiers with the ones it is resent with
+    if REWARD_TIERS_UPDATE.exists(ctx.deps.storage) {
+        return Err(RewardTiersUpdateInProgress);
+    }
+
+    let mut tiers = REWARD_TIERS.load(ctx.deps.storage)?;
+
+    let old_minimum_weight = load_minimum_eligible_weight(ctx.deps.storage)?;
     let new_minimum_weight = msg.minimum_eligible_weight;
 
-    update_minimum_eligible_weight(ctx.deps.branch(), old_minimum_weight, new_minimum_weight)?;
+    if tiers
+        .get(1)
+        .is_some_and(|next_tier| new_minimum_weight >= next_tier.min_weight)
+    {
+        return Err(InvalidRewardTiers {
+            reason: "minimum eligible weight must be under the second tier's minimum weight"
+                .to_string(),
+        });
+    }
+
+    if let Some(lowest_tier) = tiers.first_mut() {
+        lowest_tier.min_weight = new_minimum_weight;
+    }
+    REWARD_TIERS.save(ctx.deps.storage, &tiers)?;
+
+    update_minimum_eligible_weight(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        old_minimum_weight,
+        new_minimum_weight,
+    )?;
 
     Ok(Response::new()
         .add_attribute("action", "update_minimum_eligible_weight")
@@ -37,9 +81,13 @@ pub fn execute_update_minimum_eligible_weight(
 /// Update minimum eligible weight for rewards by going through all the users
 /// between the old and the new minimum and updating their effective weight (to either their
 /// actual weight, or 0, depending on whether they're above or below the new minimum).
+///
+/// The new minimum has to already be stored as the lowest reward tier's minimum weight, so that
+/// the users are moved to their tiers under it.
 // TODO: the name is very similar to the above, but this does not check for unauthorized use; reveal this through the name somehow
 pub fn update_minimum_eligible_weight(
     mut deps: DepsMut,
+    height: u64,
     old_minimum_weight: Uint128,
     new_minimum_weight: Uint128,
 ) -> DistributorResult<()> {
@@ -98,15 +146,34 @@ pub fn update_minimum_eligible_weight(
         };
 
         // change user's effective weight to account for the change in effective weight
-        EFFECTIVE_USER_WEIGHTS.save(deps.storage, user, &new_effective_weight)?;
+        EFFECTIVE_USER_WEIGHTS.save(deps.storage, user.clone(), &new_effective_weight, height)?;
+
+        update_user_group_weights(
+            deps.branch(),
+            user.clone(),
+            old_effective_weight,
+            new_effective_weight,
+        )?;
+
+        update_user_member_eligibility(
+            deps.branch(),
+            user.clone(),
+            old_effective_weight,
+            new_effective_weight,
+        )?;
+
+        update_user_tier(
+            deps.branch(),
+            user,
+            old_effective_weight,
+            new_effective_weight,
+        )?;
 
         // update total weight
         total_weight = total_weight - old_effective_weight + new_effective_weight;
     }
 
-    MINIMUM_ELIGIBLE_WEIGHT.save(deps.storage, &new_minimum_weight)?;
-
-    TOTAL_WEIGHT.save(deps.storage, &total_weight)?;
+    TOTAL_WEIGHT.save(deps.storage, &total_weight, height)?;
 
     Ok(())
 }
@@ -114,7 +181,7 @@ pub fn update_minimum_eligible_weight(
 pub fn query_minimum_eligible_weight(
     qctx: QueryContext,
 ) -> DistributorResult<MinimumEligibleWeightResponse> {
-    let minimum_eligible_weight = MINIMUM_ELIGIBLE_WEIGHT.load(qctx.deps.storage)?;
+    let minimum_eligible_weight = load_minimum_eligible_weight(qctx.deps.storage)?;
 
     Ok(MinimumEligibleWeightResponse {
         minimum_eligible_weight,
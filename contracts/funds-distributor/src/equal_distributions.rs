@@ -0,0 +1,223 @@
+use crate::denoms::{add_refund, split_distribution_funds};
+use crate::distributors::assert_authorized_distributor;
+use crate::hooks::hook_submsgs;
+use crate::native_distributions::{add_user_pending_rewards, ensure_native_denom_known};
+use crate::rewards::calculate_new_user_reward;
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, DepsMut, Order, Response, StdResult, Uint128};
+use cw_storage_plus::{Item, Map};
+use funds_distributor_api::api::{
+    DistributionHookMsg, EligibleMemberCountResponse, FundsDistributorHookMsg,
+};
+use funds_distributor_api::error::DistributorError::NoEligibleMembers;
+use funds_distributor_api::error::DistributorResult;
+use std::ops::Add;
+
+/// Number of users with a non-zero effective weight.
+pub const ELIGIBLE_MEMBER_COUNT: Item<u64> = Item::new("eligible_member_count");
+
+/// Amount of each native asset distributed per eligible member so far.
+pub const NATIVE_PER_MEMBER_INDICES: Map<String, Decimal> = Map::new("native_per_member_indices");
+
+/// The last per-member index at which the user's equal split rewards were moved into their
+/// pending rewards, keyed by (user, denom).
+pub const USER_PER_MEMBER_INDICES: Map<(Addr, String), Decimal> =
+    Map::new("user_per_member_indices");
+
+/// Distributes new rewards for native assets, using funds found in MessageInfo, in equal
+/// amounts to each user eligible for rewards, regardless of their weights.
+/// Will increase per-member index for each of the assets being distributed.
+pub fn distribute_native_equally(ctx: &mut Context) -> DistributorResult<Response> {
+    assert_authorized_distributor(ctx.deps.as_ref(), &ctx.info.sender)?;
+
+    let (funds, rejected_funds) =
+        split_distribution_funds(ctx.deps.as_ref(), ctx.info.funds.clone())?;
+
+    let member_count = ELIGIBLE_MEMBER_COUNT
+        .may_load(ctx.deps.storage)?
+        .unwrap_or_default();
+    if member_count == 0 {
+        return Err(NoEligibleMembers);
+    }
+
+    for fund in funds.clone() {
+        let per_member_index = NATIVE_PER_MEMBER_INDICES
+            .may_load(ctx.deps.storage, fund.denom.clone())?
+            .unwrap_or_default();
+
+        let index_increment = Decimal::from_ratio(fund.amount, member_count);
+
+        NATIVE_PER_MEMBER_INDICES.save(
+            ctx.deps.storage,
+            fund.denom.clone(),
+            &per_member_index.add(index_increment),
+        )?;
+
+        ensure_native_denom_known(ctx.deps.storage, &fund.denom)?;
+    }
+
+    let hook_submsgs = hook_submsgs(
+        ctx.deps.as_ref(),
+        FundsDistributorHookMsg::DistributionHook(DistributionHookMsg {
+            distributor: ctx.info.sender.to_string(),
+            funds,
+            total_weight: Uint128::zero(),
+            snapshot_height: None,
+            group_id: None,
+            eligible_member_count: Some(member_count),
+        }),
+    )?;
+
+    let response = Response::new()
+        .add_attribute("action", "distribute_native_equally")
+        .add_attribute("eligible_member_count", member_count.to_string())
+        .add_submessages(hook_submsgs);
+
+    Ok(add_refund(response, &ctx.info.sender, rejected_funds))
+}
+
+/// Updates the eligible member count to account for a change in the user's effective weight.
+///
+/// If the user's eligibility changes, their equal split rewards accrued until now are first
+/// moved into their pending rewards.
+pub fn update_user_member_eligibility(
+    mut deps: DepsMut,
+    user: Addr,
+    old_user_weight: Uint128,
+    new_user_weight: Uint128,
+) -> DistributorResult<()> {
+    let was_eligible = !old_user_weight.is_zero();
+    let is_eligible = !new_user_weight.is_zero();
+
+    if was_eligible == is_eligible {
+        return Ok(());
+    }
+
+    settle_per_member_rewards(deps.branch(), user, was_eligible)?;
+
+    ELIGIBLE_MEMBER_COUNT.update(deps.storage, |member_count| -> StdResult<_> {
+        Ok(if is_eligible {
+            member_count + 1
+        } else {
+            member_count - 1
+        })
+    })?;
+
+    Ok(())
+}
+
+/// Moves the user's equal split rewards accrued until now into their pending rewards.
+pub fn settle_user_per_member_rewards(deps: DepsMut, user: Addr) -> DistributorResult<()> {
+    let is_eligible = is_eligible(deps.as_ref(), &user)?;
+
+    settle_per_member_rewards(deps, user, is_eligible)
+}
+
+/// Calculates the user's equal split rewards that were not yet moved into their pending rewards.
+pub fn unsettled_per_member_rewards(deps: Deps, user: &Addr) -> DistributorResult<Vec<Coin>> {
+    let is_eligible = is_eligible(deps, user)?;
+
+    let rewards = per_member_rewards(deps, user, is_eligible)?
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect();
+
+    Ok(rewards)
+}
+
+/// Counts the users with a non-zero effective weight.
+///
+/// Used when the count is not tracked yet, e.g. when migrating from older versions.
+pub fn initialize_eligible_member_count(deps: DepsMut) -> DistributorResult<()> {
+    if ELIGIBLE_MEMBER_COUNT.exists(deps.storage) {
+        return Ok(());
+    }
+
+    let mut member_count = 0u64;
+
+    for user_weight in EFFECTIVE_USER_WEIGHTS.range(deps.storage, None, None, Order::Ascending) {
+        let (_, weight) = user_weight?;
+        if !weight.is_zero() {
+            member_count += 1;
+        }
+    }
+
+    ELIGIBLE_MEMBER_COUNT.save(deps.storage, &member_count)?;
+
+    Ok(())
+}
+
+/// Moves the user's equal split rewards accrued until now into their pending rewards, and brings
+/// their per-member indices up to date.
+fn settle_per_member_rewards(
+    mut deps: DepsMut,
+    user: Addr,
+    is_eligible: bool,
+) -> DistributorResult<()> {
+    for (denom, reward) in per_member_rewards(deps.as_ref(), &user, is_eligible)? {
+        if !reward.is_zero() {
+            add_user_pending_rewards(deps.branch(), user.clone(), denom, reward)?;
+        }
+    }
+
+    let per_member_indices = NATIVE_PER_MEMBER_INDICES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, Decimal)>>>()?;
+
+    for (denom, per_member_index) in per_member_indices {
+        USER_PER_MEMBER_INDICES.save(deps.storage, (user.clone(), denom), &per_member_index)?;
+    }
+
+    Ok(())
+}
+
+/// Calculates the user's equal split rewards for each denom, accrued since the last update to
+/// their per-member indices.
+fn per_member_rewards(
+    deps: Deps,
+    user: &Addr,
+    is_eligible: bool,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let share = if is_eligible {
+        Uint128::one()
+    } else {
+        Uint128::zero()
+    };
+
+    NATIVE_PER_MEMBER_INDICES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|per_member_index| {
+            let (denom, per_member_index) = per_member_index?;
+
+            let user_index = USER_PER_MEMBER_INDICES
+                .may_load(deps.storage, (user.clone(), denom.clone()))?
+                .unwrap_or_default();
+
+            let reward = calculate_new_user_reward(per_member_index, user_index, share);
+
+            Ok((denom, reward))
+        })
+        .collect()
+}
+
+fn is_eligible(deps: Deps, user: &Addr) -> StdResult<bool> {
+    let weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    Ok(!weight.is_zero())
+}
+
+pub fn query_eligible_member_count(
+    qctx: QueryContext,
+) -> DistributorResult<EligibleMemberCountResponse> {
+    let eligible_member_count = ELIGIBLE_MEMBER_COUNT
+        .may_load(qctx.deps.storage)?
+        .unwrap_or_default();
+
+    Ok(EligibleMemberCountResponse {
+        eligible_member_count,
+    })
+}
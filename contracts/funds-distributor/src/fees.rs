@@ -0,0 +1,166 @@
+use crate::state::SQUAD_CONTRACT;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, BankMsg, Coin, Order, Response, StdResult, Storage, Uint128};
+use cw_storage_plus::{Item, Map};
+use funds_distributor_api::api::{
+    DenomDistributionStats, DistributionStatsParams, DistributionStatsResponse, ProtocolFee,
+    ProtocolFeesResponse, UpdateProtocolFeesMsg,
+};
+use funds_distributor_api::error::DistributorError::{
+    FeeCollectorNotSet, InvalidProtocolFee, Unauthorized,
+};
+use funds_distributor_api::error::DistributorResult;
+
+const MAX_FEE_BPS: u16 = 10_000;
+
+/// Address that receives the protocol fees.
+pub const FEE_COLLECTOR: Item<Addr> = Item::new("fee_collector");
+
+/// Protocol fees in basis points, per denom. Denoms not in the map are not charged a fee.
+pub const PROTOCOL_FEES: Map<String, u16> = Map::new("protocol_fees");
+
+/// Amounts distributed through `DistributeNative` and taken as protocol fees, per denom.
+pub const DISTRIBUTION_STATS: Map<String, (Uint128, Uint128)> = Map::new("distribution_stats");
+
+pub fn update_protocol_fees(
+    ctx: &mut Context,
+    msg: UpdateProtocolFeesMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    if let Some(fee_collector) = msg.fee_collector {
+        let fee_collector = ctx.deps.api.addr_validate(&fee_collector)?;
+        FEE_COLLECTOR.save(ctx.deps.storage, &fee_collector)?;
+    }
+
+    for denom in msg.remove {
+        PROTOCOL_FEES.remove(ctx.deps.storage, denom);
+    }
+
+    for fee in msg.set {
+        if fee.fee_bps > MAX_FEE_BPS {
+            return Err(InvalidProtocolFee {
+                fee_bps: fee.fee_bps,
+            });
+        }
+        PROTOCOL_FEES.save(ctx.deps.storage, fee.denom, &fee.fee_bps)?;
+    }
+
+    if !PROTOCOL_FEES.is_empty(ctx.deps.storage) && !FEE_COLLECTOR.exists(ctx.deps.storage) {
+        return Err(FeeCollectorNotSet);
+    }
+
+    Ok(Response::new().add_attribute("action", "update_protocol_fees"))
+}
+
+/// Skims the protocol fees off the funds being distributed, and records both parts in the
+/// distribution stats.
+/// Returns the funds left to distribute, and the fees to be sent to the fee collector.
+pub fn take_protocol_fees(
+    storage: &mut dyn Storage,
+    funds: Vec<Coin>,
+) -> DistributorResult<(Vec<Coin>, Vec<Coin>)> {
+    let mut remaining_funds: Vec<Coin> = vec![];
+    let mut fees: Vec<Coin> = vec![];
+
+    for fund in funds {
+        let fee_bps = PROTOCOL_FEES
+            .may_load(storage, fund.denom.clone())?
+            .unwrap_or_default();
+
+        let fee = fund.amount.multiply_ratio(fee_bps, MAX_FEE_BPS);
+        let remaining = fund.amount - fee;
+
+        DISTRIBUTION_STATS.update(storage, fund.denom.clone(), |stats| -> StdResult<_> {
+            let (distributed, protocol_fees) = stats.unwrap_or_default();
+            Ok((distributed + remaining, protocol_fees + fee))
+        })?;
+
+        if !fee.is_zero() {
+            fees.push(Coin::new(fee, fund.denom.clone()));
+        }
+        if !remaining.is_zero() {
+            remaining_funds.push(Coin::new(remaining, fund.denom));
+        }
+    }
+
+    Ok((remaining_funds, fees))
+}
+
+/// Adds a message sending the protocol fees to the fee collector, if there are any.
+pub fn add_protocol_fees(
+    storage: &dyn Storage,
+    response: Response,
+    fees: Vec<Coin>,
+) -> DistributorResult<Response> {
+    if fees.is_empty() {
+        return Ok(response);
+    }
+
+    let fee_collector = FEE_COLLECTOR.load(storage)?;
+
+    let protocol_fees = fees
+        .iter()
+        .map(|coin| coin.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+
+    Ok(response
+        .add_attribute("protocol_fees", protocol_fees)
+        .add_attribute("fee_collector", fee_collector.to_string())
+        .add_message(BankMsg::Send {
+            to_address: fee_collector.to_string(),
+            amount: fees,
+        }))
+}
+
+pub fn query_protocol_fees(qctx: QueryContext) -> DistributorResult<ProtocolFeesResponse> {
+    let fee_collector = FEE_COLLECTOR
+        .may_load(qctx.deps.storage)?
+        .map(|fee_collector| fee_collector.to_string());
+
+    let fees = PROTOCOL_FEES
+        .range(qctx.deps.storage, None, None, Order::Ascending)
+        .map(|fee| fee.map(|(denom, fee_bps)| ProtocolFee { denom, fee_bps }))
+        .collect::<StdResult<Vec<ProtocolFee>>>()?;
+
+    Ok(ProtocolFeesResponse {
+        fee_collector,
+        fees,
+    })
+}
+
+pub fn query_distribution_stats(
+    qctx: QueryContext,
+    params: DistributionStatsParams,
+) -> DistributorResult<DistributionStatsResponse> {
+    let denoms = params.native_denoms.map_or_else(
+        || {
+            DISTRIBUTION_STATS
+                .keys(qctx.deps.storage, None, None, Order::Ascending)
+                .collect::<StdResult<Vec<String>>>()
+        },
+        Ok,
+    )?;
+
+    let stats = denoms
+        .into_iter()
+        .map(|denom| {
+            let (distributed, protocol_fees) = DISTRIBUTION_STATS
+                .may_load(qctx.deps.storage, denom.clone())?
+                .unwrap_or_default();
+
+            Ok(DenomDistributionStats {
+                denom,
+                distributed,
+                protocol_fees,
+            })
+        })
+        .collect::<StdResult<Vec<DenomDistributionStats>>>()?;
+
+    Ok(DistributionStatsResponse { stats })
+}
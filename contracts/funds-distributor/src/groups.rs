@@ -0,0 +1,333 @@
+use crate::denoms::{add_refund, split_distribution_funds};
+use crate::distributors::assert_authorized_distributor;
+use crate::hooks::hook_submsgs;
+use crate::native_distributions::{add_user_pending_rewards, ensure_native_denom_known};
+use crate::rewards::calculate_new_user_reward;
+use crate::state::SQUAD_CONTRACT;
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, DepsMut, Order, Response, StdResult, Uint128};
+use cw_storage_plus::{Bound, Map};
+use funds_distributor_api::api::{
+    DistributionHookMsg, FundsDistributorHookMsg, GroupMembersParams, GroupMembersResponse,
+    UpdateGroupMembersMsg,
+};
+use funds_distributor_api::error::DistributorError::{Unauthorized, ZeroTotalWeight};
+use funds_distributor_api::error::DistributorResult;
+use std::ops::Add;
+
+const DEFAULT_QUERY_LIMIT: u32 = 10;
+const MAX_QUERY_LIMIT: u32 = 30;
+
+/// Members of the admin-defined groups, keyed by (group ID, user).
+pub const GROUP_MEMBERS: Map<(String, Addr), ()> = Map::new("group_members");
+
+/// Groups that each user is a member of, keyed by (user, group ID).
+pub const USER_GROUPS: Map<(Addr, String), ()> = Map::new("user_groups");
+
+/// Total effective weight of each group's members.
+pub const GROUP_TOTAL_WEIGHTS: Map<String, Uint128> = Map::new("group_total_weights");
+
+/// Global indices of the distributions to each group, keyed by (group ID, denom).
+pub const GROUP_GLOBAL_INDICES: Map<(String, String), Decimal> = Map::new("group_global_indices");
+
+/// The last group global index at which the member's group rewards were moved into their pending
+/// rewards, keyed by (group ID, user, denom).
+pub const GROUP_USER_INDICES: Map<(String, Addr, String), Decimal> = Map::new("group_user_indices");
+
+/// Adds and removes members of a group, creating the group if it doesn't exist yet.
+pub fn update_group_members(
+    ctx: &mut Context,
+    msg: UpdateGroupMembersMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let group_id = msg.group_id;
+
+    let mut total_weight = GROUP_TOTAL_WEIGHTS
+        .may_load(ctx.deps.storage, group_id.clone())?
+        .unwrap_or_default();
+
+    for user in msg.remove {
+        let user = ctx.deps.api.addr_validate(&user)?;
+
+        if !GROUP_MEMBERS.has(ctx.deps.storage, (group_id.clone(), user.clone())) {
+            continue;
+        }
+
+        let user_weight = load_effective_weight(ctx.deps.as_ref(), &user)?;
+
+        settle_group_rewards(ctx.deps.branch(), &group_id, user.clone(), user_weight)?;
+
+        let denoms = GROUP_USER_INDICES
+            .prefix((group_id.clone(), user.clone()))
+            .keys(ctx.deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<String>>>()?;
+        for denom in denoms {
+            GROUP_USER_INDICES.remove(ctx.deps.storage, (group_id.clone(), user.clone(), denom));
+        }
+
+        GROUP_MEMBERS.remove(ctx.deps.storage, (group_id.clone(), user.clone()));
+        USER_GROUPS.remove(ctx.deps.storage, (user, group_id.clone()));
+
+        total_weight -= user_weight;
+    }
+
+    for user in msg.add {
+        let user = ctx.deps.api.addr_validate(&user)?;
+
+        if GROUP_MEMBERS.has(ctx.deps.storage, (group_id.clone(), user.clone())) {
+            continue;
+        }
+
+        // new members are only entitled to the group's distributions from now on
+        let group_indices = GROUP_GLOBAL_INDICES
+            .prefix(group_id.clone())
+            .range(ctx.deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(String, Decimal)>>>()?;
+        for (denom, group_index) in group_indices {
+            GROUP_USER_INDICES.save(
+                ctx.deps.storage,
+                (group_id.clone(), user.clone(), denom),
+                &group_index,
+            )?;
+        }
+
+        GROUP_MEMBERS.save(ctx.deps.storage, (group_id.clone(), user.clone()), &())?;
+        USER_GROUPS.save(ctx.deps.storage, (user.clone(), group_id.clone()), &())?;
+
+        total_weight += load_effective_weight(ctx.deps.as_ref(), &user)?;
+    }
+
+    GROUP_TOTAL_WEIGHTS.save(ctx.deps.storage, group_id.clone(), &total_weight)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_group_members")
+        .add_attribute("group_id", group_id)
+        .add_attribute("group_total_weight", total_weight.to_string()))
+}
+
+/// Distributes new rewards for native assets, using funds found in MessageInfo, only to the
+/// members of the given group, proportionally to their weights.
+pub fn distribute_native_to_group(
+    ctx: &mut Context,
+    group_id: String,
+) -> DistributorResult<Response> {
+    assert_authorized_distributor(ctx.deps.as_ref(), &ctx.info.sender)?;
+
+    let (funds, rejected_funds) =
+        split_distribution_funds(ctx.deps.as_ref(), ctx.info.funds.clone())?;
+
+    let total_weight = GROUP_TOTAL_WEIGHTS
+        .may_load(ctx.deps.storage, group_id.clone())?
+        .unwrap_or_default();
+    if total_weight.is_zero() {
+        return Err(ZeroTotalWeight);
+    }
+
+    for fund in funds.clone() {
+        let group_index = GROUP_GLOBAL_INDICES
+            .may_load(ctx.deps.storage, (group_id.clone(), fund.denom.clone()))?
+            .unwrap_or_default();
+
+        let index_increment = Decimal::from_ratio(fund.amount, total_weight);
+
+        GROUP_GLOBAL_INDICES.save(
+            ctx.deps.storage,
+            (group_id.clone(), fund.denom.clone()),
+            &group_index.add(index_increment),
+        )?;
+
+        ensure_native_denom_known(ctx.deps.storage, &fund.denom)?;
+    }
+
+    let hook_submsgs = hook_submsgs(
+        ctx.deps.as_ref(),
+        FundsDistributorHookMsg::DistributionHook(DistributionHookMsg {
+            distributor: ctx.info.sender.to_string(),
+            funds,
+            total_weight,
+            snapshot_height: None,
+            group_id: Some(group_id.clone()),
+            eligible_member_count: None,
+        }),
+    )?;
+
+    let response = Response::new()
+        .add_attribute("action", "distribute_native_to_group")
+        .add_attribute("group_id", group_id)
+        .add_attribute("total_weight", total_weight.to_string())
+        .add_submessages(hook_submsgs);
+
+    Ok(add_refund(response, &ctx.info.sender, rejected_funds))
+}
+
+/// Updates the total weights of the user's groups to account for a change in the user's
+/// effective weight.
+///
+/// Rewards accrued in the user's groups until now are first moved into their pending rewards,
+/// using their old weight.
+pub fn update_user_group_weights(
+    mut deps: DepsMut,
+    user: Addr,
+    old_user_weight: Uint128,
+    new_user_weight: Uint128,
+) -> DistributorResult<()> {
+    if old_user_weight == new_user_weight {
+        return Ok(());
+    }
+
+    for group_id in load_user_groups(deps.as_ref(), &user)? {
+        settle_group_rewards(deps.branch(), &group_id, user.clone(), old_user_weight)?;
+
+        GROUP_TOTAL_WEIGHTS.update(deps.storage, group_id, |total_weight| -> StdResult<_> {
+            Ok(total_weight.unwrap_or_default() - old_user_weight + new_user_weight)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Moves rewards accrued in all of the user's groups into their pending rewards.
+pub fn settle_user_group_rewards(mut deps: DepsMut, user: Addr) -> DistributorResult<()> {
+    let user_weight = load_effective_weight(deps.as_ref(), &user)?;
+
+    for group_id in load_user_groups(deps.as_ref(), &user)? {
+        settle_group_rewards(deps.branch(), &group_id, user.clone(), user_weight)?;
+    }
+
+    Ok(())
+}
+
+/// Calculates rewards accrued in all of the user's groups that were not yet moved into their
+/// pending rewards.
+pub fn unsettled_group_rewards(deps: Deps, user: &Addr) -> DistributorResult<Vec<Coin>> {
+    let user_weight = load_effective_weight(deps, user)?;
+
+    let mut rewards: Vec<Coin> = vec![];
+
+    for group_id in load_user_groups(deps, user)? {
+        for (denom, reward) in group_rewards(deps, &group_id, user, user_weight)? {
+            match rewards.iter_mut().find(|coin| coin.denom == denom) {
+                Some(coin) => coin.amount += reward,
+                None => rewards.push(Coin {
+                    denom,
+                    amount: reward,
+                }),
+            }
+        }
+    }
+
+    Ok(rewards)
+}
+
+fn settle_group_rewards(
+    mut deps: DepsMut,
+    group_id: &str,
+    user: Addr,
+    user_weight: Uint128,
+) -> DistributorResult<()> {
+    let group_indices = GROUP_GLOBAL_INDICES
+        .prefix(group_id.to_string())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, Decimal)>>>()?;
+
+    for (denom, reward) in group_rewards(deps.as_ref(), group_id, &user, user_weight)? {
+        if !reward.is_zero() {
+            add_user_pending_rewards(deps.branch(), user.clone(), denom, reward)?;
+        }
+    }
+
+    for (denom, group_index) in group_indices {
+        GROUP_USER_INDICES.save(
+            deps.storage,
+            (group_id.to_string(), user.clone(), denom),
+            &group_index,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Calculates the user's rewards for each denom distributed to the group, accrued since the
+/// last update to their group indices.
+fn group_rewards(
+    deps: Deps,
+    group_id: &str,
+    user: &Addr,
+    user_weight: Uint128,
+) -> StdResult<Vec<(String, Uint128)>> {
+    GROUP_GLOBAL_INDICES
+        .prefix(group_id.to_string())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|group_index| {
+            let (denom, group_index) = group_index?;
+
+            let user_index = GROUP_USER_INDICES
+                .may_load(
+                    deps.storage,
+                    (group_id.to_string(), user.clone(), denom.clone()),
+                )?
+                .unwrap_or_default();
+
+            let reward = calculate_new_user_reward(group_index, user_index, user_weight);
+
+            Ok((denom, reward))
+        })
+        .collect()
+}
+
+fn load_user_groups(deps: Deps, user: &Addr) -> StdResult<Vec<String>> {
+    USER_GROUPS
+        .prefix(user.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect()
+}
+
+fn load_effective_weight(deps: Deps, user: &Addr) -> StdResult<Uint128> {
+    let weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    Ok(weight)
+}
+
+pub fn query_group_members(
+    qctx: QueryContext,
+    params: GroupMembersParams,
+) -> DistributorResult<GroupMembersResponse> {
+    let start_after = params
+        .start_after
+        .map(|member| qctx.deps.api.addr_validate(&member))
+        .transpose()?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .min(MAX_QUERY_LIMIT);
+
+    let total_weight = GROUP_TOTAL_WEIGHTS
+        .may_load(qctx.deps.storage, params.group_id.clone())?
+        .unwrap_or_default();
+
+    let members = GROUP_MEMBERS
+        .prefix(params.group_id.clone())
+        .keys(
+            qctx.deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .map(|member| member.map(|member| member.to_string()))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(GroupMembersResponse {
+        group_id: params.group_id,
+        total_weight,
+        members,
+    })
+}
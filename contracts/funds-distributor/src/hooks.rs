@@ -0,0 +1,110 @@
+use crate::state::SQUAD_CONTRACT;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{
+    wasm_execute, Addr, Deps, Order, Reply, Response, StdResult, SubMsg, SubMsgResult,
+};
+use cw_storage_plus::Map;
+use funds_distributor_api::api::{
+    AddHookMsg, FundsDistributorHookMsg, Hook, HookFailurePolicy, HooksResponse, RemoveHookMsg,
+};
+use funds_distributor_api::error::DistributorError::{
+    HookAlreadyRegistered, HookNotRegistered, Unauthorized,
+};
+use funds_distributor_api::error::DistributorResult;
+
+/// Reply ID used for hooks whose failures should be ignored.
+pub const HOOK_REPLY_ID: u64 = 1;
+
+/// Contracts notified of distributions and claims, along with how their failures are handled.
+pub const HOOKS: Map<Addr, HookFailurePolicy> = Map::new("hooks");
+
+pub fn add_hook(ctx: &mut Context, msg: AddHookMsg) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let addr = ctx.deps.api.addr_validate(&msg.addr)?;
+
+    if HOOKS.has(ctx.deps.storage, addr.clone()) {
+        return Err(HookAlreadyRegistered {
+            addr: addr.to_string(),
+        });
+    }
+
+    HOOKS.save(ctx.deps.storage, addr.clone(), &msg.on_failure)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_hook")
+        .add_attribute("hook", addr.to_string()))
+}
+
+pub fn remove_hook(ctx: &mut Context, msg: RemoveHookMsg) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let addr = ctx.deps.api.addr_validate(&msg.addr)?;
+
+    if !HOOKS.has(ctx.deps.storage, addr.clone()) {
+        return Err(HookNotRegistered {
+            addr: addr.to_string(),
+        });
+    }
+
+    HOOKS.remove(ctx.deps.storage, addr.clone());
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_hook")
+        .add_attribute("hook", addr.to_string()))
+}
+
+/// Creates submessages sending the given hook message to all the registered hooks.
+///
+/// Hooks whose failures should be ignored are sent with a reply on error, so that their
+/// failure does not revert the whole transaction.
+pub fn hook_submsgs(deps: Deps, msg: FundsDistributorHookMsg) -> DistributorResult<Vec<SubMsg>> {
+    HOOKS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|hook| {
+            let (addr, on_failure) = hook?;
+            let execute_msg = wasm_execute(addr, &msg, vec![])?;
+
+            let submsg = match on_failure {
+                HookFailurePolicy::Abort => SubMsg::new(execute_msg),
+                HookFailurePolicy::Ignore => SubMsg::reply_on_error(execute_msg, HOOK_REPLY_ID),
+            };
+
+            Ok(submsg)
+        })
+        .collect()
+}
+
+/// Handles the reply of a failed hook whose failures should be ignored.
+pub fn reply_hook_failed(msg: Reply) -> DistributorResult<Response> {
+    let error = match msg.result {
+        SubMsgResult::Ok(_) => "".to_string(),
+        SubMsgResult::Err(error) => error,
+    };
+
+    Ok(Response::new()
+        .add_attribute("action", "hook_failed")
+        .add_attribute("error", error))
+}
+
+pub fn query_hooks(qctx: QueryContext) -> DistributorResult<HooksResponse> {
+    let hooks = HOOKS
+        .range(qctx.deps.storage, None, None, Order::Ascending)
+        .map(|hook| {
+            hook.map(|(addr, on_failure)| Hook {
+                addr: addr.to_string(),
+                on_failure,
+            })
+        })
+        .collect::<StdResult<Vec<Hook>>>()?;
+
+    Ok(HooksResponse { hooks })
+}
@@ -1,13 +1,30 @@
 extern crate core;
 
 mod claim;
+mod compounding;
 pub mod contract;
+mod delegates;
+mod denoms;
 mod distributing;
+mod distributors;
 mod eligibility;
+mod equal_distributions;
+mod fees;
+mod groups;
+mod hooks;
+mod merkle_distributions;
+mod migrations;
 mod native_distributions;
+mod operators;
+mod reward_splits;
 mod rewards;
+mod snapshot_distributions;
+mod stake_weights;
 mod state;
+mod state_transfer;
+mod tiers;
 mod user_weights;
+mod weight_sources;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,240 @@
+use crate::operators::load_reward_recipient;
+use crate::state::SQUAD_CONTRACT;
+use common::cw::{Context, QueryContext};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{coins, BankMsg, Coin, Response, Uint128};
+use cw_storage_plus::{Item, Map};
+use cw_utils::Expiration;
+use funds_distributor_api::api::{
+    ClaimMerkleMsg, MerkleClaimedParams, MerkleClaimedResponse, MerkleDistributionParams,
+    MerkleDistributionResponse, RegisterMerkleDistributionMsg, SweepMerkleDistributionMsg,
+};
+use funds_distributor_api::error::DistributorError::{
+    InvalidExpiration, InvalidMerkleDistributionFunds, InvalidMerkleProof, InvalidMerkleRoot,
+    MerkleAlreadyClaimed, MerkleDistributionExceeded, MerkleDistributionExpired,
+    MerkleDistributionNotExpired, MerkleDistributionNotFound, MerkleDistributionSwept,
+    Unauthorized,
+};
+use funds_distributor_api::error::DistributorResult;
+use funds_distributor_api::merkle::{decode_hash, leaf_hash, root_from_proof, Hash};
+
+/// One-off distribution of a native asset, where each recipient's amount is proven against a
+/// Merkle root instead of being derived from the users' weights.
+#[cw_serde]
+pub struct MerkleDistribution {
+    pub root: String,
+    pub denom: String,
+    pub total: Uint128,
+    /// Total amount claimed so far
+    pub claimed: Uint128,
+    pub expiry: Expiration,
+    pub swept: bool,
+}
+
+/// ID of the last registered Merkle distribution.
+pub const MERKLE_DISTRIBUTION_ID: Item<u64> = Item::new("merkle_distribution_id");
+
+pub const MERKLE_DISTRIBUTIONS: Map<u64, MerkleDistribution> = Map::new("merkle_distributions");
+
+/// Bitmap of claimed entries, keyed by (distribution ID, index / 64), with each bit marking
+/// whether the entry with the given index was claimed.
+pub const MERKLE_CLAIMED_BITMAP: Map<(u64, u32), u64> = Map::new("merkle_claimed_bitmap");
+
+pub fn register_merkle_distribution(
+    ctx: &mut Context,
+    msg: RegisterMerkleDistributionMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    if decode_hash(&msg.root).is_none() {
+        return Err(InvalidMerkleRoot);
+    }
+
+    if msg.expiry.is_expired(&ctx.env.block) {
+        return Err(InvalidExpiration);
+    }
+
+    // the distribution has to be funded with exactly its total
+    let expected_funds = coins(msg.total.u128(), msg.denom.clone());
+    if msg.total.is_zero() || ctx.info.funds != expected_funds {
+        return Err(InvalidMerkleDistributionFunds {
+            denom: msg.denom,
+            total: msg.total,
+        });
+    }
+
+    let id = MERKLE_DISTRIBUTION_ID
+        .may_load(ctx.deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    MERKLE_DISTRIBUTION_ID.save(ctx.deps.storage, &id)?;
+
+    MERKLE_DISTRIBUTIONS.save(
+        ctx.deps.storage,
+        id,
+        &MerkleDistribution {
+            root: msg.root.to_lowercase(),
+            denom: msg.denom.clone(),
+            total: msg.total,
+            claimed: Uint128::zero(),
+            expiry: msg.expiry,
+            swept: false,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_merkle_distribution")
+        .add_attribute("merkle_distribution_id", id.to_string())
+        .add_attribute("denom", msg.denom)
+        .add_attribute("total", msg.total.to_string()))
+}
+
+/// Claims the sender's entry of a Merkle distribution, sending it to the sender's reward
+/// recipient.
+pub fn claim_merkle(ctx: &mut Context, msg: ClaimMerkleMsg) -> DistributorResult<Response> {
+    let mut distribution = load_merkle_distribution(ctx, msg.id)?;
+
+    if distribution.expiry.is_expired(&ctx.env.block) {
+        return Err(MerkleDistributionExpired { id: msg.id });
+    }
+
+    let (word, bit) = bitmap_position(msg.index);
+    let claimed_bits = MERKLE_CLAIMED_BITMAP
+        .may_load(ctx.deps.storage, (msg.id, word))?
+        .unwrap_or_default();
+
+    if claimed_bits & bit != 0 {
+        return Err(MerkleAlreadyClaimed {
+            id: msg.id,
+            index: msg.index,
+        });
+    }
+
+    let proof = msg
+        .proof
+        .iter()
+        .map(|hash| decode_hash(hash))
+        .collect::<Option<Vec<Hash>>>()
+        .ok_or(InvalidMerkleProof)?;
+
+    let leaf = leaf_hash(msg.index, ctx.info.sender.as_str(), msg.amount);
+    let root = root_from_proof(leaf, msg.index, &proof);
+
+    if decode_hash(&distribution.root) != Some(root) {
+        return Err(InvalidMerkleProof);
+    }
+
+    distribution.claimed += msg.amount;
+    if distribution.claimed > distribution.total {
+        return Err(MerkleDistributionExceeded { id: msg.id });
+    }
+
+    MERKLE_CLAIMED_BITMAP.save(ctx.deps.storage, (msg.id, word), &(claimed_bits | bit))?;
+    MERKLE_DISTRIBUTIONS.save(ctx.deps.storage, msg.id, &distribution)?;
+
+    let recipient = load_reward_recipient(ctx, &ctx.info.sender)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "claim_merkle")
+        .add_attribute("merkle_distribution_id", msg.id.to_string())
+        .add_attribute("user", ctx.info.sender.to_string())
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("amount", msg.amount.to_string())
+        .add_message(BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: coins(msg.amount.u128(), distribution.denom),
+        }))
+}
+
+/// Sends the unclaimed funds of an expired Merkle distribution to the admin.
+pub fn sweep_merkle_distribution(
+    ctx: &mut Context,
+    msg: SweepMerkleDistributionMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let mut distribution = load_merkle_distribution(ctx, msg.id)?;
+
+    if !distribution.expiry.is_expired(&ctx.env.block) {
+        return Err(MerkleDistributionNotExpired { id: msg.id });
+    }
+
+    if distribution.swept {
+        return Err(MerkleDistributionSwept { id: msg.id });
+    }
+
+    distribution.swept = true;
+    MERKLE_DISTRIBUTIONS.save(ctx.deps.storage, msg.id, &distribution)?;
+
+    let unclaimed = distribution.total - distribution.claimed;
+
+    let mut response = Response::new()
+        .add_attribute("action", "sweep_merkle_distribution")
+        .add_attribute("merkle_distribution_id", msg.id.to_string())
+        .add_attribute("unclaimed", unclaimed.to_string());
+
+    if !unclaimed.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: squad_contract.to_string(),
+            amount: vec![Coin {
+                denom: distribution.denom,
+                amount: unclaimed,
+            }],
+        });
+    }
+
+    Ok(response)
+}
+
+fn load_merkle_distribution(ctx: &Context, id: u64) -> DistributorResult<MerkleDistribution> {
+    MERKLE_DISTRIBUTIONS
+        .may_load(ctx.deps.storage, id)?
+        .ok_or(MerkleDistributionNotFound { id })
+}
+
+/// Position of the given entry index in the claimed bitmap, as (word, bit mask).
+fn bitmap_position(index: u32) -> (u32, u64) {
+    (index / 64, 1u64 << (index % 64))
+}
+
+pub fn query_merkle_distribution(
+    qctx: QueryContext,
+    params: MerkleDistributionParams,
+) -> DistributorResult<MerkleDistributionResponse> {
+    let distribution = MERKLE_DISTRIBUTIONS
+        .may_load(qctx.deps.storage, params.id)?
+        .ok_or(MerkleDistributionNotFound { id: params.id })?;
+
+    Ok(MerkleDistributionResponse {
+        id: params.id,
+        root: distribution.root,
+        denom: distribution.denom,
+        total: distribution.total,
+        claimed: distribution.claimed,
+        expiry: distribution.expiry,
+        swept: distribution.swept,
+    })
+}
+
+pub fn query_merkle_claimed(
+    qctx: QueryContext,
+    params: MerkleClaimedParams,
+) -> DistributorResult<MerkleClaimedResponse> {
+    let (word, bit) = bitmap_position(params.index);
+
+    let claimed_bits = MERKLE_CLAIMED_BITMAP
+        .may_load(qctx.deps.storage, (params.id, word))?
+        .unwrap_or_default();
+
+    Ok(MerkleClaimedResponse {
+        claimed: claimed_bits & bit != 0,
+    })
+}
@@ -0,0 +1,83 @@
+use crate::eligibility::MINIMUM_ELIGIBLE_WEIGHT;
+use crate::equal_distributions::initialize_eligible_member_count;
+use crate::native_distributions::migrate_native_index_history;
+use crate::tiers::migrate_minimum_eligible_weight_to_reward_tiers;
+use crate::weight_sources::migrate_to_weight_sources;
+use cosmwasm_std::{DepsMut, Uint128};
+use cw2::{get_contract_version, set_contract_version};
+use funds_distributor_api::error::DistributorError::{
+    InvalidContractVersion, InvalidMigrationContract, MigrationDowngrade,
+};
+use funds_distributor_api::error::DistributorResult;
+use semver::Version;
+
+type MigrationStep = fn(DepsMut) -> DistributorResult<()>;
+
+/// Steps bringing the state up to date, each run when migrating from a version older than the
+/// one it is listed under.
+///
+/// Ordered by version, and run in this order.
+const MIGRATIONS: &[(&str, MigrationStep)] = &[
+    ("0.3.0", save_default_minimum_eligible_weight),
+    ("0.4.0", migrate_to_weight_sources),
+    ("0.4.0", initialize_eligible_member_count),
+    ("0.4.0", migrate_native_index_history),
+    ("0.4.0", migrate_minimum_eligible_weight_to_reward_tiers),
+];
+
+/// Runs the migration steps for all the versions newer than the stored one, up to the given
+/// version, and stores the given version.
+///
+/// Fails if the stored version belongs to another contract, or is newer than the given version.
+///
+/// Returns the previously stored version.
+pub fn migrate_state(
+    mut deps: DepsMut,
+    contract_name: &str,
+    contract_version: &str,
+) -> DistributorResult<String> {
+    let previous = get_contract_version(deps.storage)?;
+
+    if previous.contract != contract_name {
+        return Err(InvalidMigrationContract {
+            contract: previous.contract,
+        });
+    }
+
+    let previous_version = parse_version(&previous.version)?;
+    let new_version = parse_version(contract_version)?;
+
+    if previous_version > new_version {
+        return Err(MigrationDowngrade {
+            from: previous.version,
+            to: contract_version.to_string(),
+        });
+    }
+
+    for (version, step) in MIGRATIONS {
+        let version = parse_version(version)?;
+
+        if previous_version < version && version <= new_version {
+            step(deps.branch())?;
+        }
+    }
+
+    set_contract_version(deps.storage, contract_name, contract_version)?;
+
+    Ok(previous.version)
+}
+
+fn parse_version(version: &str) -> DistributorResult<Version> {
+    Version::parse(version).map_err(|_| InvalidContractVersion {
+        version: version.to_string(),
+    })
+}
+
+/// Saves the default minimum eligible weight of zero, for state that does not have it stored.
+fn save_default_minimum_eligible_weight(deps: DepsMut) -> DistributorResult<()> {
+    if !MINIMUM_ELIGIBLE_WEIGHT.exists(deps.storage) {
+        MINIMUM_ELIGIBLE_WEIGHT.save(deps.storage, &Uint128::zero())?;
+    }
+
+    Ok(())
+}
@@ -1,11 +1,23 @@
-use crate::rewards::calculate_user_reward;
-use crate::state::NATIVE_GLOBAL_INDICES;
+use crate::delegates::delegate_commission;
+use crate::denoms::is_denom_state_removed;
+use crate::rewards::calculate_new_user_reward;
+use crate::state::{
+    DENOMS_BY_LAST_DISTRIBUTION, DISTRIBUTION_SEQ, NATIVE_GLOBAL_INDICES, NATIVE_INDEX_HISTORY,
+};
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Order::Ascending;
-use cosmwasm_std::{Addr, Decimal, DepsMut, StdResult, Uint128};
-use cw_storage_plus::{Index, IndexList, IndexedMap, MultiIndex};
+use cosmwasm_std::Order::{Ascending, Descending};
+use cosmwasm_std::{Addr, Decimal, Deps, DepsMut, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Map, MultiIndex, PrefixBound};
 use funds_distributor_api::error::DistributorResult;
 
+/// Distribution sequence number up to which all of the user's native rewards were moved into
+/// their pending rewards.
+///
+/// Users only have a `NativeDistribution` stored for the denoms they accrued rewards in since,
+/// for the other denoms their reward index is the global index as of this checkpoint.
+pub const USER_CHECKPOINTS: Map<Addr, u64> = Map::new("user_checkpoints");
+
 #[cw_serde]
 /// State of a single user's specific native rewards.
 pub struct NativeDistribution {
@@ -48,36 +60,267 @@ impl From<NativeDistribution> for (Decimal, Uint128) {
     }
 }
 
-/// Updates user's reward indices for all native assets.
+/// Updates user's reward indices for the native assets distributed since their last checkpoint.
 ///
 /// Will calculate newly pending rewards since the last update to the user's reward index until now,
-/// using their last weight to calculate the newly accrued rewards.
+/// using their last weight to calculate the newly accrued rewards, and credit the user's delegate
+/// their commission.
+/// Only denoms that the user accrued rewards in get their state stored.
 pub fn update_user_native_distributions(
-    deps: DepsMut,
+    mut deps: DepsMut,
     user: Addr,
     old_user_weight: Uint128,
 ) -> DistributorResult<()> {
+    let checkpoint = USER_CHECKPOINTS.may_load(deps.storage, user.clone())?;
+
+    let denoms = match checkpoint {
+        // users from before checkpoints were introduced are brought up to date for all the denoms
+        None => NATIVE_GLOBAL_INDICES
+            .keys(deps.storage, None, None, Ascending)
+            .collect::<StdResult<Vec<String>>>()?,
+        Some(checkpoint) => DENOMS_BY_LAST_DISTRIBUTION
+            .prefix_range(
+                deps.storage,
+                Some(PrefixBound::exclusive(checkpoint)),
+                None,
+                Ascending,
+            )
+            .map(|key| key.map(|((_, denom), _)| denom))
+            .collect::<StdResult<Vec<String>>>()?,
+    };
+
+    for denom in denoms {
+        settle_user_native_reward(deps.branch(), &user, denom, old_user_weight)?;
+    }
+
+    save_user_checkpoint(deps.storage, user)?;
+
+    Ok(())
+}
+
+/// Moves the rewards that the user accrued in the denom since the last update to their reward
+/// index into their pending rewards, crediting their delegate's commission on them.
+///
+/// Returns user's pending rewards for the denom.
+pub fn settle_user_native_reward(
+    deps: DepsMut,
+    user: &Addr,
+    denom: String,
+    user_weight: Uint128,
+) -> DistributorResult<Uint128> {
+    let global_index = NATIVE_GLOBAL_INDICES
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default();
+
+    let (user_index, pending_rewards) = load_user_distribution(deps.as_ref(), user, &denom)?;
+
+    let new_reward = calculate_new_user_reward(global_index, user_index, user_weight);
+
+    // nothing to remember if the user did not accrue anything
+    if new_reward.is_zero()
+        && !NATIVE_DISTRIBUTIONS().has(deps.storage, (user.clone(), denom.clone()))
+    {
+        return Ok(pending_rewards);
+    }
+
+    let commission = delegate_commission(deps.as_ref(), user, new_reward)?;
+    let commission_amount = commission
+        .as_ref()
+        .map(|(_, amount)| *amount)
+        .unwrap_or_default();
+
+    let pending_rewards = pending_rewards + new_reward - commission_amount;
+
+    NATIVE_DISTRIBUTIONS().save(
+        deps.storage,
+        (user.clone(), denom.clone()),
+        &NativeDistribution {
+            user: user.clone(),
+            denom: denom.clone(),
+            user_index: global_index,
+            pending_rewards,
+        },
+    )?;
+
+    if let Some((delegate, commission)) = commission {
+        credit_delegate_commission(deps, delegate, denom, commission)?;
+    }
+
+    Ok(pending_rewards)
+}
+
+/// Adds the commission to the delegate's pending rewards for the denom.
+///
+/// Delegate's reward index is left as is, so their own rewards are settled the next time they
+/// are updated. This keeps crediting a commission from settling the whole chain of delegates.
+fn credit_delegate_commission(
+    deps: DepsMut,
+    delegate: Addr,
+    denom: String,
+    commission: Uint128,
+) -> StdResult<()> {
+    let (user_index, pending_rewards) = load_user_distribution(deps.as_ref(), &delegate, &denom)?;
+
+    NATIVE_DISTRIBUTIONS().save(
+        deps.storage,
+        (delegate.clone(), denom.clone()),
+        &NativeDistribution {
+            user: delegate,
+            denom,
+            user_index,
+            pending_rewards: pending_rewards + commission,
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Sets the user's checkpoint to the latest distribution, making their reward indices for all
+/// denoms without a stored `NativeDistribution` equal to the current global indices.
+pub fn save_user_checkpoint(storage: &mut dyn Storage, user: Addr) -> StdResult<()> {
+    let distribution_seq = DISTRIBUTION_SEQ.may_load(storage)?.unwrap_or_default();
+
+    USER_CHECKPOINTS.save(storage, user, &distribution_seq)
+}
+
+/// Loads user's reward index and pending rewards for the given denom.
+///
+/// Users without a stored `NativeDistribution` for the denom have no pending rewards, and their
+/// index is the global index as of their checkpoint.
+pub fn load_user_distribution(
+    deps: Deps,
+    user: &Addr,
+    denom: &str,
+) -> StdResult<(Decimal, Uint128)> {
+    if let Some(distribution) =
+        NATIVE_DISTRIBUTIONS().may_load(deps.storage, (user.clone(), denom.to_string()))?
+    {
+        return Ok(distribution.into());
+    }
+
+    let user_index = match USER_CHECKPOINTS.may_load(deps.storage, user.clone())? {
+        // before checkpoints were introduced, missing distributions meant the denom was first
+        // distributed after the user's last update
+        None => Decimal::zero(),
+        Some(checkpoint) => native_global_index_at(deps, denom, checkpoint)?,
+    };
+
+    Ok((user_index, Uint128::zero()))
+}
+
+/// Saves the new global index of a denom for the given distribution.
+pub fn save_native_global_index(
+    storage: &mut dyn Storage,
+    denom: String,
+    global_index: Decimal,
+) -> StdResult<()> {
+    let distribution_seq = DISTRIBUTION_SEQ.may_load(storage)?.unwrap_or_default();
+
+    let last_distribution = NATIVE_INDEX_HISTORY
+        .prefix(denom.clone())
+        .keys(storage, None, None, Descending)
+        .next()
+        .transpose()?;
+    if let Some(last_distribution) = last_distribution {
+        DENOMS_BY_LAST_DISTRIBUTION.remove(storage, (last_distribution, denom.clone()));
+    }
+
+    DENOMS_BY_LAST_DISTRIBUTION.save(storage, (distribution_seq, denom.clone()), &())?;
+    NATIVE_INDEX_HISTORY.save(storage, (denom.clone(), distribution_seq), &global_index)?;
+    NATIVE_GLOBAL_INDICES.save(storage, denom, &global_index)?;
+
+    Ok(())
+}
+
+/// Saves a zero global index for a denom that doesn't have one yet, so that claiming all rewards
+/// includes it even if it was only distributed through other distribution modes.
+pub fn ensure_native_denom_known(storage: &mut dyn Storage, denom: &str) -> StdResult<()> {
+    if NATIVE_GLOBAL_INDICES.has(storage, denom.to_string()) {
+        return Ok(());
+    }
+
+    save_native_global_index(storage, denom.to_string(), Decimal::zero())
+}
+
+/// Global index of a denom right after the distribution with the given sequence number.
+fn native_global_index_at(deps: Deps, denom: &str, distribution_seq: u64) -> StdResult<Decimal> {
+    if is_denom_state_removed(deps.storage, denom)? {
+        let global_index = NATIVE_GLOBAL_INDICES
+            .may_load(deps.storage, denom.to_string())?
+            .unwrap_or_default();
+
+        return Ok(global_index);
+    }
+
+    let global_index = NATIVE_INDEX_HISTORY
+        .prefix(denom.to_string())
+        .range(
+            deps.storage,
+            None,
+            Some(Bound::inclusive(distribution_seq)),
+            Descending,
+        )
+        .next()
+        .transpose()?
+        .map(|(_, global_index)| global_index)
+        .unwrap_or_default();
+
+    Ok(global_index)
+}
+
+/// Records the current global indices as the history of the denoms distributed before the
+/// index history was kept.
+///
+/// Used when migrating from older versions.
+pub fn migrate_native_index_history(deps: DepsMut) -> DistributorResult<()> {
+    if DISTRIBUTION_SEQ.exists(deps.storage) {
+        return Ok(());
+    }
+
     let native_global_indices = NATIVE_GLOBAL_INDICES
         .range(deps.storage, None, None, Ascending)
         .collect::<StdResult<Vec<(String, Decimal)>>>()?;
 
+    DISTRIBUTION_SEQ.save(deps.storage, &0)?;
+
     for (denom, global_index) in native_global_indices {
-        let distribution =
-            NATIVE_DISTRIBUTIONS().may_load(deps.storage, (user.clone(), denom.clone()))?;
+        save_native_global_index(deps.storage, denom, global_index)?;
+    }
 
-        let reward = calculate_user_reward(global_index, distribution, old_user_weight);
+    Ok(())
+}
 
-        NATIVE_DISTRIBUTIONS().save(
-            deps.storage,
-            (user.clone(), denom.clone()),
-            &NativeDistribution {
-                user: user.clone(),
-                denom,
-                user_index: global_index,
-                pending_rewards: reward,
-            },
-        )?;
-    }
+/// Adds the given amount to user's pending rewards for the given denom.
+///
+/// User's reward index for the denom is first brought up to date, so that the rewards accrued
+/// until now are not lost.
+pub fn add_user_pending_rewards(
+    mut deps: DepsMut,
+    user: Addr,
+    denom: String,
+    amount: Uint128,
+) -> DistributorResult<()> {
+    let user_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    let pending_rewards =
+        settle_user_native_reward(deps.branch(), &user, denom.clone(), user_weight)?;
+
+    let global_index = NATIVE_GLOBAL_INDICES
+        .may_load(deps.storage, denom.clone())?
+        .unwrap_or_default();
+
+    NATIVE_DISTRIBUTIONS().save(
+        deps.storage,
+        (user.clone(), denom.clone()),
+        &NativeDistribution {
+            user,
+            denom,
+            user_index: global_index,
+            pending_rewards: pending_rewards + amount,
+        },
+    )?;
 
     Ok(())
 }
@@ -0,0 +1,160 @@
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, Order, Response, StdResult};
+use cw_storage_plus::{Bound, Map};
+use cw_utils::Expiration;
+use funds_distributor_api::api::{
+    ClaimOperator, ClaimOperatorsParams, ClaimOperatorsResponse, GrantClaimOperatorMsg,
+    RevokeClaimOperatorMsg, RewardRecipientParams, RewardRecipientResponse, SetRewardRecipientMsg,
+};
+use funds_distributor_api::error::DistributorError::{InvalidExpiration, Unauthorized};
+use funds_distributor_api::error::DistributorResult;
+
+const DEFAULT_QUERY_LIMIT: u32 = 10;
+const MAX_QUERY_LIMIT: u32 = 30;
+
+/// Operators that users allowed to claim rewards and change reward recipients on their behalf,
+/// keyed by (user, operator).
+pub const CLAIM_OPERATORS: Map<(Addr, Addr), Expiration> = Map::new("claim_operators");
+
+/// Addresses that receive the users' claimed rewards, if different from the users themselves.
+pub const REWARD_RECIPIENTS: Map<Addr, Addr> = Map::new("reward_recipients");
+
+pub fn grant_claim_operator(
+    ctx: &mut Context,
+    msg: GrantClaimOperatorMsg,
+) -> DistributorResult<Response> {
+    let user = ctx.info.sender.clone();
+    let operator = ctx.deps.api.addr_validate(&msg.operator)?;
+
+    let expires = msg.expires.unwrap_or_default();
+    if expires.is_expired(&ctx.env.block) {
+        return Err(InvalidExpiration);
+    }
+
+    CLAIM_OPERATORS.save(ctx.deps.storage, (user.clone(), operator.clone()), &expires)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "grant_claim_operator")
+        .add_attribute("user", user.to_string())
+        .add_attribute("operator", operator.to_string())
+        .add_attribute("expires", expires.to_string()))
+}
+
+pub fn revoke_claim_operator(
+    ctx: &mut Context,
+    msg: RevokeClaimOperatorMsg,
+) -> DistributorResult<Response> {
+    let user = ctx.info.sender.clone();
+    let operator = ctx.deps.api.addr_validate(&msg.operator)?;
+
+    CLAIM_OPERATORS.remove(ctx.deps.storage, (user.clone(), operator.clone()));
+
+    Ok(Response::new()
+        .add_attribute("action", "revoke_claim_operator")
+        .add_attribute("user", user.to_string())
+        .add_attribute("operator", operator.to_string()))
+}
+
+pub fn set_reward_recipient(
+    ctx: &mut Context,
+    msg: SetRewardRecipientMsg,
+) -> DistributorResult<Response> {
+    let user = ctx.deps.api.addr_validate(&msg.user)?;
+
+    assert_user_or_operator(ctx, &user)?;
+
+    let recipient = match msg.recipient {
+        None => {
+            REWARD_RECIPIENTS.remove(ctx.deps.storage, user.clone());
+            user.clone()
+        }
+        Some(recipient) => {
+            let recipient = ctx.deps.api.addr_validate(&recipient)?;
+            REWARD_RECIPIENTS.save(ctx.deps.storage, user.clone(), &recipient)?;
+            recipient
+        }
+    };
+
+    Ok(Response::new()
+        .add_attribute("action", "set_reward_recipient")
+        .add_attribute("user", user.to_string())
+        .add_attribute("recipient", recipient.to_string()))
+}
+
+/// Ensures that the sender is either the user or one of the user's non-expired operators.
+pub fn assert_user_or_operator(ctx: &Context, user: &Addr) -> DistributorResult<()> {
+    if ctx.info.sender == *user {
+        return Ok(());
+    }
+
+    let expires =
+        CLAIM_OPERATORS.may_load(ctx.deps.storage, (user.clone(), ctx.info.sender.clone()))?;
+
+    match expires {
+        Some(expires) if !expires.is_expired(&ctx.env.block) => Ok(()),
+        _ => Err(Unauthorized),
+    }
+}
+
+/// Loads the address that should receive the user's claimed rewards.
+pub fn load_reward_recipient(ctx: &Context, user: &Addr) -> StdResult<Addr> {
+    let recipient = REWARD_RECIPIENTS
+        .may_load(ctx.deps.storage, user.clone())?
+        .unwrap_or_else(|| user.clone());
+
+    Ok(recipient)
+}
+
+pub fn query_claim_operators(
+    qctx: QueryContext,
+    params: ClaimOperatorsParams,
+) -> DistributorResult<ClaimOperatorsResponse> {
+    let user = qctx.deps.api.addr_validate(&params.user)?;
+
+    let start_after = params
+        .start_after
+        .map(|operator| qctx.deps.api.addr_validate(&operator))
+        .transpose()?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .min(MAX_QUERY_LIMIT);
+
+    let operators = CLAIM_OPERATORS
+        .prefix(user)
+        .range(
+            qctx.deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .filter(|operator| match operator {
+            Ok((_, expires)) => !expires.is_expired(&qctx.env.block),
+            Err(_) => true,
+        })
+        .take(limit as usize)
+        .map(|operator| {
+            operator.map(|(operator, expires)| ClaimOperator {
+                operator: operator.to_string(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<ClaimOperator>>>()?;
+
+    Ok(ClaimOperatorsResponse { operators })
+}
+
+pub fn query_reward_recipient(
+    qctx: QueryContext,
+    params: RewardRecipientParams,
+) -> DistributorResult<RewardRecipientResponse> {
+    let user = qctx.deps.api.addr_validate(&params.user)?;
+
+    let recipient = REWARD_RECIPIENTS
+        .may_load(qctx.deps.storage, user.clone())?
+        .unwrap_or(user);
+
+    Ok(RewardRecipientResponse {
+        recipient: recipient.to_string(),
+    })
+}
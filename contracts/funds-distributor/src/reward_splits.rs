@@ -0,0 +1,140 @@
+use crate::operators::assert_user_or_operator;
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, Response, StdResult, Uint128};
+use cw_storage_plus::Map;
+use funds_distributor_api::api::{
+    RewardSplit, RewardSplitParams, RewardSplitResponse, SetRewardSplitMsg,
+};
+use funds_distributor_api::error::DistributorError::InvalidRewardSplit;
+use funds_distributor_api::error::DistributorResult;
+use std::collections::HashSet;
+
+const MAX_SPLIT_RECIPIENTS: usize = 10;
+
+/// Recipients that the users' claimed rewards are split between, along with their shares.
+pub const REWARD_SPLITS: Map<Addr, Vec<(Addr, Decimal)>> = Map::new("reward_splits");
+
+/// Coins to be sent to each of the split recipients.
+pub type SplitPayouts = Vec<(Addr, Vec<Coin>)>;
+
+pub fn set_reward_split(ctx: &mut Context, msg: SetRewardSplitMsg) -> DistributorResult<Response> {
+    let user = ctx.deps.api.addr_validate(&msg.user)?;
+
+    assert_user_or_operator(ctx, &user)?;
+
+    if msg.splits.is_empty() {
+        REWARD_SPLITS.remove(ctx.deps.storage, user.clone());
+
+        return Ok(Response::new()
+            .add_attribute("action", "set_reward_split")
+            .add_attribute("user", user.to_string()));
+    }
+
+    if msg.splits.len() > MAX_SPLIT_RECIPIENTS {
+        return Err(InvalidRewardSplit {
+            reason: format!("cannot have more than {MAX_SPLIT_RECIPIENTS} recipients"),
+        });
+    }
+
+    let mut recipients: HashSet<Addr> = HashSet::new();
+    let mut splits: Vec<(Addr, Decimal)> = vec![];
+
+    for split in msg.splits {
+        let recipient = ctx.deps.api.addr_validate(&split.recipient)?;
+
+        if split.share.is_zero() {
+            return Err(InvalidRewardSplit {
+                reason: format!("share of {recipient} cannot be zero"),
+            });
+        }
+        if !recipients.insert(recipient.clone()) {
+            return Err(InvalidRewardSplit {
+                reason: format!("duplicate recipient {recipient}"),
+            });
+        }
+
+        splits.push((recipient, split.share));
+    }
+
+    let total_share = splits
+        .iter()
+        .map(|(_, share)| *share)
+        .fold(Decimal::zero(), |total, share| total + share);
+    if total_share != Decimal::one() {
+        return Err(InvalidRewardSplit {
+            reason: "shares must add up to 1".to_string(),
+        });
+    }
+
+    REWARD_SPLITS.save(ctx.deps.storage, user.clone(), &splits)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "set_reward_split")
+        .add_attribute("user", user.to_string())
+        .add_attribute("recipients", splits.len().to_string()))
+}
+
+/// Splits the claimed coins between the user's split recipients, according to their shares.
+///
+/// Amounts are rounded down, with the rounding remainder going to the first recipient.
+/// Returns None if the user has no split configured.
+pub fn split_claimed_coins(
+    deps: Deps,
+    user: &Addr,
+    coins: &[Coin],
+) -> StdResult<Option<SplitPayouts>> {
+    let splits = match REWARD_SPLITS.may_load(deps.storage, user.clone())? {
+        None => return Ok(None),
+        Some(splits) => splits,
+    };
+
+    let mut payouts: SplitPayouts = splits
+        .iter()
+        .map(|(recipient, _)| (recipient.clone(), vec![]))
+        .collect();
+
+    for coin in coins {
+        let mut remaining = coin.amount;
+
+        for (i, (_, share)) in splits.iter().enumerate().skip(1) {
+            let amount = coin.amount.mul_floor(*share);
+            remaining -= amount;
+
+            push_coin(&mut payouts[i].1, amount, &coin.denom);
+        }
+
+        push_coin(&mut payouts[0].1, remaining, &coin.denom);
+    }
+
+    let payouts = payouts
+        .into_iter()
+        .filter(|(_, coins)| !coins.is_empty())
+        .collect();
+
+    Ok(Some(payouts))
+}
+
+fn push_coin(coins: &mut Vec<Coin>, amount: Uint128, denom: &str) {
+    if !amount.is_zero() {
+        coins.push(Coin::new(amount, denom));
+    }
+}
+
+pub fn query_reward_split(
+    qctx: QueryContext,
+    params: RewardSplitParams,
+) -> DistributorResult<RewardSplitResponse> {
+    let user = qctx.deps.api.addr_validate(&params.user)?;
+
+    let splits = REWARD_SPLITS
+        .may_load(qctx.deps.storage, user)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(recipient, share)| RewardSplit {
+            recipient: recipient.to_string(),
+            share,
+        })
+        .collect();
+
+    Ok(RewardSplitResponse { splits })
+}
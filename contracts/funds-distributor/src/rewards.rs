@@ -1,25 +1,19 @@
-use crate::native_distributions::NATIVE_DISTRIBUTIONS;
+use crate::delegates::delegate_commission;
+use crate::equal_distributions::unsettled_per_member_rewards;
+use crate::groups::unsettled_group_rewards;
+use crate::native_distributions::load_user_distribution;
+use crate::snapshot_distributions::{
+    unsettled_snapshot_shares, SnapshotShares, MAX_SNAPSHOT_SETTLEMENTS,
+};
 use crate::state::NATIVE_GLOBAL_INDICES;
+use crate::tiers::unsettled_tier_rewards;
 use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
 use common::cw::QueryContext;
-use cosmwasm_std::{Decimal, Order, Uint128};
+use cosmwasm_std::{Addr, Decimal, Deps, Order, Uint128};
 use funds_distributor_api::api::{NativeReward, UserRewardsParams, UserRewardsResponse};
 use funds_distributor_api::error::DistributorResult;
 use std::collections::HashSet;
-use std::ops::{Add, Mul, Sub};
-
-/// Calculates user's currently available rewards for an asset, given its current global index
-/// and user's weight.
-pub fn calculate_user_reward(
-    global_index: Decimal,
-    distribution: Option<impl Into<(Decimal, Uint128)>>,
-    user_weight: Uint128,
-) -> Uint128 {
-    let (user_index, pending_rewards) =
-        distribution.map_or((Decimal::zero(), Uint128::zero()), |it| it.into());
-
-    calculate_new_user_reward(global_index, user_index, user_weight).add(pending_rewards)
-}
+use std::ops::{Add, Sub};
 
 /// Calculates reward accrued for the given asset since the last update to the user's reward
 /// index for the given asset.
@@ -37,12 +31,6 @@ pub fn query_user_rewards(
 ) -> DistributorResult<UserRewardsResponse> {
     let user = qctx.deps.api.addr_validate(&params.user)?;
 
-    let user_weight = EFFECTIVE_USER_WEIGHTS
-        .may_load(qctx.deps.storage, user.clone())?
-        .unwrap_or_default();
-
-    let mut native_rewards: Vec<NativeReward> = vec![];
-
     let denoms = params.native_denoms.map_or_else(
         || {
             NATIVE_GLOBAL_INDICES
@@ -53,15 +41,61 @@ pub fn query_user_rewards(
     )?;
     let denoms = dedup_native_denoms(denoms);
 
+    calculate_user_rewards(qctx.deps, &user, denoms)
+}
+
+/// Calculates user's currently available rewards for the given denoms, including the rewards
+/// from snapshot, group, equal split and tiered distributions that were not yet moved into
+/// their pending rewards, and excluding their delegate's commission.
+///
+/// Only as many snapshot distributions as a claim settles are included, the response reports
+/// whether there are more of them.
+/// Commissions from the user's delegators are only included once credited.
+pub fn calculate_user_rewards(
+    deps: Deps,
+    user: &Addr,
+    denoms: Vec<String>,
+) -> DistributorResult<UserRewardsResponse> {
+    let user_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    let SnapshotShares {
+        shares: snapshot_shares,
+        complete: snapshot_shares_complete,
+        ..
+    } = unsettled_snapshot_shares(deps, user, MAX_SNAPSHOT_SETTLEMENTS)?;
+    let group_rewards = unsettled_group_rewards(deps, user)?;
+    let per_member_rewards = unsettled_per_member_rewards(deps, user)?;
+    let tier_rewards = unsettled_tier_rewards(deps, user)?;
+
+    let mut native_rewards: Vec<NativeReward> = vec![];
+
     for denom in denoms {
         let global_index = NATIVE_GLOBAL_INDICES
-            .may_load(qctx.deps.storage, denom.clone())?
+            .may_load(deps.storage, denom.clone())?
+            .unwrap_or_default();
+
+        let (user_index, pending_rewards) = load_user_distribution(deps, user, &denom)?;
+
+        let new_reward = calculate_new_user_reward(global_index, user_index, user_weight);
+        let commission = delegate_commission(deps, user, new_reward)?
+            .map(|(_, commission)| commission)
             .unwrap_or_default();
 
-        let distribution =
-            NATIVE_DISTRIBUTIONS().may_load(qctx.deps.storage, (user.clone(), denom.clone()))?;
+        let unsettled_reward = snapshot_shares
+            .iter()
+            .chain(group_rewards.iter())
+            .chain(per_member_rewards.iter())
+            .chain(tier_rewards.iter())
+            .filter(|coin| coin.denom == denom)
+            .map(|coin| coin.amount)
+            .sum::<Uint128>();
 
-        let reward = calculate_user_reward(global_index, distribution, user_weight);
+        let reward = pending_rewards
+            .add(new_reward)
+            .sub(commission)
+            .add(unsettled_reward);
 
         native_rewards.push(NativeReward {
             denom,
@@ -69,7 +103,10 @@ pub fn query_user_rewards(
         });
     }
 
-    Ok(UserRewardsResponse { native_rewards })
+    Ok(UserRewardsResponse {
+        native_rewards,
+        snapshot_settlement_pending: !snapshot_shares_complete,
+    })
 }
 
 /// Takes a vector of native denoms and returns a vector with all duplicates removed.
@@ -0,0 +1,220 @@
+use crate::denoms::{add_refund, split_distribution_funds};
+use crate::distributors::assert_authorized_distributor;
+use crate::hooks::hook_submsgs;
+use crate::native_distributions::{add_user_pending_rewards, ensure_native_denom_known};
+use crate::state::TOTAL_WEIGHT;
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
+use common::cw::Context;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Deps, DepsMut, Order, Response, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use funds_distributor_api::api::{DistributionHookMsg, FundsDistributorHookMsg};
+use funds_distributor_api::error::DistributorError::{InvalidSnapshotHeight, ZeroTotalWeight};
+use funds_distributor_api::error::DistributorResult;
+
+/// Funds distributed according to the users' weights at a past block height.
+#[cw_serde]
+pub struct SnapshotDistribution {
+    pub height: u64,
+    /// Total weight at the beginning of the snapshot height
+    pub total_weight: Uint128,
+    pub funds: Vec<Coin>,
+}
+
+/// ID of the last snapshot distribution.
+pub const SNAPSHOT_DISTRIBUTION_ID: Item<u64> = Item::new("snapshot_distribution_id");
+
+pub const SNAPSHOT_DISTRIBUTIONS: Map<u64, SnapshotDistribution> =
+    Map::new("snapshot_distributions");
+
+/// ID of the last snapshot distribution whose share was moved into the user's pending rewards.
+pub const USER_SNAPSHOT_CURSORS: Map<Addr, u64> = Map::new("user_snapshot_cursors");
+
+/// Maximum number of snapshot distributions settled for a user at once. Users with more
+/// unsettled distributions have the rest settled the next time they claim.
+pub const MAX_SNAPSHOT_SETTLEMENTS: u32 = 30;
+
+/// Distributes new rewards for native assets, using funds found in MessageInfo, split by the
+/// users' weights at the beginning of the given block height.
+///
+/// Users' shares are independent of any weight changes after the given height, and are moved
+/// into their pending rewards when they claim.
+pub fn distribute_native_at_height(ctx: &mut Context, height: u64) -> DistributorResult<Response> {
+    if height > ctx.env.block.height {
+        return Err(InvalidSnapshotHeight { height });
+    }
+
+    assert_authorized_distributor(ctx.deps.as_ref(), &ctx.info.sender)?;
+
+    let (funds, rejected_funds) =
+        split_distribution_funds(ctx.deps.as_ref(), ctx.info.funds.clone())?;
+
+    let total_weight = TOTAL_WEIGHT
+        .may_load_at_height(ctx.deps.storage, height)?
+        .unwrap_or_default();
+    if total_weight.is_zero() {
+        return Err(ZeroTotalWeight);
+    }
+
+    for fund in &funds {
+        ensure_native_denom_known(ctx.deps.storage, &fund.denom)?;
+    }
+
+    let id = SNAPSHOT_DISTRIBUTION_ID
+        .may_load(ctx.deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    SNAPSHOT_DISTRIBUTION_ID.save(ctx.deps.storage, &id)?;
+
+    SNAPSHOT_DISTRIBUTIONS.save(
+        ctx.deps.storage,
+        id,
+        &SnapshotDistribution {
+            height,
+            total_weight,
+            funds: funds.clone(),
+        },
+    )?;
+
+    let hook_submsgs = hook_submsgs(
+        ctx.deps.as_ref(),
+        FundsDistributorHookMsg::DistributionHook(DistributionHookMsg {
+            distributor: ctx.info.sender.to_string(),
+            funds,
+            total_weight,
+            snapshot_height: Some(height),
+            group_id: None,
+            eligible_member_count: None,
+        }),
+    )?;
+
+    let response = Response::new()
+        .add_attribute("action", "distribute_native_at_height")
+        .add_attribute("snapshot_distribution_id", id.to_string())
+        .add_attribute("height", height.to_string())
+        .add_attribute("total_weight", total_weight.to_string())
+        .add_submessages(hook_submsgs);
+
+    Ok(add_refund(response, &ctx.info.sender, rejected_funds))
+}
+
+/// Moves user's shares of the oldest snapshot distributions they haven't settled yet into their
+/// pending rewards, settling at most `limit` distributions.
+pub fn settle_snapshot_distributions(
+    mut deps: DepsMut,
+    user: Addr,
+    limit: u32,
+) -> DistributorResult<()> {
+    let SnapshotShares {
+        last_id, shares, ..
+    } = unsettled_snapshot_shares(deps.as_ref(), &user, limit)?;
+
+    let Some(last_id) = last_id else {
+        return Ok(());
+    };
+
+    for share in shares {
+        add_user_pending_rewards(deps.branch(), user.clone(), share.denom, share.amount)?;
+    }
+
+    USER_SNAPSHOT_CURSORS.save(deps.storage, user, &last_id)?;
+
+    Ok(())
+}
+
+/// User's shares of the oldest snapshot distributions they haven't settled yet.
+pub struct SnapshotShares {
+    /// ID of the last distribution gone through, if any
+    pub last_id: Option<u64>,
+    pub shares: Vec<Coin>,
+    /// Whether all the unsettled distributions were gone through
+    pub complete: bool,
+}
+
+/// Starts the user's snapshot cursor at the last snapshot distribution, for users that never had
+/// a weight, since they have no share in any of the distributions made so far.
+pub fn initialize_user_snapshot_cursor(storage: &mut dyn Storage, user: Addr) -> StdResult<()> {
+    if USER_SNAPSHOT_CURSORS.has(storage, user.clone()) {
+        return Ok(());
+    }
+
+    let had_weight = EFFECTIVE_USER_WEIGHTS
+        .changelog()
+        .prefix(user.clone())
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if had_weight {
+        return Ok(());
+    }
+
+    let last_id = SNAPSHOT_DISTRIBUTION_ID
+        .may_load(storage)?
+        .unwrap_or_default();
+    USER_SNAPSHOT_CURSORS.save(storage, user, &last_id)
+}
+
+/// Calculates user's shares of the oldest snapshot distributions they haven't settled yet, going
+/// through at most `limit` distributions.
+pub fn unsettled_snapshot_shares(
+    deps: Deps,
+    user: &Addr,
+    limit: u32,
+) -> DistributorResult<SnapshotShares> {
+    let cursor = USER_SNAPSHOT_CURSORS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    let distributions = SNAPSHOT_DISTRIBUTIONS
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(cursor)),
+            None,
+            Order::Ascending,
+        )
+        .take(limit as usize)
+        .collect::<StdResult<Vec<(u64, SnapshotDistribution)>>>()?;
+
+    let last_id = distributions.last().map(|(id, _)| *id);
+
+    let complete = last_id.unwrap_or(cursor)
+        >= SNAPSHOT_DISTRIBUTION_ID
+            .may_load(deps.storage)?
+            .unwrap_or_default();
+
+    let mut shares: Vec<Coin> = vec![];
+
+    for (_, distribution) in distributions {
+        let user_weight = EFFECTIVE_USER_WEIGHTS
+            .may_load_at_height(deps.storage, user.clone(), distribution.height)?
+            .unwrap_or_default();
+
+        if user_weight.is_zero() {
+            continue;
+        }
+
+        for fund in distribution.funds {
+            let amount = fund
+                .amount
+                .multiply_ratio(user_weight, distribution.total_weight);
+
+            if amount.is_zero() {
+                continue;
+            }
+
+            match shares.iter_mut().find(|share| share.denom == fund.denom) {
+                Some(share) => share.amount += amount,
+                None => shares.push(Coin {
+                    denom: fund.denom,
+                    amount,
+                }),
+            }
+        }
+    }
+
+    Ok(SnapshotShares {
+        last_id,
+        shares,
+        complete,
+    })
+}
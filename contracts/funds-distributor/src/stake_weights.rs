@@ -0,0 +1,191 @@
+use crate::user_weights::update_source_user_weights;
+use crate::weight_sources::{WeightSourceConfig, SOURCE_USER_WEIGHTS, WEIGHT_SOURCES};
+use common::cw::Context;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Deps, QueryRequest, Response, StdResult, Uint128, WasmQuery,
+};
+use funds_distributor_api::api::{
+    StakeChangedHookMsg, UpdateNativeStakeWeightsMsg, WeightSourceKind,
+};
+use funds_distributor_api::error::DistributorError::{
+    InsufficientStake, NativeStakingSourceNotRegistered, Unauthorized,
+};
+use funds_distributor_api::error::DistributorResult;
+
+/// Query messages of cw20-stake style staking contracts, used to sync the initial stakes.
+#[cw_serde]
+enum Cw20StakeQueryMsg {
+    ListStakers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+struct ListStakersResponse {
+    stakers: Vec<StakerBalanceResponse>,
+}
+
+#[cw_serde]
+struct StakerBalanceResponse {
+    address: String,
+    balance: Uint128,
+}
+
+/// Updates the users' weights in a cw20-stake style weight source, according to the change in
+/// their stake.
+/// Will calculate any accrued rewards since the last update to their rewards.
+pub fn update_stake_weights(
+    ctx: &mut Context,
+    msg: StakeChangedHookMsg,
+) -> DistributorResult<Response> {
+    let source = ctx.info.sender.clone();
+
+    match WEIGHT_SOURCES.may_load(ctx.deps.storage, source.clone())? {
+        Some(WeightSourceConfig {
+            kind: WeightSourceKind::Cw20Stake { .. },
+            ..
+        }) => {}
+        _ => return Err(Unauthorized),
+    }
+
+    let (user, old_stake, new_stake) = match msg {
+        StakeChangedHookMsg::Stake { addr, amount } => {
+            let old_stake = load_source_user_weight(ctx.deps.as_ref(), &source, &addr)?;
+            (addr, old_stake, old_stake + amount)
+        }
+        StakeChangedHookMsg::Unstake { addr, amount } => {
+            let old_stake = load_source_user_weight(ctx.deps.as_ref(), &source, &addr)?;
+            let new_stake = old_stake
+                .checked_sub(amount)
+                .map_err(|_| InsufficientStake)?;
+            (addr, old_stake, new_stake)
+        }
+    };
+
+    // users without any stake are not members of the source
+    let new_stake = if new_stake.is_zero() {
+        None
+    } else {
+        Some(new_stake)
+    };
+
+    update_source_user_weights(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        source,
+        vec![(user.clone(), new_stake)],
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_stake_weights")
+        .add_attribute("user", user.to_string())
+        .add_attribute("old_stake", old_stake.to_string())
+        .add_attribute("new_stake", new_stake.unwrap_or_default().to_string()))
+}
+
+/// Sets the users' weights in the native staking weight source to their current delegations.
+/// Will calculate any accrued rewards since the last update to their rewards.
+///
+/// Can be called by anyone, since the weights are read directly from the staking module.
+pub fn update_native_stake_weights(
+    ctx: &mut Context,
+    msg: UpdateNativeStakeWeightsMsg,
+) -> DistributorResult<Response> {
+    let source = ctx.env.contract.address.clone();
+
+    let validators = match WEIGHT_SOURCES.may_load(ctx.deps.storage, source.clone())? {
+        Some(WeightSourceConfig {
+            kind: WeightSourceKind::NativeStaking { validators },
+            ..
+        }) => validators,
+        _ => return Err(NativeStakingSourceNotRegistered),
+    };
+
+    let mut weight_changes: Vec<(Addr, Option<Uint128>)> = vec![];
+
+    for user in msg.users {
+        let user = ctx.deps.api.addr_validate(&user)?;
+
+        let delegated: Uint128 = ctx
+            .deps
+            .querier
+            .query_all_delegations(user.clone())?
+            .into_iter()
+            .filter(|delegation| {
+                validators
+                    .as_ref()
+                    .is_none_or(|validators| validators.contains(&delegation.validator))
+            })
+            .map(|delegation| delegation.amount.amount)
+            .sum();
+
+        let is_member = SOURCE_USER_WEIGHTS.has(ctx.deps.storage, (source.clone(), user.clone()));
+
+        // avoid creating state for users that never delegated
+        if delegated.is_zero() && !is_member {
+            continue;
+        }
+
+        let new_weight = if delegated.is_zero() {
+            None
+        } else {
+            Some(delegated)
+        };
+
+        weight_changes.push((user, new_weight));
+    }
+
+    let updated_users = weight_changes.len();
+
+    update_source_user_weights(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        source,
+        weight_changes,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "update_native_stake_weights")
+        .add_attribute("updated_users", updated_users.to_string()))
+}
+
+/// Lists all the stakers of a cw20-stake style staking contract, and their stakes.
+pub fn get_initial_stakes(deps: Deps, staking_contract: Addr) -> StdResult<Vec<(String, Uint128)>> {
+    let mut stakes: Vec<(String, Uint128)> = vec![];
+
+    loop {
+        let query_msg = Cw20StakeQueryMsg::ListStakers {
+            start_after: stakes.last().map(|(staker, _)| staker.clone()),
+            limit: Some(30),
+        };
+
+        let query_response: ListStakersResponse =
+            deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: staking_contract.to_string(),
+                msg: to_json_binary(&query_msg)?,
+            }))?;
+
+        if query_response.stakers.is_empty() {
+            break;
+        }
+
+        stakes.extend(
+            query_response
+                .stakers
+                .into_iter()
+                .map(|staker| (staker.address, staker.balance)),
+        );
+    }
+
+    Ok(stakes)
+}
+
+fn load_source_user_weight(deps: Deps, source: &Addr, user: &Addr) -> StdResult<Uint128> {
+    let weight = SOURCE_USER_WEIGHTS
+        .may_load(deps.storage, (source.clone(), user.clone()))?
+        .unwrap_or_default();
+
+    Ok(weight)
+}
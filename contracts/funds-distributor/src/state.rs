@@ -1,12 +1,30 @@
 use cosmwasm_std::{Addr, Decimal, Uint128};
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Item, Map, SnapshotItem, Strategy};
 
 pub const SQUAD_CONTRACT: Item<Addr> = Item::new("enterprise_contract");
 
-/// Total weight of all users eligible for rewards.
-pub const TOTAL_WEIGHT: Item<Uint128> = Item::new("total_weight");
+/// Total weight of all users eligible for rewards, with its history kept for every block that it
+/// changed in.
+pub const TOTAL_WEIGHT: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_weight",
+    "total_weight__checkpoints",
+    "total_weight__changelog",
+    Strategy::EveryBlock,
+);
 
 /// Tracks global index for native denomination rewards.
 /// Global index is simply a decimal number representing the amount of currency rewards paid
 /// for a unit of user weight, since the beginning of time.
 pub const NATIVE_GLOBAL_INDICES: Map<String, Decimal> = Map::new("native_global_indices");
+
+/// Sequence number of the last distribution that increased the native global indices.
+pub const DISTRIBUTION_SEQ: Item<u64> = Item::new("distribution_seq");
+
+/// Native global indices right after each distribution of the denom, keyed by
+/// (denom, distribution sequence number).
+pub const NATIVE_INDEX_HISTORY: Map<(String, u64), Decimal> = Map::new("native_index_history");
+
+/// Denoms keyed by the sequence number of their last distribution, to find the denoms distributed
+/// since a given distribution.
+pub const DENOMS_BY_LAST_DISTRIBUTION: Map<(u64, String), ()> =
+    Map::new("denoms_by_last_distribution");
@@ -0,0 +1,212 @@
+use crate::delegates::unsettled_delegate_commission;
+use crate::denoms::load_users_page;
+use crate::native_distributions::{add_user_pending_rewards, ensure_native_denom_known};
+use crate::rewards::calculate_user_rewards;
+use crate::state::{NATIVE_GLOBAL_INDICES, SQUAD_CONTRACT};
+use common::cw::{Context, QueryContext};
+use cosmwasm_std::Order::Ascending;
+use cosmwasm_std::{coin, Addr, Coin, Response, StdResult, Storage, Uint128};
+use cw_storage_plus::{Item, Map};
+use cw_utils::NativeBalance;
+use funds_distributor_api::api::{
+    ExportStateParams, ExportStateResponse, ExportedCommission, ExportedUser, ImportStateMsg,
+    NativeReward,
+};
+use funds_distributor_api::error::DistributorError::{
+    InsolventStateImport, SnapshotSettlementPending, StateImportClosed, StateImportOpen,
+    Unauthorized, UserAlreadyImported,
+};
+use funds_distributor_api::error::DistributorResult;
+
+const DEFAULT_QUERY_LIMIT: u32 = 10;
+const MAX_QUERY_LIMIT: u32 = 30;
+
+/// Whether state can still be imported. Set at instantiation if the contract is going to import
+/// state, and removed once the admin closes the import.
+pub const STATE_IMPORT_OPEN: Item<bool> = Item::new("state_import_open");
+
+/// Rewards owed while state can be imported, per denom: imported rewards, minus claimed rewards.
+pub const OWED_REWARDS: Map<String, Uint128> = Map::new("import_owed_rewards");
+
+/// Users whose state was imported.
+pub const IMPORTED_USERS: Map<Addr, ()> = Map::new("imported_users");
+
+/// Ends the phase in which state can be imported, allowing funds to be distributed.
+pub fn close_state_import(ctx: &mut Context) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    STATE_IMPORT_OPEN.remove(ctx.deps.storage);
+    OWED_REWARDS.clear(ctx.deps.storage);
+
+    Ok(Response::new().add_attribute("action", "close_state_import"))
+}
+
+/// Fails if state can still be imported, since funds cannot be distributed until then.
+pub fn assert_state_import_closed(storage: &dyn Storage) -> DistributorResult<()> {
+    if is_state_import_open(storage)? {
+        return Err(StateImportOpen);
+    }
+
+    Ok(())
+}
+
+/// Takes the rewards claimed while state can be imported off the rewards owed.
+pub fn subtract_claimed_rewards(storage: &mut dyn Storage, funds: &[Coin]) -> StdResult<()> {
+    if !is_state_import_open(storage)? {
+        return Ok(());
+    }
+
+    for fund in funds {
+        let owed = OWED_REWARDS
+            .may_load(storage, fund.denom.clone())?
+            .unwrap_or_default();
+        OWED_REWARDS.save(storage, fund.denom.clone(), &(owed - fund.amount))?;
+    }
+
+    Ok(())
+}
+
+fn is_state_import_open(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(STATE_IMPORT_OPEN.may_load(storage)?.unwrap_or_default())
+}
+
+/// Credits the users the rewards exported from another contract. Each user can only be imported
+/// once.
+///
+/// After crediting them, the contract's balance has to cover all the rewards it owes.
+pub fn import_state(ctx: &mut Context, msg: ImportStateMsg) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    if !is_state_import_open(ctx.deps.storage)? {
+        return Err(StateImportClosed);
+    }
+
+    let mut imported = NativeBalance(vec![]);
+
+    for exported_user in msg.users {
+        let user = ctx.deps.api.addr_validate(&exported_user.user)?;
+
+        if IMPORTED_USERS.has(ctx.deps.storage, user.clone()) {
+            return Err(UserAlreadyImported {
+                user: user.to_string(),
+            });
+        }
+        IMPORTED_USERS.save(ctx.deps.storage, user.clone(), &())?;
+
+        let mut credits = vec![(user, exported_user.rewards)];
+        // delegates have their own rewards exported, the commission is added on top of them
+        if let Some(commission) = exported_user.delegate_commission {
+            let delegate = ctx.deps.api.addr_validate(&commission.delegate)?;
+            credits.push((delegate, commission.commission));
+        }
+
+        for (user, rewards) in credits {
+            for reward in rewards {
+                if reward.amount.is_zero() {
+                    continue;
+                }
+
+                ensure_native_denom_known(ctx.deps.storage, &reward.denom)?;
+
+                add_user_pending_rewards(
+                    ctx.deps.branch(),
+                    user.clone(),
+                    reward.denom.clone(),
+                    reward.amount,
+                )?;
+
+                imported += coin(reward.amount.u128(), reward.denom);
+            }
+        }
+    }
+
+    imported.normalize();
+
+    for imported in imported.clone().into_vec() {
+        let owed = OWED_REWARDS
+            .may_load(ctx.deps.storage, imported.denom.clone())?
+            .unwrap_or_default()
+            + imported.amount;
+        OWED_REWARDS.save(ctx.deps.storage, imported.denom.clone(), &owed)?;
+
+        let balance = ctx
+            .deps
+            .querier
+            .query_balance(ctx.env.contract.address.clone(), imported.denom.clone())?
+            .amount;
+        if balance < owed {
+            return Err(InsolventStateImport {
+                denom: imported.denom,
+                owed,
+                balance,
+            });
+        }
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "import_state")
+        .add_attribute("imported", imported.to_string()))
+}
+
+/// Queries a page of the users' rewards.
+///
+/// Users' rewards are exported settled, as the total they could claim from the contract, so that
+/// the export does not depend on the way they were distributed.
+pub fn query_export_state(
+    qctx: QueryContext,
+    params: ExportStateParams,
+) -> DistributorResult<ExportStateResponse> {
+    let start_after = params
+        .start_after
+        .map(|user| qctx.deps.api.addr_validate(&user))
+        .transpose()?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_QUERY_LIMIT)
+        .min(MAX_QUERY_LIMIT) as usize;
+
+    let denoms = NATIVE_GLOBAL_INDICES
+        .keys(qctx.deps.storage, None, None, Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+
+    // users who left still have their rewards stored, so they are exported along with the members
+    let users = load_users_page(qctx.deps, start_after, limit)?
+        .into_iter()
+        .map(|user| {
+            let user_rewards = calculate_user_rewards(qctx.deps, &user, denoms.clone())?;
+            if user_rewards.snapshot_settlement_pending {
+                return Err(SnapshotSettlementPending {
+                    user: user.to_string(),
+                });
+            }
+
+            let delegate_commission = unsettled_delegate_commission(qctx.deps, &user, &denoms)?
+                .map(|(delegate, commission)| ExportedCommission {
+                    delegate: delegate.to_string(),
+                    commission: commission
+                        .into_iter()
+                        .map(|coin| NativeReward {
+                            denom: coin.denom,
+                            amount: coin.amount,
+                        })
+                        .collect(),
+                });
+
+            Ok(ExportedUser {
+                user: user.to_string(),
+                rewards: user_rewards.native_rewards,
+                delegate_commission,
+            })
+        })
+        .collect::<DistributorResult<Vec<ExportedUser>>>()?;
+
+    Ok(ExportStateResponse { users })
+}
@@ -0,0 +1,303 @@
+use crate::contract::{execute, instantiate, query, reply};
+use cosmwasm_std::{coin, coins, Addr, Empty, Uint128};
+use cw4::Member;
+use cw_multi_test::error::AnyResult;
+use cw_multi_test::{App, AppBuilder, AppResponse, Contract, ContractWrapper, Executor};
+use funds_distributor_api::api::{
+    ClaimRewardsMsg, NativeReward, UpdateMinimumEligibleWeightMsg, UserRewardsParams,
+    UserRewardsResponse, UserWeightAtParams, UserWeightResponse,
+};
+use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+
+const LUNA: &str = "uluna";
+
+/// Deployed cw4-group and funds distributor, with the distributor registered as a group hook.
+struct Suite {
+    app: App,
+    admin: Addr,
+    distributor: Addr,
+    group: Addr,
+    funder: Addr,
+}
+
+impl Suite {
+    fn new(members: Vec<(&str, u64)>) -> Self {
+        let mut app = AppBuilder::new().build(|_, _, _| {});
+
+        let admin = app.api().addr_make("admin");
+        let funder = app.api().addr_make("funder");
+
+        app.init_modules(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &funder, coins(1_000_000, LUNA))
+        })
+        .unwrap();
+
+        let group_code_id = app.store_code(cw4_group_contract());
+        let distributor_code_id = app.store_code(distributor_contract());
+
+        let members = members
+            .into_iter()
+            .map(|(user, weight)| Member {
+                addr: app.api().addr_make(user).to_string(),
+                weight,
+            })
+            .collect();
+
+        let group = app
+            .instantiate_contract(
+                group_code_id,
+                admin.clone(),
+                &cw4_group::msg::InstantiateMsg {
+                    admin: Some(admin.to_string()),
+                    members,
+                },
+                &[],
+                "group",
+                None,
+            )
+            .unwrap();
+
+        let distributor = app
+            .instantiate_contract(
+                distributor_code_id,
+                admin.clone(),
+                &InstantiateMsg {
+                    squad_contract: group.to_string(),
+                    minimum_eligible_weight: None,
+                    state_import: None,
+                },
+                &[],
+                "funds distributor",
+                None,
+            )
+            .unwrap();
+
+        app.execute_contract(
+            admin.clone(),
+            group.clone(),
+            &cw4_group::msg::ExecuteMsg::AddHook {
+                addr: distributor.to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+
+        Suite {
+            app,
+            admin,
+            distributor,
+            group,
+            funder,
+        }
+    }
+
+    fn addr(&self, user: &str) -> Addr {
+        self.app.api().addr_make(user)
+    }
+
+    fn update_members(&mut self, add: Vec<(&str, u64)>, remove: Vec<&str>) {
+        let add = add
+            .into_iter()
+            .map(|(user, weight)| Member {
+                addr: self.addr(user).to_string(),
+                weight,
+            })
+            .collect();
+        let remove = remove
+            .into_iter()
+            .map(|user| self.addr(user).to_string())
+            .collect();
+
+        self.app
+            .execute_contract(
+                self.admin.clone(),
+                self.group.clone(),
+                &cw4_group::msg::ExecuteMsg::UpdateMembers { remove, add },
+                &[],
+            )
+            .unwrap();
+    }
+
+    fn distribute_native(&mut self, amount: u128) -> AnyResult<AppResponse> {
+        self.app.execute_contract(
+            self.funder.clone(),
+            self.distributor.clone(),
+            &ExecuteMsg::DistributeNative {},
+            &coins(amount, LUNA),
+        )
+    }
+
+    fn claim(&mut self, user: &str) -> AnyResult<AppResponse> {
+        let user = self.addr(user);
+
+        self.app.execute_contract(
+            user.clone(),
+            self.distributor.clone(),
+            &ExecuteMsg::ClaimRewards(ClaimRewardsMsg {
+                user: user.to_string(),
+                native_denoms: None,
+                compound_to: None,
+            }),
+            &[],
+        )
+    }
+
+    fn update_minimum_eligible_weight(&mut self, minimum_eligible_weight: u128) {
+        // the group is the distributor's admin
+        self.app
+            .execute_contract(
+                self.group.clone(),
+                self.distributor.clone(),
+                &ExecuteMsg::UpdateMinimumEligibleWeight(UpdateMinimumEligibleWeightMsg {
+                    minimum_eligible_weight: Uint128::from(minimum_eligible_weight),
+                }),
+                &[],
+            )
+            .unwrap();
+    }
+
+    fn rewards(&self, user: &str) -> Vec<NativeReward> {
+        let response: UserRewardsResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                self.distributor.clone(),
+                &QueryMsg::UserRewards(UserRewardsParams {
+                    user: self.addr(user).to_string(),
+                    native_denoms: Some(vec![LUNA.to_string()]),
+                }),
+            )
+            .unwrap();
+
+        response.native_rewards
+    }
+
+    fn weight(&self, user: &str) -> Uint128 {
+        let response: UserWeightResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                self.distributor.clone(),
+                &QueryMsg::UserWeightAt(UserWeightAtParams {
+                    user: self.addr(user).to_string(),
+                    height: self.app.block_info().height + 1,
+                }),
+            )
+            .unwrap();
+
+        response.weight
+    }
+
+    fn balance(&self, user: &str) -> u128 {
+        self.app
+            .wrap()
+            .query_balance(self.addr(user), LUNA)
+            .unwrap()
+            .amount
+            .u128()
+    }
+}
+
+fn cw4_group_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw4_group::contract::execute,
+        cw4_group::contract::instantiate,
+        cw4_group::contract::query,
+    ))
+}
+
+fn distributor_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply))
+}
+
+#[test]
+fn instantiate_loads_all_group_members() {
+    // more members than fit into a single ListMembers page
+    let users = (0..45).map(|i| format!("user{i}")).collect::<Vec<String>>();
+    let members = users.iter().map(|user| (user.as_str(), 2)).collect();
+
+    let mut suite = Suite::new(members);
+
+    for user in &users {
+        assert_eq!(suite.weight(user), Uint128::from(2u8));
+    }
+
+    suite.distribute_native(900).unwrap();
+
+    suite.claim("user44").unwrap();
+    assert_eq!(suite.balance("user44"), 20);
+}
+
+#[test]
+fn distribute_and_claim_follow_group_membership() {
+    let mut suite = Suite::new(vec![("user1", 1), ("user2", 3)]);
+
+    suite.distribute_native(400).unwrap();
+
+    // membership changes are reported to the distributor through the group's hooks
+    suite.update_members(vec![("user3", 4)], vec!["user1"]);
+
+    suite.distribute_native(700).unwrap();
+
+    suite.claim("user1").unwrap();
+    suite.claim("user2").unwrap();
+    suite.claim("user3").unwrap();
+
+    assert_eq!(suite.balance("user1"), 100);
+    assert_eq!(suite.balance("user2"), 600);
+    assert_eq!(suite.balance("user3"), 400);
+    assert_eq!(suite.balance("funder"), 1_000_000 - 1100);
+
+    let distributor_balance = suite
+        .app
+        .wrap()
+        .query_balance(suite.distributor.clone(), LUNA)
+        .unwrap();
+    assert_eq!(distributor_balance, coin(0, LUNA));
+
+    // nothing left to claim
+    assert_eq!(suite.rewards("user2"), vec![native_reward(0)]);
+    suite.claim("user2").unwrap();
+    assert_eq!(suite.balance("user2"), 600);
+}
+
+#[test]
+fn minimum_eligible_weight_excludes_small_members() {
+    let mut suite = Suite::new(vec![("user1", 1), ("user2", 4)]);
+
+    suite.update_minimum_eligible_weight(2);
+
+    suite.distribute_native(400).unwrap();
+    assert_eq!(suite.rewards("user1"), vec![native_reward(0)]);
+    assert_eq!(suite.rewards("user2"), vec![native_reward(400)]);
+
+    // crossing the minimum through the group makes the user eligible again
+    suite.update_members(vec![("user1", 4)], vec![]);
+
+    suite.distribute_native(400).unwrap();
+
+    suite.claim("user1").unwrap();
+    suite.claim("user2").unwrap();
+
+    assert_eq!(suite.balance("user1"), 200);
+    assert_eq!(suite.balance("user2"), 600);
+}
+
+#[test]
+fn distribute_with_no_group_weight_fails() {
+    let mut suite = Suite::new(vec![("user1", 1)]);
+
+    suite.update_members(vec![], vec!["user1"]);
+
+    assert!(suite.distribute_native(100).is_err());
+    assert_eq!(suite.balance("funder"), 1_000_000);
+}
+
+fn native_reward(amount: u128) -> NativeReward {
+    NativeReward {
+        denom: LUNA.to_string(),
+        amount: Uint128::from(amount),
+    }
+}
@@ -0,0 +1,257 @@
+use crate::contract::{execute, instantiate, query};
+use crate::state::TOTAL_WEIGHT;
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
+use cosmwasm_std::testing::{
+    message_info, mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{
+    coins, from_json, to_json_binary, Addr, BankMsg, ContractResult, CosmosMsg, Decimal256, Env,
+    Order, OwnedDeps, StdResult, SystemResult, Uint128,
+};
+use cw4::{MemberChangedHookMsg, MemberDiff, MemberListResponse};
+use funds_distributor_api::api::{
+    ClaimRewardsMsg, UpdateMinimumEligibleWeightMsg, UserRewardsParams, UserRewardsResponse,
+};
+use funds_distributor_api::error::DistributorError::ZeroTotalWeight;
+use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use funds_distributor_model::model::DistributorModel;
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+
+const LUNA: &str = "uluna";
+const USERS: usize = 5;
+
+#[derive(Clone, Debug)]
+enum Op {
+    UpdateWeight { user: usize, weight: Option<u64> },
+    UpdateMinimumEligibleWeight(u64),
+    Distribute(u128),
+    Claim { user: usize },
+    NextBlock,
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        3 => (0..USERS, proptest::option::weighted(0.8, 0..100u64))
+            .prop_map(|(user, weight)| Op::UpdateWeight { user, weight }),
+        1 => (0..50u64).prop_map(Op::UpdateMinimumEligibleWeight),
+        3 => (1..1_000_000u128).prop_map(Op::Distribute),
+        2 => (0..USERS).prop_map(|user| Op::Claim { user }),
+        1 => Just(Op::NextBlock),
+    ]
+}
+
+fn user(i: usize) -> Addr {
+    MockApi::default().addr_make(&format!("user{i}"))
+}
+
+fn squad_contract() -> Addr {
+    MockApi::default().addr_make("squad_contract")
+}
+
+fn mock_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(|_| {
+        let response = to_json_binary(&MemberListResponse { members: vec![] });
+        SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+    });
+    deps
+}
+
+fn query_reward(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, env: &Env, i: usize) -> u128 {
+    let response: UserRewardsResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::UserRewards(UserRewardsParams {
+                user: user(i).to_string(),
+                native_denoms: Some(vec![LUNA.to_string()]),
+            }),
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    response.native_rewards[0].amount.u128()
+}
+
+fn run(ops: Vec<Op>) -> Result<(), TestCaseError> {
+    let mut deps = mock_deps();
+    let mut env = mock_env();
+    let squad = squad_contract();
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&squad, &[]),
+        InstantiateMsg {
+            squad_contract: squad.to_string(),
+            minimum_eligible_weight: None,
+            state_import: None,
+        },
+    )
+    .unwrap();
+
+    let mut model = DistributorModel::new(Uint128::zero());
+    // how many times each user's rewards were settled, each settlement rounding down the user's
+    // rewards by less than one unit
+    let mut settlements: BTreeMap<usize, u128> = BTreeMap::new();
+    // what each user claimed from the contract, and would have claimed from the model
+    let mut claimed_by: BTreeMap<usize, u128> = BTreeMap::new();
+    let mut model_claimed_by: BTreeMap<usize, u128> = BTreeMap::new();
+    let mut total_distributed = 0u128;
+    let mut total_claimed = 0u128;
+
+    for op in ops {
+        match op {
+            Op::UpdateWeight { user: i, weight } => {
+                execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    message_info(&squad, &[]),
+                    ExecuteMsg::MemberChangedHook(MemberChangedHookMsg {
+                        diffs: vec![MemberDiff::new(user(i), None, weight)],
+                    }),
+                )
+                .unwrap();
+
+                model.update_user_weight(user(i).as_str(), weight.map(Uint128::from));
+                *settlements.entry(i).or_default() += 1;
+            }
+            Op::UpdateMinimumEligibleWeight(minimum_eligible_weight) => {
+                execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    message_info(&squad, &[]),
+                    ExecuteMsg::UpdateMinimumEligibleWeight(UpdateMinimumEligibleWeightMsg {
+                        minimum_eligible_weight: Uint128::from(minimum_eligible_weight),
+                    }),
+                )
+                .unwrap();
+
+                model.update_minimum_eligible_weight(Uint128::from(minimum_eligible_weight));
+                (0..USERS).for_each(|i| *settlements.entry(i).or_default() += 1);
+            }
+            Op::Distribute(amount) => {
+                let result = execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    message_info(
+                        &MockApi::default().addr_make("funder"),
+                        &coins(amount, LUNA),
+                    ),
+                    ExecuteMsg::DistributeNative {},
+                );
+
+                if model.total_weight().is_zero() {
+                    prop_assert_eq!(result, Err(ZeroTotalWeight));
+                    prop_assert!(model.distribute(&coins(amount, LUNA)).is_err());
+                } else {
+                    result.unwrap();
+                    model.distribute(&coins(amount, LUNA))?;
+                    total_distributed += amount;
+                }
+            }
+            Op::Claim { user: i } => {
+                let response = execute(
+                    deps.as_mut(),
+                    env.clone(),
+                    message_info(&user(i), &[]),
+                    ExecuteMsg::ClaimRewards(ClaimRewardsMsg {
+                        user: user(i).to_string(),
+                        native_denoms: None,
+                        compound_to: None,
+                    }),
+                )
+                .unwrap();
+
+                let claimed: u128 = response
+                    .messages
+                    .iter()
+                    .filter_map(|submsg| match &submsg.msg {
+                        CosmosMsg::Bank(BankMsg::Send { amount, .. }) => Some(amount),
+                        _ => None,
+                    })
+                    .flatten()
+                    .map(|coin| coin.amount.u128())
+                    .sum();
+
+                let model_claimed: u128 = model
+                    .claim(user(i).as_str())
+                    .iter()
+                    .map(|coin| coin.amount.u128())
+                    .sum();
+
+                *claimed_by.entry(i).or_default() += claimed;
+                *model_claimed_by.entry(i).or_default() += model_claimed;
+                *settlements.entry(i).or_default() += 1;
+
+                total_claimed += claimed;
+            }
+            Op::NextBlock => {
+                env.block.height += 1;
+                env.block.time = env.block.time.plus_seconds(5);
+            }
+        }
+
+        // TOTAL_WEIGHT always equals the sum of the effective weights
+        let total_weight = TOTAL_WEIGHT.load(&deps.storage).unwrap();
+        let effective_weights_sum = EFFECTIVE_USER_WEIGHTS
+            .range(&deps.storage, None, None, Order::Ascending)
+            .map(|weight| weight.map(|(_, weight)| weight))
+            .sum::<StdResult<Uint128>>()
+            .unwrap();
+        prop_assert_eq!(total_weight, effective_weights_sum);
+        prop_assert_eq!(total_weight, model.total_weight());
+
+        let mut total_pending = 0u128;
+
+        for i in 0..USERS {
+            let reward = query_reward(&deps, &env, i);
+            total_pending += reward;
+
+            // rewards claimed and pending are never above the exact ones, and are only off by
+            // what was rounded down in settlements
+            let model_reward = model.exact_user_reward(user(i).as_str(), LUNA)
+                + Decimal256::from_atomics(
+                    model_claimed_by.get(&i).copied().unwrap_or_default(),
+                    0,
+                )
+                .unwrap();
+            let reward = Decimal256::from_atomics(
+                reward + claimed_by.get(&i).copied().unwrap_or_default(),
+                0,
+            )
+            .unwrap();
+            let settlements = settlements.get(&i).copied().unwrap_or_default();
+            prop_assert!(
+                reward <= model_reward,
+                "user{} has {} rewards claimed and pending, over the exact {}",
+                i,
+                reward,
+                model_reward
+            );
+            prop_assert!(
+                model_reward - reward < Decimal256::from_atomics(settlements + 1, 0).unwrap(),
+                "user{} has {} rewards claimed and pending, too far under the exact {}",
+                i,
+                reward,
+                model_reward
+            );
+        }
+
+        // the contract never owes more than it was given
+        prop_assert!(total_claimed + total_pending <= total_distributed);
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn reward_accounting_invariants_hold(ops in prop::collection::vec(op(), 1..60)) {
+        run(ops)?;
+    }
+}
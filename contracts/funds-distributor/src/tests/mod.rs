@@ -1 +1,3 @@
+mod integration;
+mod invariants;
 mod unit;
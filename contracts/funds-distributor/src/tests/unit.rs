@@ -1,27 +1,69 @@
-use crate::contract::{execute, instantiate};
+use crate::contract::{execute, instantiate, query, reply};
+use crate::hooks::HOOK_REPLY_ID;
+use crate::native_distributions::{NativeDistribution, NATIVE_DISTRIBUTIONS};
 use crate::rewards::query_user_rewards;
-use common::cw::testing::{mock_ctx, mock_info};
+use crate::snapshot_distributions::{MAX_SNAPSHOT_SETTLEMENTS, USER_SNAPSHOT_CURSORS};
+use common::cw::testing::{mock_ctx, mock_info, MOCK_CONTRACT_ADDR};
 use common::cw::{Context, QueryContext};
-use cosmwasm_std::testing::mock_dependencies;
-use cosmwasm_std::{coins, Coin, Response, Uint128};
-use cw4::{MemberChangedHookMsg, MemberDiff};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    attr, coin, coins, from_json, to_json_binary, wasm_execute, Addr, BankMsg, Binary, Coin,
+    ContractResult, CosmosMsg, Decimal, FullDelegation, Order, OwnedDeps, Reply, Response,
+    StdResult, SubMsg, SubMsgResult, SystemError, SystemResult, Uint128, WasmMsg, WasmQuery,
+};
+use cw2::{get_contract_version, set_contract_version};
+use cw4::{Member, MemberChangedHookMsg, MemberDiff, MemberListResponse};
+use cw_storage_plus::{Item, Map};
+use std::collections::HashMap;
+use std::str::FromStr;
 
+use cw_utils::Expiration;
 use funds_distributor_api::api::{
-    ClaimRewardsMsg, NativeReward, UpdateMinimumEligibleWeightMsg, UserRewardsParams, UserWeight,
+    AddHookMsg, AddWeightSourceMsg, AllowedDenom, ChangeWeightSourceMsg, ClaimHookMsg,
+    ClaimMerkleMsg, ClaimOperatorsParams, ClaimOperatorsResponse, ClaimRecipient, ClaimRewardsMsg,
+    CompoundTarget, CompoundingTargetsResponse, DelegateCommission, DelegateParams,
+    DelegateResponse, DenomDistributionStats, DistributionHookMsg, DistributionStatsParams,
+    DistributionStatsResponse, EligibleMemberCountResponse, ExportStateParams, ExportStateResponse,
+    ExportedCommission, ExportedUser, FundsDistributorHookMsg, GrantClaimOperatorMsg,
+    HookFailurePolicy, HooksResponse, ImportStateMsg, MinimumEligibleWeightResponse, NativeReward,
+    ProtocolFee, RegisterMerkleDistributionMsg, RemoveHookMsg, RemoveWeightSourceMsg,
+    RetireDenomMsg, RevokeClaimOperatorMsg, RewardSplit, RewardTier, RewardTierResponse,
+    RewardTiersResponse, SetDelegateMsg, SetRewardRecipientMsg, SetRewardSplitMsg,
+    StakeChangedHookMsg, SweepMerkleDistributionMsg, TotalWeightAtParams, TotalWeightResponse,
+    UpdateAuthorizedDistributorsMsg, UpdateCompoundingTargetsMsg, UpdateDenomAllowlistMsg,
+    UpdateGroupMembersMsg, UpdateMinimumEligibleWeightMsg, UpdateNativeStakeWeightsMsg,
+    UpdateProtocolFeesMsg, UpdateRewardTiersMsg, UserRewardsParams, UserRewardsResponse,
+    UserWeightAtParams, UserWeightResponse, WeightSource, WeightSourceKind, WeightSourcesResponse,
+};
+use funds_distributor_api::error::DistributorError::{
+    CompoundingTargetNotAllowed, DenomNotDistributed, DenomNotFullyClaimed,
+    DenomRetirementInProgress, FeeCollectorNotSet, HookAlreadyRegistered, InsolventStateImport,
+    InsufficientStake, InvalidContractVersion, InvalidDelegateCommission, InvalidExpiration,
+    InvalidMerkleDistributionFunds, InvalidMerkleProof, InvalidMigrationContract,
+    InvalidProtocolFee, InvalidRewardSplit, InvalidRewardTiers, InvalidSnapshotHeight,
+    MerkleAlreadyClaimed, MerkleDistributionExpired, MerkleDistributionNotExpired,
+    MerkleDistributionSwept, MigrationDowngrade, NativeStakingSourceNotRegistered,
+    NoEligibleMembers, NoFundsToDistribute, RewardTiersUpdateInProgress, SelfDelegation,
+    StateImportClosed, StateImportOpen, Unauthorized, UserAlreadyImported,
+    WeightSourceAlreadyRegistered, WeightSourceChangeInProgress, WeightSourceNotRegistered,
+    ZeroTotalWeight,
 };
-use funds_distributor_api::error::DistributorError::ZeroTotalWeight;
 use funds_distributor_api::error::DistributorResult;
-use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg};
+use funds_distributor_api::merkle::{
+    build_tree, encode_hash, leaf_hash, parent_hash, tree_proof, tree_root,
+};
+use funds_distributor_api::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use itertools::Itertools;
 
 const SQUAD_CONTRACT: &str = "enterprise_contract";
+const STAKING_CONTRACT: &str = "staking_contract";
 
 const LUNA: &str = "uluna";
-const CW20_TOKEN: &str = "cw20_token";
 
 #[test]
 pub fn no_rewards_after_instantiate() -> DistributorResult<()> {
-    let mut deps = mock_dependencies();
+    let mut deps = mock_deps();
     let ctx = &mut mock_ctx(deps.as_mut());
 
     instantiate_default(ctx)?;
@@ -29,7 +71,7 @@ pub fn no_rewards_after_instantiate() -> DistributorResult<()> {
     let user_rewards = query_user_rewards(
         ctx.to_query(),
         UserRewardsParams {
-            user: "user".to_string(),
+            user: addr("user"),
             native_denoms: Some(vec!["uluna".to_string()]),
         },
     )?;
@@ -41,7 +83,7 @@ pub fn no_rewards_after_instantiate() -> DistributorResult<()> {
 
 #[test]
 pub fn distribute_native_zero_total_weight_fails() -> DistributorResult<()> {
-    let mut deps = mock_dependencies();
+    let mut deps = mock_deps();
     let ctx = &mut mock_ctx(deps.as_mut());
 
     instantiate_default(ctx)?;
@@ -406,123 +448,3411 @@ pub fn distribute_native_zero_total_weight_fails() -> DistributorResult<()> {
 //     Ok(())
 // }
 
-///////////////////////
-/////// HELPERS ///////
-///////////////////////
+#[test]
+pub fn claim_without_compound_to_sends_rewards_to_user() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
 
-fn instantiate_default(ctx: &mut Context) -> DistributorResult<()> {
-    instantiate(
-        ctx.deps.branch(),
-        ctx.env.clone(),
-        ctx.info.clone(),
-        InstantiateMsg {
-            squad_contract: SQUAD_CONTRACT.to_string(),
-            minimum_eligible_weight: None,
-        },
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    let response = claim(ctx, "user", vec![LUNA])?;
+
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: addr("user"),
+            amount: coins(30, LUNA),
+        })],
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn claim_with_compound_to_sends_rewards_to_target() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_compounding_targets(ctx, &addr(SQUAD_CONTRACT), vec![STAKING_CONTRACT], vec![])?;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    let bond_msg = Binary::from(br#"{"bond":{}}"#);
+    let response = claim_and_compound(ctx, "user", "user", STAKING_CONTRACT, bond_msg.clone())?;
+
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: addr(STAKING_CONTRACT),
+            msg: bond_msg,
+            funds: coins(30, LUNA),
+        }))],
+    );
+
+    assert_user_rewards(ctx, "user", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn claim_without_rewards_notifies_hooks_without_sending_funds() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "points",
+        HookFailurePolicy::Abort,
     )?;
+    update_compounding_targets(ctx, &addr(SQUAD_CONTRACT), vec![STAKING_CONTRACT], vec![])?;
+
+    let claim_hook = |recipient: &str| {
+        FundsDistributorHookMsg::ClaimHook(ClaimHookMsg {
+            user: addr("user"),
+            recipients: vec![ClaimRecipient {
+                recipient: addr(recipient),
+                funds: vec![],
+            }],
+            funds: vec![],
+        })
+    };
+
+    // empty bank sends are rejected, so only the hooks are notified
+    let response = claim(ctx, "user", vec![LUNA])?;
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(wasm_execute(
+            addr("points"),
+            &claim_hook("user"),
+            vec![]
+        )?)],
+    );
+
+    // nor is anything compounded to the compounding targets
+    let bond_msg = Binary::from(br#"{"bond":{}}"#);
+    let response = claim_and_compound(ctx, "user", "user", STAKING_CONTRACT, bond_msg)?;
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(wasm_execute(
+            addr("points"),
+            &claim_hook(STAKING_CONTRACT),
+            vec![]
+        )?)],
+    );
+
+    let result = claim_and_compound(ctx, "user", "user", "other_staking", Binary::default());
+    assert_eq!(
+        result,
+        Err(CompoundingTargetNotAllowed {
+            contract: addr("other_staking")
+        })
+    );
+
     Ok(())
 }
 
-fn native_reward(denom: impl Into<String>, amount: impl Into<Uint128>) -> NativeReward {
-    NativeReward {
-        denom: denom.into(),
-        amount: amount.into(),
-    }
+#[test]
+pub fn claim_with_compound_to_not_allowed_target_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    let result = claim_and_compound(ctx, "user", "user", STAKING_CONTRACT, Binary::default());
+
+    assert_eq!(
+        result,
+        Err(CompoundingTargetNotAllowed {
+            contract: addr(STAKING_CONTRACT)
+        })
+    );
+
+    Ok(())
 }
 
-fn distribute_native(ctx: &mut Context, funds: &[Coin]) -> DistributorResult<Response> {
-    execute(
-        ctx.deps.branch(),
-        ctx.env.clone(),
-        mock_info(ctx.info.sender.as_ref(), funds),
-        ExecuteMsg::DistributeNative {},
-    )
+#[test]
+pub fn claim_with_compound_to_for_another_user_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_compounding_targets(ctx, &addr(SQUAD_CONTRACT), vec![STAKING_CONTRACT], vec![])?;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    let result = claim_and_compound(ctx, "other", "user", STAKING_CONTRACT, Binary::default());
+
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
 }
 
-fn claim(
-    ctx: &mut Context,
-    user: &str,
-    native_denoms: Vec<impl Into<String>>,
-    _cw20_assets: Vec<impl Into<String>>,
-) -> DistributorResult<Response> {
-    execute(
-        ctx.deps.branch(),
+#[test]
+pub fn update_compounding_targets_by_non_squad_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = update_compounding_targets(ctx, "not_squad", vec![STAKING_CONTRACT], vec![]);
+
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
+}
+
+#[test]
+pub fn update_compounding_targets_adds_and_removes_targets() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_compounding_targets(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![STAKING_CONTRACT, "other_staking"],
+        vec![],
+    )?;
+    update_compounding_targets(ctx, &addr(SQUAD_CONTRACT), vec![], vec!["other_staking"])?;
+
+    let response: CompoundingTargetsResponse = from_json(query(
+        ctx.deps.as_ref(),
         ctx.env.clone(),
-        ctx.info.clone(),
-        ExecuteMsg::ClaimRewards(ClaimRewardsMsg {
-            user: user.to_string(),
-            native_denoms: Some(
-                native_denoms
-                    .into_iter()
-                    .map(|denom| denom.into())
-                    .collect_vec(),
+        QueryMsg::CompoundingTargets {},
+    )?)?;
+
+    assert_eq!(response.targets, vec![addr(STAKING_CONTRACT)]);
+
+    Ok(())
+}
+
+#[test]
+pub fn claim_operator_can_compound_and_set_recipient_for_user() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_compounding_targets(ctx, &addr(SQUAD_CONTRACT), vec![STAKING_CONTRACT], vec![])?;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    grant_claim_operator(ctx, "user", "keeper", None)?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    let bond_msg = Binary::from(br#"{"bond":{}}"#);
+    let response = claim_and_compound(ctx, "keeper", "user", STAKING_CONTRACT, bond_msg.clone())?;
+
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: addr(STAKING_CONTRACT),
+            msg: bond_msg,
+            funds: coins(30, LUNA),
+        }))],
+    );
+
+    set_reward_recipient(ctx, "keeper", "user", Some("vault"))?;
+
+    distribute_native(ctx, &coins(20, LUNA))?;
+
+    let response = claim(ctx, "user", vec![LUNA])?;
+
+    assert_eq!(
+        response.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: addr("vault"),
+            amount: coins(20, LUNA),
+        })],
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn expired_or_revoked_claim_operator_is_unauthorized() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let expires_at = ctx.env.block.height + 10;
+    grant_claim_operator(
+        ctx,
+        "user",
+        "keeper",
+        Some(Expiration::AtHeight(expires_at)),
+    )?;
+    grant_claim_operator(ctx, "user", "other_keeper", None)?;
+
+    assert_eq!(
+        query_claim_operators(ctx, "user")?,
+        vec![addr("keeper"), addr("other_keeper")]
+            .into_iter()
+            .sorted()
+            .collect_vec(),
+    );
+
+    ctx.env.block.height = expires_at;
+
+    let result = set_reward_recipient(ctx, "keeper", "user", Some("keeper"));
+    assert_eq!(result, Err(Unauthorized));
+
+    revoke_claim_operator(ctx, "user", "other_keeper")?;
+
+    let result = set_reward_recipient(ctx, "other_keeper", "user", Some("other_keeper"));
+    assert_eq!(result, Err(Unauthorized));
+
+    assert!(query_claim_operators(ctx, "user")?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+pub fn grant_claim_operator_with_past_expiration_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let expires_at = ctx.env.block.height;
+    let result = grant_claim_operator(
+        ctx,
+        "user",
+        "keeper",
+        Some(Expiration::AtHeight(expires_at)),
+    );
+
+    assert_eq!(result, Err(InvalidExpiration));
+
+    Ok(())
+}
+
+#[test]
+pub fn distributions_and_claims_notify_hooks() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "indexer",
+        HookFailurePolicy::Ignore,
+    )?;
+    add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "points",
+        HookFailurePolicy::Abort,
+    )?;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    let response = distribute_native(ctx, &coins(30, LUNA))?;
+
+    let distribution_hook = FundsDistributorHookMsg::DistributionHook(DistributionHookMsg {
+        distributor: ctx.info.sender.to_string(),
+        funds: coins(30, LUNA),
+        total_weight: Uint128::one(),
+        snapshot_height: None,
+        group_id: None,
+        eligible_member_count: None,
+    });
+    // hooks are notified in the order of their addresses
+    assert_eq!(
+        response.messages,
+        vec![
+            SubMsg::new(wasm_execute(addr("points"), &distribution_hook, vec![])?),
+            SubMsg::reply_on_error(
+                wasm_execute(addr("indexer"), &distribution_hook, vec![])?,
+                HOOK_REPLY_ID,
             ),
-        }),
-    )
+        ],
+    );
+
+    let response = claim(ctx, "user", vec![LUNA])?;
+
+    let claim_hook = FundsDistributorHookMsg::ClaimHook(ClaimHookMsg {
+        user: addr("user"),
+        recipients: vec![ClaimRecipient {
+            recipient: addr("user"),
+            funds: coins(30, LUNA),
+        }],
+        funds: coins(30, LUNA),
+    });
+    assert_eq!(
+        response.messages[1..],
+        vec![
+            SubMsg::new(wasm_execute(addr("points"), &claim_hook, vec![])?),
+            SubMsg::reply_on_error(
+                wasm_execute(addr("indexer"), &claim_hook, vec![])?,
+                HOOK_REPLY_ID,
+            ),
+        ],
+    );
+
+    Ok(())
 }
 
-fn user_weight(user: impl Into<String>, weight: impl Into<Uint128>) -> UserWeight {
-    UserWeight {
-        user: user.into(),
-        weight: weight.into(),
-    }
+#[test]
+pub fn add_hook_by_non_squad_or_twice_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = add_hook(ctx, "not_squad", "indexer", HookFailurePolicy::Ignore);
+    assert_eq!(result, Err(Unauthorized));
+
+    add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "indexer",
+        HookFailurePolicy::Ignore,
+    )?;
+
+    let result = add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "indexer",
+        HookFailurePolicy::Abort,
+    );
+    assert_eq!(
+        result,
+        Err(HookAlreadyRegistered {
+            addr: addr("indexer")
+        })
+    );
+
+    Ok(())
 }
 
-fn update_user_weights(
-    ctx: &mut Context,
-    sender: &str,
-    new_user_weights: Vec<MemberDiff>,
-) -> DistributorResult<Response> {
+#[test]
+pub fn removed_hooks_are_not_notified() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "indexer",
+        HookFailurePolicy::Ignore,
+    )?;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
     execute(
         ctx.deps.branch(),
         ctx.env.clone(),
-        mock_info(sender, &[]),
-        ExecuteMsg::MemberChangedHook(MemberChangedHookMsg {
-            diffs: new_user_weights,
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::RemoveHook(RemoveHookMsg {
+            addr: addr("indexer"),
         }),
-    )
+    )?;
+
+    let response = distribute_native(ctx, &coins(30, LUNA))?;
+    assert!(response.messages.is_empty());
+
+    let hooks: HooksResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::Hooks {},
+    )?)?;
+    assert!(hooks.hooks.is_empty());
+
+    Ok(())
 }
 
-fn update_minimum_eligible_weight(
-    ctx: &mut Context,
-    sender: &str,
-    new_minimum_eligible_weight: impl Into<Uint128>,
-) -> DistributorResult<Response> {
-    execute(
+#[test]
+pub fn failed_ignored_hook_reply_succeeds() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    let response = reply(
         ctx.deps.branch(),
         ctx.env.clone(),
-        mock_info(sender, &[]),
-        ExecuteMsg::UpdateMinimumEligibleWeight(UpdateMinimumEligibleWeightMsg {
-            minimum_eligible_weight: new_minimum_eligible_weight.into(),
-        }),
-    )
+        Reply {
+            id: HOOK_REPLY_ID,
+            payload: Binary::default(),
+            gas_used: 0,
+            result: SubMsgResult::Err("indexer is down".to_string()),
+        },
+    )?;
+
+    assert_eq!(
+        response.attributes,
+        vec![
+            attr("action", "hook_failed"),
+            attr("error", "indexer is down")
+        ]
+    );
+
+    Ok(())
 }
 
-fn assert_user_rewards(
-    ctx: &mut Context,
-    user: &str,
-    native_denoms: Vec<impl Into<String>>,
-    cw20_assets: Vec<impl Into<String>>,
-    expected_native_rewards: Vec<NativeReward>,
-) -> DistributorResult<()> {
-    let qctx = QueryContext {
-        deps: ctx.deps.as_ref(),
-        env: ctx.env.clone(),
-    };
+#[test]
+pub fn user_weights_are_summed_across_scaled_weight_sources() -> DistributorResult<()> {
+    let mut deps = mock_deps_with_members(&[
+        (SQUAD_CONTRACT, &[("user1", 1)]),
+        ("stakers", &[("user1", 1), ("user2", 1)]),
+    ]);
+    let ctx = &mut mock_ctx(deps.as_mut());
 
-    let native_denoms = native_denoms
-        .into_iter()
-        .map(|denom| denom.into())
-        .collect_vec();
-    let _cw20_assets = cw20_assets
-        .into_iter()
-        .map(|asset| asset.into())
+    instantiate_default(ctx)?;
+
+    add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        cw4_source("stakers"),
+        Decimal::percent(200),
+    )?;
+
+    distribute_native(ctx, &coins(50, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 30u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 20u8)])?;
+
+    // stakers' hook only changes their component of user1's weight
+    update_user_weights(ctx, &addr("stakers"), vec![user_weight("user1", 0u64)])?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 40u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 40u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn removing_weight_source_removes_its_weights() -> DistributorResult<()> {
+    let mut deps = mock_deps_with_members(&[
+        (SQUAD_CONTRACT, &[("user1", 1)]),
+        ("stakers", &[("user1", 1), ("user2", 1)]),
+    ]);
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        cw4_source("stakers"),
+        Decimal::one(),
+    )?;
+
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::RemoveWeightSource(RemoveWeightSourceMsg {
+            addr: addr("stakers"),
+        }),
+    )?;
+
+    distribute_native(ctx, &coins(30, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 30u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    let result = update_user_weights(ctx, &addr("stakers"), vec![user_weight("user2", 1u64)]);
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
+}
+
+#[test]
+pub fn add_weight_source_by_non_squad_or_twice_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = add_weight_source(ctx, "not_squad", cw4_source("stakers"), Decimal::one());
+    assert_eq!(result, Err(Unauthorized));
+
+    let result = add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        cw4_source(SQUAD_CONTRACT),
+        Decimal::one(),
+    );
+    assert_eq!(
+        result,
+        Err(WeightSourceAlreadyRegistered {
+            addr: addr(SQUAD_CONTRACT)
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn cw20_stake_hooks_update_user_weights() -> DistributorResult<()> {
+    let mut deps = mock_deps_with_members(&[("cw20_stake", &[("user1", 10)])]);
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        WeightSourceKind::Cw20Stake {
+            addr: addr("cw20_stake"),
+        },
+        Decimal::one(),
+    )?;
+
+    stake_hook(
+        ctx,
+        "cw20_stake",
+        StakeChangedHookMsg::Stake {
+            addr: Addr::unchecked(addr("user2")),
+            amount: 30u8.into(),
+        },
+    )?;
+
+    distribute_native(ctx, &coins(40, LUNA))?;
+
+    stake_hook(
+        ctx,
+        "cw20_stake",
+        StakeChangedHookMsg::Unstake {
+            addr: Addr::unchecked(addr("user2")),
+            amount: 30u8.into(),
+        },
+    )?;
+
+    distribute_native(ctx, &coins(40, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 30u8)])?;
+
+    let result = stake_hook(
+        ctx,
+        "cw20_stake",
+        StakeChangedHookMsg::Unstake {
+            addr: Addr::unchecked(addr("user1")),
+            amount: 11u8.into(),
+        },
+    );
+    assert_eq!(result, Err(InsufficientStake));
+
+    // cw4 sources cannot send stake hooks
+    let result = stake_hook(
+        ctx,
+        SQUAD_CONTRACT,
+        StakeChangedHookMsg::Stake {
+            addr: Addr::unchecked(addr("user1")),
+            amount: 1u8.into(),
+        },
+    );
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
+}
+
+#[test]
+pub fn native_stake_snapshots_update_user_weights() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let delegation = |user: &str, validator: &str, amount: u128| {
+        FullDelegation::create(
+            Addr::unchecked(addr(user)),
+            validator.to_string(),
+            coin(amount, LUNA),
+            coin(amount, LUNA),
+            vec![],
+        )
+    };
+    deps.querier.staking.update(
+        LUNA,
+        &[],
+        &[
+            delegation("user1", "validator1", 10),
+            delegation("user1", "validator2", 20),
+            delegation("user2", "validator1", 10),
+        ],
+    );
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = update_native_stake_weights(ctx, vec!["user1"]);
+    assert_eq!(result, Err(NativeStakingSourceNotRegistered));
+
+    add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        WeightSourceKind::NativeStaking {
+            validators: Some(vec!["validator1".to_string()]),
+        },
+        Decimal::one(),
+    )?;
+
+    update_native_stake_weights(ctx, vec!["user1", "user2", "user3"])?;
+
+    distribute_native(ctx, &coins(40, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 20u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 20u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn weights_are_queryable_at_past_heights() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let first_height = ctx.env.block.height;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    ctx.env.block.height += 10;
+    let second_height = ctx.env.block.height;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 3u64)])?;
+
+    // weights at a height are the ones from the beginning of that block
+    let user_weight_at = |ctx: &mut Context, height: u64| -> DistributorResult<Uint128> {
+        let response: UserWeightResponse = from_json(query(
+            ctx.deps.as_ref(),
+            ctx.env.clone(),
+            QueryMsg::UserWeightAt(UserWeightAtParams {
+                user: addr("user"),
+                height,
+            }),
+        )?)?;
+        Ok(response.effective_weight)
+    };
+    let total_weight_at = |ctx: &mut Context, height: u64| -> DistributorResult<Uint128> {
+        let response: TotalWeightResponse = from_json(query(
+            ctx.deps.as_ref(),
+            ctx.env.clone(),
+            QueryMsg::TotalWeightAt(TotalWeightAtParams { height }),
+        )?)?;
+        Ok(response.total_weight)
+    };
+
+    assert_eq!(user_weight_at(ctx, first_height)?, Uint128::zero());
+    assert_eq!(user_weight_at(ctx, first_height + 1)?, Uint128::one());
+    assert_eq!(user_weight_at(ctx, second_height)?, Uint128::one());
+    assert_eq!(user_weight_at(ctx, second_height + 1)?, Uint128::from(3u8));
+
+    assert_eq!(total_weight_at(ctx, first_height + 1)?, Uint128::one());
+    assert_eq!(total_weight_at(ctx, second_height + 1)?, Uint128::from(3u8));
+
+    Ok(())
+}
+
+#[test]
+pub fn distribute_native_at_height_splits_by_past_weights() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 3u64)],
+    )?;
+
+    ctx.env.block.height += 10;
+    let snapshot_height = ctx.env.block.height;
+
+    // weights change after the snapshot height
+    ctx.env.block.height += 10;
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 4u64), user_weight("user2", 0u64)],
+    )?;
+
+    distribute_native_at_height(ctx, &coins(100, LUNA), snapshot_height)?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 25u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 75u8)])?;
+
+    // later weight changes do not affect the shares
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 0u64)])?;
+
+    let response = claim(ctx, "user1", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user1"),
+            amount: coins(25, LUNA),
+        })
+    );
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    // shares are settled together with the regular rewards
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user2", 1u64)])?;
+    distribute_native(ctx, &coins(10, LUNA))?;
+
+    let response = claim(ctx, "user2", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user2"),
+            amount: coins(85, LUNA),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn distribute_native_at_future_or_zero_weight_height_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let instantiate_height = ctx.env.block.height;
+
+    ctx.env.block.height += 1;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    let future_height = ctx.env.block.height + 1;
+    let result = distribute_native_at_height(ctx, &coins(100, LUNA), future_height);
+    assert_eq!(
+        result,
+        Err(InvalidSnapshotHeight {
+            height: future_height
+        })
+    );
+
+    let result = distribute_native_at_height(ctx, &coins(100, LUNA), instantiate_height);
+    assert_eq!(result, Err(ZeroTotalWeight));
+
+    Ok(())
+}
+
+#[test]
+pub fn distribute_native_at_height_without_funds_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    let height = ctx.env.block.height;
+    let result = distribute_native_at_height(ctx, &[], height);
+    assert_eq!(result, Err(NoFundsToDistribute));
+
+    Ok(())
+}
+
+#[test]
+pub fn snapshot_distributions_are_settled_in_batches() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    ctx.env.block.height += 1;
+    let height = ctx.env.block.height;
+
+    for _ in 0..MAX_SNAPSHOT_SETTLEMENTS + 5 {
+        distribute_native_at_height(ctx, &coins(10, LUNA), height)?;
+    }
+
+    // rewards include a batch of the distributions at a time, as much as a claim settles
+    assert_user_rewards(ctx, "user", vec![LUNA], vec![native_reward(LUNA, 300u16)])?;
+    assert!(query_rewards(ctx, "user")?.snapshot_settlement_pending);
+
+    // users getting their first weight start after the existing distributions
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("new_user", 1u64)],
+    )?;
+    assert_eq!(
+        USER_SNAPSHOT_CURSORS.load(ctx.deps.storage, Addr::unchecked(addr("new_user")))?,
+        u64::from(MAX_SNAPSHOT_SETTLEMENTS + 5)
+    );
+    assert!(!query_rewards(ctx, "new_user")?.snapshot_settlement_pending);
+
+    let response = claim(ctx, "user", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user"),
+            amount: coins(300, LUNA),
+        })
+    );
+    assert_user_rewards(ctx, "user", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert!(!query_rewards(ctx, "user")?.snapshot_settlement_pending);
+
+    let response = claim(ctx, "user", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user"),
+            amount: coins(50, LUNA),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn merkle_distribution_entries_can_be_claimed_once() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let entries = [(addr("user1"), 10u128), (addr("user2"), 20u128)];
+    let leaves = entries
+        .iter()
+        .enumerate()
+        .map(|(index, (user, amount))| leaf_hash(index as u32, user, Uint128::from(*amount)))
+        .collect_vec();
+    let root = parent_hash(&leaves[0], &leaves[1]);
+
+    register_merkle_distribution(ctx, &encode_hash(&root), 40)?;
+
+    // proofs for another user's entry or amount are rejected
+    let result = claim_merkle(ctx, "user1", 1, 20, vec![encode_hash(&leaves[0])]);
+    assert_eq!(result, Err(InvalidMerkleProof));
+    let result = claim_merkle(ctx, "user1", 0, 15, vec![encode_hash(&leaves[1])]);
+    assert_eq!(result, Err(InvalidMerkleProof));
+
+    let response = claim_merkle(ctx, "user1", 0, 10, vec![encode_hash(&leaves[1])])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user1"),
+            amount: coins(10, LUNA),
+        })
+    );
+
+    let result = claim_merkle(ctx, "user1", 0, 10, vec![encode_hash(&leaves[1])]);
+    assert_eq!(result, Err(MerkleAlreadyClaimed { id: 1, index: 0 }));
+
+    // unclaimed funds can only be swept after the expiry
+    let sweep = |ctx: &mut Context| {
+        execute(
+            ctx.deps.branch(),
+            ctx.env.clone(),
+            mock_info(&addr(SQUAD_CONTRACT), &[]),
+            ExecuteMsg::SweepMerkleDistribution(SweepMerkleDistributionMsg { id: 1 }),
+        )
+    };
+
+    assert_eq!(sweep(ctx), Err(MerkleDistributionNotExpired { id: 1 }));
+
+    ctx.env.block.height += 100;
+
+    let result = claim_merkle(ctx, "user2", 1, 20, vec![encode_hash(&leaves[0])]);
+    assert_eq!(result, Err(MerkleDistributionExpired { id: 1 }));
+
+    let response = sweep(ctx)?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr(SQUAD_CONTRACT),
+            amount: coins(30, LUNA),
+        })
+    );
+
+    assert_eq!(sweep(ctx), Err(MerkleDistributionSwept { id: 1 }));
+
+    Ok(())
+}
+
+#[test]
+pub fn register_merkle_distribution_with_wrong_funds_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let root = encode_hash(&leaf_hash(0, &addr("user"), Uint128::one()));
+
+    let result = execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &coins(39, LUNA)),
+        ExecuteMsg::RegisterMerkleDistribution(RegisterMerkleDistributionMsg {
+            root,
+            denom: LUNA.to_string(),
+            total: Uint128::from(40u8),
+            expiry: Expiration::AtHeight(ctx.env.block.height + 100),
+        }),
+    );
+
+    assert_eq!(
+        result,
+        Err(InvalidMerkleDistributionFunds {
+            denom: LUNA.to_string(),
+            total: Uint128::from(40u8),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn merkle_tree_builder_proofs_are_accepted() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    // an odd number of entries leaves the last one without a sibling
+    let entries = [
+        (addr("user1"), 10u128),
+        (addr("user2"), 20u128),
+        (addr("user3"), 30u128),
+    ];
+    let leaves = entries
+        .iter()
+        .enumerate()
+        .map(|(index, (user, amount))| leaf_hash(index as u32, user, Uint128::from(*amount)))
+        .collect_vec();
+    let levels = build_tree(leaves);
+
+    register_merkle_distribution(ctx, &encode_hash(&tree_root(&levels).unwrap()), 60)?;
+
+    for (index, (user, amount)) in entries.iter().enumerate() {
+        let proof = tree_proof(&levels, index as u32)
+            .iter()
+            .map(encode_hash)
+            .collect_vec();
+
+        execute(
+            ctx.deps.branch(),
+            ctx.env.clone(),
+            mock_info(user, &[]),
+            ExecuteMsg::ClaimMerkle(ClaimMerkleMsg {
+                id: 1,
+                index: index as u32,
+                amount: Uint128::from(*amount),
+                proof,
+            }),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn merkle_leaves_with_different_entries_differ() -> DistributorResult<()> {
+    // the address and the amount can't be shifted into each other
+    assert_ne!(
+        leaf_hash(0, "user1", Uint128::from(23u8)),
+        leaf_hash(0, "user12", Uint128::from(3u8)),
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn distribute_native_to_group_rewards_only_group_members() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![
+            user_weight("user1", 1u64),
+            user_weight("user2", 3u64),
+            user_weight("user3", 4u64),
+        ],
+    )?;
+
+    update_group_members(ctx, "devs", vec!["user1", "user2"], vec![])?;
+
+    distribute_native_to_group(ctx, &coins(100, LUNA), "devs")?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 25u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 75u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    // group total weight follows the members' weight changes
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user2", 1u64)])?;
+    distribute_native_to_group(ctx, &coins(20, LUNA), "devs")?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 35u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 85u8)])?;
+
+    // removed members keep their rewards, but do not receive new ones
+    update_group_members(ctx, "devs", vec![], vec!["user1"])?;
+    distribute_native_to_group(ctx, &coins(10, LUNA), "devs")?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 35u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 95u8)])?;
+
+    let response = claim(ctx, "user1", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user1"),
+            amount: coins(35, LUNA),
+        })
+    );
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn group_distribution_edge_cases_fail() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    let result = execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr("user"), &[]),
+        ExecuteMsg::UpdateGroupMembers(UpdateGroupMembersMsg {
+            group_id: "devs".to_string(),
+            add: vec![addr("user")],
+            remove: vec![],
+        }),
+    );
+    assert_eq!(result, Err(Unauthorized));
+
+    let result = distribute_native_to_group(ctx, &coins(100, LUNA), "devs");
+    assert_eq!(result, Err(ZeroTotalWeight));
+
+    Ok(())
+}
+
+#[test]
+pub fn distribute_native_equally_splits_between_eligible_members() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = distribute_native_equally(ctx, &coins(100, LUNA));
+    assert_eq!(result, Err(NoEligibleMembers));
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![
+            user_weight("user1", 1u64),
+            user_weight("user2", 3u64),
+            user_weight("user3", 0u64),
+        ],
+    )?;
+    assert_eq!(query_eligible_member_count(ctx)?, 2);
+
+    distribute_native_equally(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    // users falling under the minimum eligible weight are no longer counted
+    update_minimum_eligible_weight(ctx, &addr(SQUAD_CONTRACT), 2u8)?;
+    assert_eq!(query_eligible_member_count(ctx)?, 1);
+
+    distribute_native_equally(ctx, &coins(30, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 80u8)])?;
+
+    // new members only receive distributions made after they joined
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user3", 5u64)])?;
+    assert_eq!(query_eligible_member_count(ctx)?, 2);
+
+    distribute_native_equally(ctx, &coins(10, LUNA))?;
+
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 85u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 5u8)])?;
+
+    let response = claim(ctx, "user2", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user2"),
+            amount: coins(85, LUNA),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn distribute_native_tiered_splits_across_and_within_tiers() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![
+            user_weight("user1", 2u64),
+            user_weight("user2", 3u64),
+            user_weight("user3", 10u64),
+        ],
+    )?;
+
+    update_reward_tiers(
+        ctx,
+        vec![
+            reward_tier("bronze", 1, "0.25"),
+            reward_tier("silver", 5, "0.75"),
+        ],
+    )?;
+
+    distribute_native_tiered(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 10u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 15u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 75u8)])?;
+
+    // user2 moves up to the silver tier
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user2", 5u64)])?;
+
+    distribute_native_tiered(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 35u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 40u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 125u8)])?;
+
+    // tiers without any users are skipped
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 0u64)])?;
+
+    distribute_native_tiered(ctx, &coins(30, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 35u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 145u8)])?;
+
+    let response = claim(ctx, "user3", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user3"),
+            amount: coins(145, LUNA),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn update_reward_tiers_with_invalid_tiers_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = update_reward_tiers(
+        ctx,
+        vec![
+            reward_tier("bronze", 1, "0.25"),
+            reward_tier("silver", 5, "0.5"),
+        ],
+    );
+    assert!(matches!(result, Err(InvalidRewardTiers { .. })));
+
+    let result = update_reward_tiers(
+        ctx,
+        vec![
+            reward_tier("bronze", 5, "0.25"),
+            reward_tier("silver", 5, "0.75"),
+        ],
+    );
+    assert!(matches!(result, Err(InvalidRewardTiers { .. })));
+
+    // the lowest tier holds the minimum eligible weight
+    let result = update_reward_tiers(ctx, vec![]);
+    assert!(matches!(result, Err(InvalidRewardTiers { .. })));
+
+    Ok(())
+}
+
+#[test]
+pub fn minimum_eligible_weight_is_lowest_reward_tier() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![
+            user_weight("user1", 1u64),
+            user_weight("user2", 3u64),
+            user_weight("user3", 6u64),
+        ],
+    )?;
+
+    // contracts start out with a single tier holding every eligible user
+    assert_eq!(
+        query_reward_tiers(ctx)?,
+        vec![RewardTierResponse {
+            name: "default".to_string(),
+            min_weight: Uint128::zero(),
+            share: Decimal::one(),
+            total_weight: Uint128::new(10),
+        }]
+    );
+
+    // raising the lowest tier's minimum leaves user1 ineligible for any distribution
+    update_reward_tiers(
+        ctx,
+        vec![
+            reward_tier("bronze", 2, "0.5"),
+            reward_tier("silver", 5, "0.5"),
+        ],
+    )?;
+    assert_eq!(query_minimum_eligible_weight(ctx)?, Uint128::new(2));
+    assert_eq!(query_eligible_member_count(ctx)?, 2);
+
+    distribute_native(ctx, &coins(90, LUNA))?;
+    distribute_native_tiered(ctx, &coins(60, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 60u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 90u8)])?;
+
+    // the minimum eligible weight moves the lowest tier's minimum, and its users with it
+    update_minimum_eligible_weight(ctx, &addr(SQUAD_CONTRACT), 1u8)?;
+
+    let tiers = query_reward_tiers(ctx)?;
+    assert_eq!(tiers[0].min_weight, Uint128::one());
+    assert_eq!(tiers[0].total_weight, Uint128::new(4));
+    assert_eq!(tiers[1].total_weight, Uint128::new(6));
+
+    distribute_native_tiered(ctx, &coins(80, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 10u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 90u8)])?;
+
+    // but cannot reach the next tier's minimum
+    let result = update_minimum_eligible_weight(ctx, &addr(SQUAD_CONTRACT), 5u8);
+    assert!(matches!(result, Err(InvalidRewardTiers { .. })));
+
+    Ok(())
+}
+
+#[test]
+pub fn update_reward_tiers_moves_users_page_by_page() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![
+            user_weight("user1", 2u64),
+            user_weight("user2", 3u64),
+            user_weight("user3", 10u64),
+        ],
+    )?;
+
+    update_reward_tiers(
+        ctx,
+        vec![
+            reward_tier("bronze", 1, "0.25"),
+            reward_tier("silver", 5, "0.75"),
+        ],
+    )?;
+
+    distribute_native_tiered(ctx, &coins(100, LUNA))?;
+
+    // user2 moves up to the silver tier, the others stay in theirs
+    let new_tiers = vec![
+        reward_tier("bronze", 1, "0.25"),
+        reward_tier("silver", 3, "0.75"),
+    ];
+
+    let response = update_reward_tiers_page(ctx, new_tiers.clone(), Some(3))?;
+    assert_eq!(attribute_values(&response, "moved"), vec![addr("user2")]);
+    assert_eq!(attribute_values(&response, "complete"), vec!["false"]);
+
+    let result = distribute_native_tiered(ctx, &coins(100, LUNA));
+    assert_eq!(result, Err(RewardTiersUpdateInProgress));
+
+    let result = update_reward_tiers_page(ctx, vec![reward_tier("gold", 1, "1")], Some(3));
+    assert_eq!(result, Err(RewardTiersUpdateInProgress));
+
+    let response = update_reward_tiers_page(ctx, new_tiers, Some(3))?;
+    assert_eq!(attribute_values(&response, "moved"), Vec::<String>::new());
+    assert_eq!(attribute_values(&response, "complete"), vec!["true"]);
+
+    distribute_native_tiered(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 35u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 32u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 132u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn distributions_of_denoms_outside_allowlist_are_refunded() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::UpdateDenomAllowlist(UpdateDenomAllowlistMsg {
+            enabled: Some(true),
+            add: vec![
+                AllowedDenom {
+                    denom: LUNA.to_string(),
+                    min_amount: Uint128::from(10u8),
+                },
+                AllowedDenom {
+                    denom: "uatom".to_string(),
+                    min_amount: Uint128::from(10u8),
+                },
+            ],
+            remove: vec![],
+        }),
+    )?;
+
+    let distributor = addr("distributor");
+    ctx.info = mock_info(&distributor, &[]);
+
+    let response = distribute_native(ctx, &[coin(100, LUNA), coin(50, "ujunk")])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: distributor.clone(),
+            amount: coins(50, "ujunk"),
+        })
+    );
+
+    // amounts under the minimum are refunded as well
+    let response = distribute_native(ctx, &[coin(100, LUNA), coin(9, "uatom")])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: distributor,
+            amount: coins(9, "uatom"),
+        })
+    );
+
+    // distributions without any accepted funds fail instead of being stored empty
+    let result = distribute_native(ctx, &coins(9, LUNA));
+    assert_eq!(result, Err(NoFundsToDistribute));
+
+    // rejected denoms are not tracked
+    assert_eq!(
+        query_all_user_rewards(ctx, "user")?,
+        vec![native_reward(LUNA, 200u8)]
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn retire_denom_only_after_fully_claimed() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 1u64)],
+    )?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // user2 leaves with unclaimed rewards
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user2", 0u64)])?;
+    claim(ctx, "user1", vec![LUNA])?;
+
+    let result = retire_denom(ctx, LUNA, None);
+    assert_eq!(
+        result,
+        Err(DenomNotFullyClaimed {
+            denom: LUNA.to_string()
+        })
+    );
+
+    claim(ctx, "user2", vec![LUNA])?;
+
+    retire_denom(ctx, LUNA, None)?;
+
+    assert_eq!(query_all_user_rewards(ctx, "user1")?, vec![]);
+
+    let result = retire_denom(ctx, LUNA, None);
+    assert_eq!(
+        result,
+        Err(DenomNotDistributed {
+            denom: LUNA.to_string()
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn retire_denom_waits_for_unsettled_delegate_commissions() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 1u64)])?;
+    set_delegate(ctx, "user1", "delegate", 10_000)?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // user1 is owed nothing, but their delegate is yet to be credited the whole reward
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    let result = retire_denom(ctx, LUNA, None);
+    assert_eq!(
+        result,
+        Err(DenomNotFullyClaimed {
+            denom: LUNA.to_string()
+        })
+    );
+
+    claim(ctx, "user1", vec![LUNA])?;
+
+    let result = retire_denom(ctx, LUNA, None);
+    assert_eq!(
+        result,
+        Err(DenomNotFullyClaimed {
+            denom: LUNA.to_string()
+        })
+    );
+
+    claim(ctx, "delegate", vec![LUNA])?;
+
+    retire_denom(ctx, LUNA, None)?;
+
+    Ok(())
+}
+
+#[test]
+pub fn retire_denom_resumes_page_by_page() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![
+            user_weight("user1", 1u64),
+            user_weight("user2", 1u64),
+            user_weight("user3", 1u64),
+        ],
+    )?;
+    update_group_members(ctx, "group", vec!["user3"], vec![])?;
+
+    distribute_native(ctx, &coins(90, LUNA))?;
+    distribute_native_to_group(ctx, &coins(10, LUNA), "group")?;
+
+    for user in ["user1", "user2", "user3"] {
+        claim(ctx, user, vec![LUNA])?;
+    }
+
+    let response = retire_denom(ctx, LUNA, Some(1))?;
+    assert_eq!(attribute_values(&response, "retired"), vec!["false"]);
+
+    // the denom cannot be distributed while it is being retired
+    let response = distribute_native(ctx, &[coin(30, LUNA), coin(30, "uatom")])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: ctx.info.sender.to_string(),
+            amount: coins(30, LUNA),
+        })
+    );
+
+    let result = retire_denom(ctx, "uatom", None);
+    assert_eq!(
+        result,
+        Err(DenomRetirementInProgress {
+            denom: LUNA.to_string()
+        })
+    );
+
+    let mut calls = 1;
+    while attribute_values(&retire_denom(ctx, LUNA, Some(1))?, "retired") == vec!["false"] {
+        calls += 1;
+
+        // users whose state was already removed are not owed anything
+        for user in ["user1", "user2", "user3"] {
+            assert_user_rewards(ctx, user, vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+        }
+    }
+    assert!(calls > 3);
+
+    let stored_distributions = NATIVE_DISTRIBUTIONS()
+        .range(ctx.deps.storage, None, None, Order::Ascending)
+        .filter(|distribution| {
+            distribution
+                .as_ref()
+                .is_ok_and(|(_, distribution)| distribution.denom == LUNA)
+        })
+        .count();
+    assert_eq!(stored_distributions, 0);
+
+    // once retired, the denom is distributed from scratch again
+    distribute_native(ctx, &coins(30, LUNA))?;
+    distribute_native_to_group(ctx, &coins(10, LUNA), "group")?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 10u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 20u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn only_authorized_distributors_can_distribute_once_configured() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::UpdateAuthorizedDistributors(UpdateAuthorizedDistributorsMsg {
+            add: vec![addr("treasury")],
+            remove: vec![],
+        }),
+    )?;
+
+    ctx.info = mock_info(&addr("griefer"), &[]);
+    let result = distribute_native(ctx, &coins(1, LUNA));
+    assert_eq!(result, Err(Unauthorized));
+
+    let result = distribute_native_equally(ctx, &coins(1, LUNA));
+    assert_eq!(result, Err(Unauthorized));
+
+    ctx.info = mock_info(&addr("treasury"), &[]);
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user", vec![LUNA], vec![native_reward(LUNA, 100u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn weight_changes_only_store_distributions_for_accrued_denoms() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 1u64)],
+    )?;
+
+    distribute_native(ctx, &[coin(100, "uatom"), coin(100, LUNA)])?;
+
+    // new users do not get any state for the existing denoms
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user3", 2u64)])?;
+    assert_eq!(
+        stored_distribution_denoms(ctx, "user3")?,
+        Vec::<String>::new()
+    );
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 2u64)])?;
+    assert_eq!(
+        stored_distribution_denoms(ctx, "user1")?,
+        vec!["uatom".to_string(), LUNA.to_string()]
+    );
+
+    distribute_native(ctx, &coins(50, "uosmo"))?;
+
+    // only the denoms distributed since the user's last update are touched
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user3", 1u64)])?;
+    assert_eq!(stored_distribution_denoms(ctx, "user3")?, vec!["uosmo"]);
+
+    assert_user_rewards(
+        ctx,
+        "user1",
+        vec!["uatom", LUNA, "uosmo"],
+        vec![
+            native_reward("uatom", 50u8),
+            native_reward(LUNA, 50u8),
+            native_reward("uosmo", 20u8),
+        ],
+    )?;
+    assert_user_rewards(
+        ctx,
+        "user3",
+        vec!["uatom", LUNA, "uosmo"],
+        vec![
+            native_reward("uatom", 0u8),
+            native_reward(LUNA, 0u8),
+            native_reward("uosmo", 20u8),
+        ],
+    )?;
+
+    Ok(())
+}
+
+#[test]
+pub fn protocol_fees_are_sent_to_fee_collector_before_distributing() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    update_protocol_fees(
+        ctx,
+        Some("fee_collector"),
+        vec![ProtocolFee {
+            denom: LUNA.to_string(),
+            fee_bps: 250,
+        }],
+    )?;
+
+    let response = distribute_native(ctx, &[coin(1000, LUNA), coin(100, "uatom")])?;
+
+    assert!(response
+        .attributes
+        .contains(&attr("protocol_fees", format!("25{}", LUNA))));
+    assert!(response
+        .attributes
+        .contains(&attr("fee_collector", addr("fee_collector"))));
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("fee_collector"),
+            amount: coins(25, LUNA),
+        })
+    );
+
+    assert_user_rewards(
+        ctx,
+        "user",
+        vec![LUNA, "uatom"],
+        vec![native_reward(LUNA, 975u16), native_reward("uatom", 100u8)],
+    )?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    let response: DistributionStatsResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::DistributionStats(DistributionStatsParams {
+            native_denoms: None,
+        }),
+    )?)?;
+    assert_eq!(
+        response.stats,
+        vec![
+            DenomDistributionStats {
+                denom: "uatom".to_string(),
+                distributed: Uint128::from(100u8),
+                protocol_fees: Uint128::zero(),
+            },
+            DenomDistributionStats {
+                denom: LUNA.to_string(),
+                distributed: Uint128::from(1073u16),
+                protocol_fees: Uint128::from(27u8),
+            },
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn invalid_protocol_fees_fail() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let luna_fee = |fee_bps: u16| ProtocolFee {
+        denom: LUNA.to_string(),
+        fee_bps,
+    };
+
+    let result = update_protocol_fees(ctx, None, vec![luna_fee(100)]);
+    assert_eq!(result, Err(FeeCollectorNotSet));
+
+    let result = update_protocol_fees(ctx, Some("fee_collector"), vec![luna_fee(10_001)]);
+    assert_eq!(result, Err(InvalidProtocolFee { fee_bps: 10_001 }));
+
+    let result = execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info("random_user", &[]),
+        ExecuteMsg::UpdateProtocolFees(UpdateProtocolFeesMsg {
+            fee_collector: Some(addr("random_user")),
+            set: vec![luna_fee(100)],
+            remove: vec![],
+        }),
+    );
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
+}
+
+#[test]
+pub fn delegates_are_credited_commission_on_settled_rewards() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 1u64)],
+    )?;
+
+    set_delegate(ctx, "user1", "delegate", 1000)?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // the commission is only credited to the delegate once the user's rewards are settled
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 45u8)])?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    let response = claim(ctx, "user1", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user1"),
+            amount: coins(45, LUNA),
+        })
+    );
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 5u8)])?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // delegate's pending commission does not accrue rewards for the time they had no weight
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("delegate", 2u64)],
+    )?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 5u8)])?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 68u8)])?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 55u8)])?;
+
+    // weight changes settle the commission as well
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 2u64)])?;
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 68u8)])?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 62u8)])?;
+
+    let response: DelegateResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::Delegate(DelegateParams {
+            user: addr("user1"),
+        }),
+    )?)?;
+    assert_eq!(
+        response.delegate,
+        Some(DelegateCommission {
+            delegate: addr("delegate"),
+            commission_bps: 1000,
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn users_delegating_to_each_other_keep_all_rewards() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 1u64)],
+    )?;
+
+    set_delegate(ctx, "user1", "user2", 5000)?;
+    set_delegate(ctx, "user2", "user1", 5000)?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // commissions are credited when the delegator's rewards are settled, so user1 only receives
+    // user2's commission after user2 claims
+    for (user, claimed) in [("user1", 25u128), ("user2", 50), ("user1", 25)] {
+        let response = claim(ctx, user, vec![LUNA])?;
+        assert_eq!(
+            response.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: addr(user),
+                amount: coins(claimed, LUNA),
+            })
+        );
+    }
+
+    let response = query_all_user_rewards(ctx, "user1")?;
+    assert_eq!(response, vec![native_reward(LUNA, 0u8)]);
+    let response = query_all_user_rewards(ctx, "user2")?;
+    assert_eq!(response, vec![native_reward(LUNA, 0u8)]);
+
+    Ok(())
+}
+
+#[test]
+pub fn delegate_chains_are_credited_one_delegate_at_a_time() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 1u64)],
+    )?;
+
+    set_delegate(ctx, "user1", "user2", 5000)?;
+    set_delegate(ctx, "user2", "user3", 5000)?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // crediting user2's commission does not settle user2's own rewards
+    claim(ctx, "user1", vec![LUNA])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 50u8)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    claim(ctx, "user2", vec![LUNA])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 25u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn delegates_are_only_credited_commission_on_regular_distributions() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 1u64)],
+    )?;
+
+    set_delegate(ctx, "user1", "delegate", 1000)?;
+
+    ctx.env.block.height += 1;
+    let snapshot_height = ctx.env.block.height;
+    ctx.env.block.height += 1;
+
+    distribute_native_equally(ctx, &coins(100, LUNA))?;
+    distribute_native_at_height(ctx, &coins(100, LUNA), snapshot_height)?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 100u8)])?;
+
+    claim(ctx, "user1", vec![LUNA])?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    claim(ctx, "user1", vec![LUNA])?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 5u8)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn invalid_delegates_fail() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let result = set_delegate(ctx, "user", "user", 1000);
+    assert_eq!(result, Err(SelfDelegation));
+
+    let result = set_delegate(ctx, "user", "delegate", 10_001);
+    assert_eq!(
+        result,
+        Err(InvalidDelegateCommission {
+            commission_bps: 10_001
+        })
+    );
+
+    let result = execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info("random_user", &[]),
+        ExecuteMsg::SetDelegate(SetDelegateMsg {
+            user: addr("user"),
+            delegate: None,
+        }),
+    );
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
+}
+
+#[test]
+pub fn claimed_rewards_are_split_between_recipients() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    add_hook(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "points",
+        HookFailurePolicy::Abort,
+    )?;
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user", 1u64)])?;
+
+    let third = Decimal::from_ratio(1u8, 3u8);
+    set_reward_split(
+        ctx,
+        "user",
+        vec![
+            reward_split("signer1", Decimal::one() - third - third),
+            reward_split("signer2", third),
+            reward_split("signer3", third),
+        ],
+    )?;
+
+    distribute_native(ctx, &[coin(100, LUNA), coin(10, "uatom")])?;
+
+    let response = claim(ctx, "user", vec![LUNA, "uatom"])?;
+
+    // rounding remainders go to the first recipient
+    let split_funds = [
+        ("signer1", vec![coin(4, "uatom"), coin(34, LUNA)]),
+        ("signer2", vec![coin(3, "uatom"), coin(33, LUNA)]),
+        ("signer3", vec![coin(3, "uatom"), coin(33, LUNA)]),
+    ];
+    let payouts = response
+        .messages
+        .iter()
+        .map(|submsg| submsg.msg.clone())
+        .collect_vec();
+    assert_eq!(
+        payouts[..3],
+        split_funds
+            .iter()
+            .map(|(recipient, funds)| CosmosMsg::Bank(BankMsg::Send {
+                to_address: addr(recipient),
+                amount: funds.clone(),
+            }))
+            .collect_vec()
+    );
+
+    // hooks are notified once, of the whole claim
+    let claim_hook = FundsDistributorHookMsg::ClaimHook(ClaimHookMsg {
+        user: addr("user"),
+        recipients: split_funds
+            .iter()
+            .map(|(recipient, funds)| ClaimRecipient {
+                recipient: addr(recipient),
+                funds: funds.clone(),
+            })
+            .collect(),
+        funds: vec![coin(10, "uatom"), coin(100, LUNA)],
+    });
+    assert_eq!(
+        payouts[3..],
+        vec![wasm_execute(addr("points"), &claim_hook, vec![])?.into()]
+    );
+
+    // removing the split sends the rewards to the user again
+    set_reward_split(ctx, "user", vec![])?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    let response = claim(ctx, "user", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0],
+        SubMsg::new(BankMsg::Send {
+            to_address: addr("user"),
+            amount: coins(100, LUNA),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn invalid_reward_splits_fail() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    let half = Decimal::percent(50);
+
+    let result = set_reward_split(
+        ctx,
+        "user",
+        vec![
+            reward_split("signer1", half),
+            reward_split("signer2", Decimal::percent(40)),
+        ],
+    );
+    assert!(matches!(result, Err(InvalidRewardSplit { .. })));
+
+    let result = set_reward_split(
+        ctx,
+        "user",
+        vec![reward_split("signer1", half), reward_split("signer1", half)],
+    );
+    assert!(matches!(result, Err(InvalidRewardSplit { .. })));
+
+    let result = set_reward_split(
+        ctx,
+        "user",
+        vec![
+            reward_split("signer1", Decimal::one()),
+            reward_split("signer2", Decimal::zero()),
+        ],
+    );
+    assert!(matches!(result, Err(InvalidRewardSplit { .. })));
+
+    let result = execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info("random_user", &[]),
+        ExecuteMsg::SetRewardSplit(SetRewardSplitMsg {
+            user: addr("user"),
+            splits: vec![reward_split("random_user", Decimal::one())],
+        }),
+    );
+    assert_eq!(result, Err(Unauthorized));
+
+    Ok(())
+}
+
+#[test]
+pub fn exported_state_is_imported_into_new_contract() -> DistributorResult<()> {
+    let mut old_deps = mock_deps();
+    let ctx = &mut mock_ctx(old_deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![user_weight("user1", 1u64), user_weight("user2", 3u64)],
+    )?;
+    set_delegate(ctx, "user1", "delegate", 1000)?;
+
+    distribute_native(ctx, &coins(400, LUNA))?;
+
+    // user2 leaves with their rewards still pending
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![MemberDiff::new(addr("user2"), Some(3), None)],
+    )?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    // contracts not instantiated for importing state cannot import any
+    let result = import_state(ctx, &addr(SQUAD_CONTRACT), vec![]);
+    assert_eq!(result, Err(StateImportClosed));
+
+    let mut exported_users: Vec<ExportedUser> = vec![];
+    let mut start_after: Option<String> = None;
+    loop {
+        let response = export_state(ctx, start_after, 2)?;
+        if response.users.is_empty() {
+            break;
+        }
+        start_after = response.users.last().map(|user| user.user.clone());
+        exported_users.extend(response.users);
+    }
+
+    let exported_users = exported_users
+        .into_iter()
+        .map(|user| (user.user.clone(), user))
+        .collect::<HashMap<String, ExportedUser>>();
+    assert_eq!(exported_users.len(), 2);
+    assert_eq!(
+        exported_users[&addr("user1")].rewards,
+        vec![native_reward(LUNA, 180u8)]
+    );
+    assert_eq!(
+        exported_users[&addr("user2")],
+        ExportedUser {
+            user: addr("user2"),
+            rewards: vec![native_reward(LUNA, 300u16)],
+            delegate_commission: None,
+        }
+    );
+
+    // delegate's commission is exported along with the delegator, as it was not credited yet
+    assert_eq!(
+        exported_users[&addr("user1")].delegate_commission,
+        Some(ExportedCommission {
+            delegate: addr("delegate"),
+            commission: vec![native_reward(LUNA, 20u8)],
+        })
+    );
+
+    let mut new_deps = mock_deps();
+    new_deps
+        .querier
+        .bank
+        .update_balance(MOCK_CONTRACT_ADDR, coins(500, LUNA));
+    let ctx = &mut mock_ctx(new_deps.as_mut());
+
+    instantiate_for_import(ctx)?;
+
+    import_state(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![exported_users[&addr("user1")].clone()],
+    )?;
+    import_state(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![exported_users[&addr("user2")].clone()],
+    )?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 180u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 300u16)])?;
+    assert_user_rewards(ctx, "delegate", vec![LUNA], vec![native_reward(LUNA, 20u8)])?;
+
+    let response = claim(ctx, "user2", vec![LUNA])?;
+    assert_eq!(
+        response.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: addr("user2"),
+            amount: coins(300, LUNA),
+        })
+    );
+
+    // users cannot be imported twice
+    let result = import_state(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![exported_users[&addr("user2")].clone()],
+    );
+    assert_eq!(
+        result,
+        Err(UserAlreadyImported {
+            user: addr("user2")
+        })
+    );
+
+    close_state_import(ctx, &addr(SQUAD_CONTRACT))?;
+
+    let result = import_state(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        exported_users.into_values().collect(),
+    );
+    assert_eq!(result, Err(StateImportClosed));
+
+    Ok(())
+}
+
+#[test]
+pub fn distributions_are_rejected_until_admin_closes_state_import() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    deps.querier
+        .bank
+        .update_balance(MOCK_CONTRACT_ADDR, coins(100, LUNA));
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_for_import(ctx)?;
+
+    update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 1u64)])?;
+
+    let exported_user = |user: &str, amount: u128| ExportedUser {
+        user: addr(user),
+        rewards: vec![native_reward(LUNA, amount)],
+        delegate_commission: None,
+    };
+
+    import_state(ctx, &addr(SQUAD_CONTRACT), vec![exported_user("user1", 60)])?;
+
+    // claims leave the import open, and are taken off what the contract owes
+    claim(ctx, "user1", vec![LUNA])?;
+    deps.querier
+        .bank
+        .update_balance(MOCK_CONTRACT_ADDR, coins(40, LUNA));
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    let result = distribute_native(ctx, &coins(1, LUNA));
+    assert_eq!(result, Err(StateImportOpen));
+
+    import_state(ctx, &addr(SQUAD_CONTRACT), vec![exported_user("user2", 40)])?;
+
+    let result = close_state_import(ctx, "user1");
+    assert_eq!(result, Err(Unauthorized));
+
+    let result = import_state(ctx, &addr(SQUAD_CONTRACT), vec![exported_user("user3", 1)]);
+    assert_eq!(
+        result,
+        Err(InsolventStateImport {
+            denom: LUNA.to_string(),
+            owed: Uint128::new(41),
+            balance: Uint128::new(40),
+        })
+    );
+
+    close_state_import(ctx, &addr(SQUAD_CONTRACT))?;
+
+    distribute_native(ctx, &coins(1, LUNA))?;
+
+    let result = import_state(ctx, &addr(SQUAD_CONTRACT), vec![exported_user("user3", 1)]);
+    assert_eq!(result, Err(StateImportClosed));
+
+    Ok(())
+}
+
+#[test]
+pub fn importing_more_than_contract_holds_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    deps.querier
+        .bank
+        .update_balance(MOCK_CONTRACT_ADDR, coins(100, LUNA));
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_for_import(ctx)?;
+
+    let exported_user = |user: &str, amount: u128| ExportedUser {
+        user: addr(user),
+        rewards: vec![native_reward(LUNA, amount)],
+        delegate_commission: None,
+    };
+
+    let result = import_state(ctx, "user1", vec![exported_user("user1", 50)]);
+    assert_eq!(result, Err(Unauthorized));
+
+    import_state(ctx, &addr(SQUAD_CONTRACT), vec![exported_user("user1", 60)])?;
+
+    // the balance has to cover the previous imports as well
+    let result = import_state(ctx, &addr(SQUAD_CONTRACT), vec![exported_user("user2", 50)]);
+    assert_eq!(
+        result,
+        Err(InsolventStateImport {
+            denom: LUNA.to_string(),
+            owed: Uint128::new(110),
+            balance: Uint128::new(100),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn migrating_from_0_2_state_brings_it_up_to_date() -> DistributorResult<()> {
+    let mut deps = mock_deps_with_members(&[(SQUAD_CONTRACT, &[("user1", 1), ("user2", 3)])]);
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    // state as stored by 0.2.x, with a single weight source and no minimum eligible weight
+    set_contract_version(ctx.deps.storage, "crates.io:funds-distributor", "0.2.1")?;
+    Item::<Addr>::new("enterprise_contract").save(
+        ctx.deps.storage,
+        &Addr::unchecked(addr("old_squad_contract")),
+    )?;
+    Item::<Uint128>::new("total_weight").save(ctx.deps.storage, &Uint128::new(4))?;
+    for (user, weight) in [("user1", 1u8), ("user2", 3u8)] {
+        let user = Addr::unchecked(addr(user));
+        Map::<Addr, Uint128>::new("user_weights").save(
+            ctx.deps.storage,
+            user.clone(),
+            &weight.into(),
+        )?;
+        Map::<Addr, Uint128>::new("effective_user_weights").save(
+            ctx.deps.storage,
+            user,
+            &weight.into(),
+        )?;
+    }
+    Map::<String, Decimal>::new("native_global_indices").save(
+        ctx.deps.storage,
+        LUNA.to_string(),
+        &Decimal::from_ratio(100u8, 1u8),
+    )?;
+    // user2 has no distribution stored, since they never had their rewards updated
+    NATIVE_DISTRIBUTIONS().save(
+        ctx.deps.storage,
+        (Addr::unchecked(addr("user1")), LUNA.to_string()),
+        &NativeDistribution {
+            user: Addr::unchecked(addr("user1")),
+            denom: LUNA.to_string(),
+            user_index: Decimal::from_ratio(50u8, 1u8),
+            pending_rewards: Uint128::new(10),
+        },
+    )?;
+
+    let response = migrate(ctx, Some(SQUAD_CONTRACT))?;
+    assert_eq!(
+        response.attributes,
+        vec![
+            attr("action", "migrate"),
+            attr("previous_version", "0.2.1"),
+            attr("new_version", env!("CARGO_PKG_VERSION")),
+        ]
+    );
+    assert_eq!(
+        get_contract_version(ctx.deps.storage)?.version,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 60u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 300u16)])?;
+
+    // the minimum eligible weight becomes the single tier's minimum weight
+    assert_eq!(query_minimum_eligible_weight(ctx)?, Uint128::zero());
+    assert_eq!(
+        query_reward_tiers(ctx)?,
+        vec![RewardTierResponse {
+            name: "default".to_string(),
+            min_weight: Uint128::zero(),
+            share: Decimal::one(),
+            total_weight: Uint128::new(4),
+        }]
+    );
+
+    let response: WeightSourcesResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::WeightSources {},
+    )?)?;
+    assert_eq!(
+        response.sources,
+        vec![WeightSource {
+            addr: addr(SQUAD_CONTRACT),
+            kind: cw4_source(SQUAD_CONTRACT),
+            scale: Decimal::one(),
+        }]
+    );
+    assert_eq!(query_eligible_member_count(ctx)?, 2);
+
+    // the old squad's weights are resynced from the new hook source
+    let response = change_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        "old_squad_contract",
+        SQUAD_CONTRACT,
+        30,
+    )?;
+    assert_eq!(attribute_values(&response, "added"), Vec::<String>::new());
+    assert_eq!(attribute_values(&response, "removed"), Vec::<String>::new());
+    assert_eq!(attribute_values(&response, "changed"), Vec::<String>::new());
+    assert_eq!(attribute_values(&response, "complete"), vec!["true"]);
+
+    // the migrated state keeps working with the new hook source
+    distribute_native(ctx, &coins(400, LUNA))?;
+
+    update_user_weights(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        vec![MemberDiff::new(addr("user2"), Some(3), None)],
+    )?;
+
+    distribute_native(ctx, &coins(100, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 260u16)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 600u16)])?;
+
+    Ok(())
+}
+
+#[test]
+pub fn migrating_from_newer_or_other_contract_fails() -> DistributorResult<()> {
+    let mut deps = mock_deps();
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    // migrating to the same version runs no migration steps
+    migrate(ctx, None)?;
+
+    // nor does passing the current hook source change the weight source
+    migrate(ctx, Some(SQUAD_CONTRACT))?;
+    add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        cw4_source("stakers"),
+        Decimal::one(),
+    )?;
+
+    set_contract_version(ctx.deps.storage, "crates.io:funds-distributor", "99.0.0")?;
+    let result = migrate(ctx, None);
+    assert_eq!(
+        result,
+        Err(MigrationDowngrade {
+            from: "99.0.0".to_string(),
+            to: env!("CARGO_PKG_VERSION").to_string(),
+        })
+    );
+
+    set_contract_version(ctx.deps.storage, "crates.io:funds-distributor", "latest")?;
+    let result = migrate(ctx, None);
+    assert_eq!(
+        result,
+        Err(InvalidContractVersion {
+            version: "latest".to_string(),
+        })
+    );
+
+    set_contract_version(ctx.deps.storage, "crates.io:cw4-group", "0.1.0")?;
+    let result = migrate(ctx, None);
+    assert_eq!(
+        result,
+        Err(InvalidMigrationContract {
+            contract: "crates.io:cw4-group".to_string(),
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn change_weight_source_resyncs_weights_page_by_page() -> DistributorResult<()> {
+    let mut deps = mock_deps_with_members(&[
+        (SQUAD_CONTRACT, &[("user1", 1), ("user2", 3), ("user3", 2)]),
+        ("new_group", &[("user1", 1), ("user2", 5), ("user4", 4)]),
+    ]);
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+
+    distribute_native(ctx, &coins(600, LUNA))?;
+
+    let mut added: Vec<String> = vec![];
+    let mut removed: Vec<String> = vec![];
+    let mut changed: Vec<String> = vec![];
+    let mut messages = 0;
+
+    loop {
+        let response =
+            change_weight_source(ctx, &addr(SQUAD_CONTRACT), SQUAD_CONTRACT, "new_group", 1)?;
+        messages += 1;
+
+        added.extend(attribute_values(&response, "added"));
+        removed.extend(attribute_values(&response, "removed"));
+        changed.extend(attribute_values(&response, "changed"));
+
+        if attribute_values(&response, "complete") == vec!["true"] {
+            break;
+        }
+
+        // the old source cannot report changes anymore, even before the change is complete
+        let result =
+            update_user_weights(ctx, &addr(SQUAD_CONTRACT), vec![user_weight("user1", 7u64)]);
+        assert_eq!(result, Err(Unauthorized));
+    }
+
+    // one page per member, and a last empty page
+    assert_eq!(messages, 4);
+    assert_eq!(added, vec![addr("user4")]);
+    assert_eq!(removed, vec![addr("user3")]);
+    assert_eq!(changed, vec![addr("user2")]);
+
+    for (user, weight) in [
+        ("user1", 1u8),
+        ("user2", 5u8),
+        ("user3", 0u8),
+        ("user4", 4u8),
+    ] {
+        let response: UserWeightResponse = from_json(query(
+            ctx.deps.as_ref(),
+            ctx.env.clone(),
+            QueryMsg::UserWeightAt(UserWeightAtParams {
+                user: addr(user),
+                height: ctx.env.block.height + 1,
+            }),
+        )?)?;
+        assert_eq!(response.weight, Uint128::from(weight));
+    }
+
+    // rewards accrued under the old weights are kept
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 100u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 300u16)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 200u8)])?;
+    assert_user_rewards(ctx, "user4", vec![LUNA], vec![native_reward(LUNA, 0u8)])?;
+
+    distribute_native(ctx, &coins(1000, LUNA))?;
+
+    assert_user_rewards(ctx, "user1", vec![LUNA], vec![native_reward(LUNA, 200u8)])?;
+    assert_user_rewards(ctx, "user2", vec![LUNA], vec![native_reward(LUNA, 800u16)])?;
+    assert_user_rewards(ctx, "user3", vec![LUNA], vec![native_reward(LUNA, 200u8)])?;
+    assert_user_rewards(ctx, "user4", vec![LUNA], vec![native_reward(LUNA, 400u16)])?;
+
+    // the new source reports its changes through its hooks
+    update_user_weights(ctx, &addr("new_group"), vec![user_weight("user4", 6u64)])?;
+
+    let response: WeightSourcesResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::WeightSources {},
+    )?)?;
+    assert_eq!(
+        response.sources,
+        vec![WeightSource {
+            addr: addr("new_group"),
+            kind: cw4_source("new_group"),
+            scale: Decimal::one(),
+        }]
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn invalid_weight_source_changes_fail() -> DistributorResult<()> {
+    let mut deps = mock_deps_with_members(&[
+        (SQUAD_CONTRACT, &[("user1", 1), ("user2", 1)]),
+        ("new_group", &[("user1", 1), ("user2", 1)]),
+    ]);
+    let ctx = &mut mock_ctx(deps.as_mut());
+
+    instantiate_default(ctx)?;
+    add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        cw4_source("stakers"),
+        Decimal::one(),
+    )?;
+
+    let result = change_weight_source(ctx, &addr("user1"), SQUAD_CONTRACT, "new_group", 1);
+    assert_eq!(result, Err(Unauthorized));
+
+    let result = change_weight_source(ctx, &addr(SQUAD_CONTRACT), "other", "new_group", 1);
+    assert_eq!(
+        result,
+        Err(WeightSourceNotRegistered {
+            addr: addr("other"),
+        })
+    );
+
+    let result = change_weight_source(ctx, &addr(SQUAD_CONTRACT), SQUAD_CONTRACT, "stakers", 1);
+    assert_eq!(
+        result,
+        Err(WeightSourceAlreadyRegistered {
+            addr: addr("stakers"),
+        })
+    );
+
+    change_weight_source(ctx, &addr(SQUAD_CONTRACT), SQUAD_CONTRACT, "new_group", 1)?;
+
+    // another change cannot start before the first one is complete
+    let result = change_weight_source(ctx, &addr(SQUAD_CONTRACT), "stakers", "other", 1);
+    assert_eq!(
+        result,
+        Err(WeightSourceChangeInProgress {
+            old_source: addr(SQUAD_CONTRACT),
+            new_source: addr("new_group"),
+        })
+    );
+
+    // nor can sources be added
+    let result = add_weight_source(
+        ctx,
+        &addr(SQUAD_CONTRACT),
+        cw4_source("other"),
+        Decimal::one(),
+    );
+    assert_eq!(
+        result,
+        Err(WeightSourceChangeInProgress {
+            old_source: addr(SQUAD_CONTRACT),
+            new_source: addr("new_group"),
+        })
+    );
+
+    Ok(())
+}
+
+///////////////////////
+/////// HELPERS ///////
+///////////////////////
+
+/// Mock dependencies with a squad contract that has no members.
+fn mock_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    mock_deps_with_members(&[])
+}
+
+/// Query messages of the mocked cw4 and cw20-stake contracts.
+#[cw_serde]
+enum MockWeightSourceQueryMsg {
+    ListMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    ListStakers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+struct MockListStakersResponse {
+    stakers: Vec<MockStakerBalance>,
+}
+
+#[cw_serde]
+struct MockStakerBalance {
+    address: String,
+    balance: Uint128,
+}
+
+/// Mock dependencies where the given cw4 (or cw20-stake) contracts have the given members
+/// (or stakers), and any other contract has no members.
+fn mock_deps_with_members(
+    groups: &[(&str, &[(&str, u64)])],
+) -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let groups: HashMap<String, Vec<Member>> = groups
+        .iter()
+        .map(|(group, members)| {
+            let members = members
+                .iter()
+                .map(|(member, weight)| Member {
+                    addr: addr(member),
+                    weight: *weight,
+                })
+                .sorted_by(|a, b| a.addr.cmp(&b.addr))
+                .collect_vec();
+            (addr(group), members)
+        })
+        .collect();
+
+    let mut deps = mock_dependencies();
+    deps.querier.update_wasm(move |query| {
+        let WasmQuery::Smart { contract_addr, msg } = query else {
+            return SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "non-smart wasm query".to_string(),
+            });
+        };
+        let members_after = |start_after: Option<String>, limit: Option<u32>| {
+            groups
+                .get(contract_addr)
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|member| start_after.as_ref().is_none_or(|it| &member.addr > it))
+                .take(limit.unwrap_or(u32::MAX) as usize)
+                .collect_vec()
+        };
+        let response = match from_json(msg).unwrap() {
+            MockWeightSourceQueryMsg::ListMembers { start_after, limit } => {
+                to_json_binary(&MemberListResponse {
+                    members: members_after(start_after, limit),
+                })
+            }
+            MockWeightSourceQueryMsg::ListStakers { start_after, limit } => {
+                to_json_binary(&MockListStakersResponse {
+                    stakers: members_after(start_after, limit)
+                        .into_iter()
+                        .map(|member| MockStakerBalance {
+                            address: member.addr,
+                            balance: member.weight.into(),
+                        })
+                        .collect(),
+                })
+            }
+        };
+        SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+    });
+    deps
+}
+
+/// Creates a valid address for the given name.
+fn addr(name: &str) -> String {
+    MockApi::default().addr_make(name).to_string()
+}
+
+fn instantiate_default(ctx: &mut Context) -> DistributorResult<()> {
+    instantiate(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        ctx.info.clone(),
+        InstantiateMsg {
+            squad_contract: addr(SQUAD_CONTRACT),
+            minimum_eligible_weight: None,
+            state_import: None,
+        },
+    )?;
+    Ok(())
+}
+
+fn instantiate_for_import(ctx: &mut Context) -> DistributorResult<()> {
+    instantiate(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        ctx.info.clone(),
+        InstantiateMsg {
+            squad_contract: addr(SQUAD_CONTRACT),
+            minimum_eligible_weight: None,
+            state_import: Some(true),
+        },
+    )?;
+    Ok(())
+}
+
+fn native_reward(denom: impl Into<String>, amount: impl Into<Uint128>) -> NativeReward {
+    NativeReward {
+        denom: denom.into(),
+        amount: amount.into(),
+    }
+}
+
+fn distribute_native(ctx: &mut Context, funds: &[Coin]) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(ctx.info.sender.as_ref(), funds),
+        ExecuteMsg::DistributeNative {},
+    )
+}
+
+fn distribute_native_at_height(
+    ctx: &mut Context,
+    funds: &[Coin],
+    height: u64,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(ctx.info.sender.as_ref(), funds),
+        ExecuteMsg::DistributeNativeAtHeight { height },
+    )
+}
+
+fn register_merkle_distribution(
+    ctx: &mut Context,
+    root: &str,
+    total: u128,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &coins(total, LUNA)),
+        ExecuteMsg::RegisterMerkleDistribution(RegisterMerkleDistributionMsg {
+            root: root.to_string(),
+            denom: LUNA.to_string(),
+            total: Uint128::from(total),
+            expiry: Expiration::AtHeight(ctx.env.block.height + 100),
+        }),
+    )
+}
+
+fn claim_merkle(
+    ctx: &mut Context,
+    user: &str,
+    index: u32,
+    amount: u128,
+    proof: Vec<String>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(user), &[]),
+        ExecuteMsg::ClaimMerkle(ClaimMerkleMsg {
+            id: 1,
+            index,
+            amount: Uint128::from(amount),
+            proof,
+        }),
+    )
+}
+
+fn update_group_members(
+    ctx: &mut Context,
+    group_id: &str,
+    add: Vec<&str>,
+    remove: Vec<&str>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::UpdateGroupMembers(UpdateGroupMembersMsg {
+            group_id: group_id.to_string(),
+            add: add.into_iter().map(addr).collect(),
+            remove: remove.into_iter().map(addr).collect(),
+        }),
+    )
+}
+
+fn distribute_native_to_group(
+    ctx: &mut Context,
+    funds: &[Coin],
+    group_id: &str,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(ctx.info.sender.as_ref(), funds),
+        ExecuteMsg::DistributeNativeToGroup {
+            group_id: group_id.to_string(),
+        },
+    )
+}
+
+fn distribute_native_equally(ctx: &mut Context, funds: &[Coin]) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(ctx.info.sender.as_ref(), funds),
+        ExecuteMsg::DistributeNativeEqually {},
+    )
+}
+
+fn query_eligible_member_count(ctx: &mut Context) -> DistributorResult<u64> {
+    let response: EligibleMemberCountResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::EligibleMemberCount {},
+    )?)?;
+
+    Ok(response.eligible_member_count)
+}
+
+fn query_minimum_eligible_weight(ctx: &mut Context) -> DistributorResult<Uint128> {
+    let response: MinimumEligibleWeightResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::MinimumEligibleWeight {},
+    )?)?;
+
+    Ok(response.minimum_eligible_weight)
+}
+
+fn query_reward_tiers(ctx: &mut Context) -> DistributorResult<Vec<RewardTierResponse>> {
+    let response: RewardTiersResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::RewardTiers {},
+    )?)?;
+
+    Ok(response.tiers)
+}
+
+fn reward_tier(name: &str, min_weight: u128, share: &str) -> RewardTier {
+    RewardTier {
+        name: name.to_string(),
+        min_weight: Uint128::from(min_weight),
+        share: Decimal::from_str(share).unwrap(),
+    }
+}
+
+fn update_reward_tiers(ctx: &mut Context, tiers: Vec<RewardTier>) -> DistributorResult<Response> {
+    update_reward_tiers_page(ctx, tiers, None)
+}
+
+fn update_reward_tiers_page(
+    ctx: &mut Context,
+    tiers: Vec<RewardTier>,
+    limit: Option<u32>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::UpdateRewardTiers(UpdateRewardTiersMsg { tiers, limit }),
+    )
+}
+
+fn distribute_native_tiered(ctx: &mut Context, funds: &[Coin]) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(ctx.info.sender.as_ref(), funds),
+        ExecuteMsg::DistributeNativeTiered {},
+    )
+}
+
+fn retire_denom(ctx: &mut Context, denom: &str, limit: Option<u32>) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::RetireDenom(RetireDenomMsg {
+            denom: denom.to_string(),
+            limit,
+        }),
+    )
+}
+
+fn update_protocol_fees(
+    ctx: &mut Context,
+    fee_collector: Option<&str>,
+    set: Vec<ProtocolFee>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(SQUAD_CONTRACT), &[]),
+        ExecuteMsg::UpdateProtocolFees(UpdateProtocolFeesMsg {
+            fee_collector: fee_collector.map(addr),
+            set,
+            remove: vec![],
+        }),
+    )
+}
+
+fn reward_split(recipient: &str, share: Decimal) -> RewardSplit {
+    RewardSplit {
+        recipient: addr(recipient),
+        share,
+    }
+}
+
+fn set_reward_split(
+    ctx: &mut Context,
+    user: &str,
+    splits: Vec<RewardSplit>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(user), &[]),
+        ExecuteMsg::SetRewardSplit(SetRewardSplitMsg {
+            user: addr(user),
+            splits,
+        }),
+    )
+}
+
+fn set_delegate(
+    ctx: &mut Context,
+    user: &str,
+    delegate: &str,
+    commission_bps: u16,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(user), &[]),
+        ExecuteMsg::SetDelegate(SetDelegateMsg {
+            user: addr(user),
+            delegate: Some(DelegateCommission {
+                delegate: addr(delegate),
+                commission_bps,
+            }),
+        }),
+    )
+}
+
+fn migrate(ctx: &mut Context, new_hook_src: Option<&str>) -> DistributorResult<Response> {
+    crate::contract::migrate(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        MigrateMsg {
+            new_hook_src: new_hook_src.map(addr),
+        },
+    )
+}
+
+fn change_weight_source(
+    ctx: &mut Context,
+    sender: &str,
+    old_source: &str,
+    new_source: &str,
+    limit: u32,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::ChangeWeightSource(ChangeWeightSourceMsg {
+            old_source: addr(old_source),
+            new_source: addr(new_source),
+            limit: Some(limit),
+        }),
+    )
+}
+
+/// Values of the response's attribute with the given key, split by commas.
+fn query_rewards(ctx: &mut Context, user: &str) -> DistributorResult<UserRewardsResponse> {
+    let qctx = QueryContext {
+        deps: ctx.deps.as_ref(),
+        env: ctx.env.clone(),
+    };
+
+    query_user_rewards(
+        qctx,
+        UserRewardsParams {
+            user: addr(user),
+            native_denoms: None,
+        },
+    )
+}
+
+fn attribute_values(response: &Response, key: &str) -> Vec<String> {
+    response
+        .attributes
+        .iter()
+        .filter(|attribute| attribute.key == key)
+        .flat_map(|attribute| attribute.value.split(','))
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+        .collect()
+}
+
+fn export_state(
+    ctx: &mut Context,
+    start_after: Option<String>,
+    limit: u32,
+) -> DistributorResult<ExportStateResponse> {
+    let response = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::ExportState(ExportStateParams {
+            start_after,
+            limit: Some(limit),
+        }),
+    )?)?;
+
+    Ok(response)
+}
+
+fn import_state(
+    ctx: &mut Context,
+    sender: &str,
+    users: Vec<ExportedUser>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::ImportState(ImportStateMsg { users }),
+    )
+}
+
+fn close_state_import(ctx: &mut Context, sender: &str) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::CloseStateImport {},
+    )
+}
+
+fn stored_distribution_denoms(ctx: &mut Context, user: &str) -> DistributorResult<Vec<String>> {
+    let denoms = NATIVE_DISTRIBUTIONS()
+        .idx
+        .user
+        .prefix(Addr::unchecked(addr(user)))
+        .keys(ctx.deps.storage, None, None, Order::Ascending)
+        .map(|key| key.map(|(_, denom)| denom))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(denoms)
+}
+
+fn claim(
+    ctx: &mut Context,
+    user: &str,
+    native_denoms: Vec<impl Into<String>>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        ctx.info.clone(),
+        ExecuteMsg::ClaimRewards(ClaimRewardsMsg {
+            user: addr(user),
+            native_denoms: Some(
+                native_denoms
+                    .into_iter()
+                    .map(|denom| denom.into())
+                    .collect_vec(),
+            ),
+            compound_to: None,
+        }),
+    )
+}
+
+fn claim_and_compound(
+    ctx: &mut Context,
+    sender: &str,
+    user: &str,
+    contract: &str,
+    msg: Binary,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(sender), &[]),
+        ExecuteMsg::ClaimRewards(ClaimRewardsMsg {
+            user: addr(user),
+            native_denoms: None,
+            compound_to: Some(CompoundTarget {
+                contract: addr(contract),
+                msg,
+            }),
+        }),
+    )
+}
+
+fn user_weight(user: &str, weight: u64) -> MemberDiff {
+    MemberDiff::new(addr(user), None, Some(weight))
+}
+
+fn update_user_weights(
+    ctx: &mut Context,
+    sender: &str,
+    new_user_weights: Vec<MemberDiff>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::MemberChangedHook(MemberChangedHookMsg {
+            diffs: new_user_weights,
+        }),
+    )
+}
+
+fn update_minimum_eligible_weight(
+    ctx: &mut Context,
+    sender: &str,
+    new_minimum_eligible_weight: impl Into<Uint128>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::UpdateMinimumEligibleWeight(UpdateMinimumEligibleWeightMsg {
+            minimum_eligible_weight: new_minimum_eligible_weight.into(),
+        }),
+    )
+}
+
+fn update_compounding_targets(
+    ctx: &mut Context,
+    sender: &str,
+    add: Vec<&str>,
+    remove: Vec<&str>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::UpdateCompoundingTargets(UpdateCompoundingTargetsMsg {
+            add: add.into_iter().map(addr).collect_vec(),
+            remove: remove.into_iter().map(addr).collect_vec(),
+        }),
+    )
+}
+
+fn grant_claim_operator(
+    ctx: &mut Context,
+    user: &str,
+    operator: &str,
+    expires: Option<Expiration>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(user), &[]),
+        ExecuteMsg::GrantClaimOperator(GrantClaimOperatorMsg {
+            operator: addr(operator),
+            expires,
+        }),
+    )
+}
+
+fn revoke_claim_operator(
+    ctx: &mut Context,
+    user: &str,
+    operator: &str,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(user), &[]),
+        ExecuteMsg::RevokeClaimOperator(RevokeClaimOperatorMsg {
+            operator: addr(operator),
+        }),
+    )
+}
+
+fn set_reward_recipient(
+    ctx: &mut Context,
+    sender: &str,
+    user: &str,
+    recipient: Option<&str>,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(sender), &[]),
+        ExecuteMsg::SetRewardRecipient(SetRewardRecipientMsg {
+            user: addr(user),
+            recipient: recipient.map(addr),
+        }),
+    )
+}
+
+fn query_claim_operators(ctx: &mut Context, user: &str) -> DistributorResult<Vec<String>> {
+    let response: ClaimOperatorsResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::ClaimOperators(ClaimOperatorsParams {
+            user: addr(user),
+            start_after: None,
+            limit: None,
+        }),
+    )?)?;
+
+    Ok(response
+        .operators
+        .into_iter()
+        .map(|operator| operator.operator)
+        .collect_vec())
+}
+
+fn add_hook(
+    ctx: &mut Context,
+    sender: &str,
+    hook: &str,
+    on_failure: HookFailurePolicy,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::AddHook(AddHookMsg {
+            addr: addr(hook),
+            on_failure,
+        }),
+    )
+}
+
+fn add_weight_source(
+    ctx: &mut Context,
+    sender: &str,
+    source: WeightSourceKind,
+    scale: Decimal,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(sender, &[]),
+        ExecuteMsg::AddWeightSource(AddWeightSourceMsg { source, scale }),
+    )
+}
+
+fn cw4_source(source: &str) -> WeightSourceKind {
+    WeightSourceKind::Cw4 { addr: addr(source) }
+}
+
+fn stake_hook(
+    ctx: &mut Context,
+    sender: &str,
+    msg: StakeChangedHookMsg,
+) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info(&addr(sender), &[]),
+        ExecuteMsg::StakeChangeHook(msg),
+    )
+}
+
+fn update_native_stake_weights(ctx: &mut Context, users: Vec<&str>) -> DistributorResult<Response> {
+    execute(
+        ctx.deps.branch(),
+        ctx.env.clone(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateNativeStakeWeights(UpdateNativeStakeWeightsMsg {
+            users: users.into_iter().map(addr).collect(),
+        }),
+    )
+}
+
+fn query_all_user_rewards(ctx: &mut Context, user: &str) -> DistributorResult<Vec<NativeReward>> {
+    let response: UserRewardsResponse = from_json(query(
+        ctx.deps.as_ref(),
+        ctx.env.clone(),
+        QueryMsg::UserRewards(UserRewardsParams {
+            user: addr(user),
+            native_denoms: None,
+        }),
+    )?)?;
+
+    Ok(response.native_rewards)
+}
+
+fn assert_user_rewards(
+    ctx: &mut Context,
+    user: &str,
+    native_denoms: Vec<impl Into<String>>,
+    expected_native_rewards: Vec<NativeReward>,
+) -> DistributorResult<()> {
+    let qctx = QueryContext {
+        deps: ctx.deps.as_ref(),
+        env: ctx.env.clone(),
+    };
+
+    let native_denoms = native_denoms
+        .into_iter()
+        .map(|denom| denom.into())
         .collect_vec();
 
     let user_rewards = query_user_rewards(
         qctx,
         UserRewardsParams {
-            user: user.to_string(),
+            user: addr(user),
             native_denoms: Some(native_denoms),
         },
     )?;
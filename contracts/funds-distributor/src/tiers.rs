@@ -0,0 +1,492 @@
+use crate::denoms::{add_refund, split_distribution_funds};
+use crate::distributors::assert_authorized_distributor;
+use crate::eligibility::{
+    load_minimum_eligible_weight, update_minimum_eligible_weight, MINIMUM_ELIGIBLE_WEIGHT,
+};
+use crate::hooks::hook_submsgs;
+use crate::native_distributions::{add_user_pending_rewards, ensure_native_denom_known};
+use crate::rewards::calculate_new_user_reward;
+use crate::state::SQUAD_CONTRACT;
+use crate::user_weights::EFFECTIVE_USER_WEIGHTS;
+use common::cw::{Context, QueryContext};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Coin, Decimal, Deps, DepsMut, Order, Response, StdResult, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use funds_distributor_api::api::{
+    DistributionHookMsg, FundsDistributorHookMsg, RewardTier, RewardTierResponse,
+    RewardTiersResponse, UpdateRewardTiersMsg,
+};
+use funds_distributor_api::error::DistributorError::{
+    InvalidRewardTiers, RewardTiersUpdateInProgress, Unauthorized, ZeroTotalWeight,
+};
+use funds_distributor_api::error::DistributorResult;
+use std::ops::Add;
+
+/// Weight tiers that tiered distributions are split across, ordered by their minimum weights.
+///
+/// The lowest tier's minimum weight is the minimum eligible weight, so there is always at least
+/// one tier, and every user with an effective weight is in a tier. Users under it get no rewards
+/// from any distribution, while the tiers' shares only apply to tiered distributions.
+pub const REWARD_TIERS: Item<Vec<RewardTier>> = Item::new("reward_tiers");
+
+/// Name of the single tier that contracts start out with.
+const DEFAULT_TIER_NAME: &str = "default";
+
+/// Most users gone through by a single reward tiers update message.
+const MAX_TIERS_UPDATE_LIMIT: u32 = 30;
+
+#[cw_serde]
+pub struct RewardTiersUpdate {
+    /// Last user gone through so far, in the order of addresses
+    pub last_synced: Option<Addr>,
+}
+
+/// Reward tiers update that is in progress, with its users not all moved to their new tiers yet.
+pub const REWARD_TIERS_UPDATE: Item<RewardTiersUpdate> = Item::new("reward_tiers_update");
+
+/// Total effective weight of the users in each tier, keyed by the tier's position.
+pub const TIER_TOTAL_WEIGHTS: Map<u32, Uint128> = Map::new("tier_total_weights");
+
+/// Global indices of the tiered distributions to each tier, keyed by (tier, denom).
+pub const TIER_GLOBAL_INDICES: Map<(u32, String), Decimal> = Map::new("tier_global_indices");
+
+/// Tier that each user is currently in. Users without an effective weight are not stored.
+pub const USER_TIERS: Map<Addr, u32> = Map::new("user_tiers");
+
+/// The last index of the user's current tier at which their tiered rewards were moved into
+/// their pending rewards, keyed by (user, denom).
+pub const USER_TIER_INDICES: Map<(Addr, String), Decimal> = Map::new("user_tier_indices");
+
+/// Replaces the reward tiers, and moves the next page of users whose tier changes to their new
+/// tiers.
+///
+/// Rewards accrued in the users' old tiers are first moved into their pending rewards. Users
+/// staying in the same tier keep accruing its rewards undisturbed.
+/// The first message starts the update, and the following ones with the same tiers continue it
+/// until all the users are gone through. Tiered distributions are not possible until then.
+pub fn update_reward_tiers(
+    ctx: &mut Context,
+    msg: UpdateRewardTiersMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let update = match REWARD_TIERS_UPDATE.may_load(ctx.deps.storage)? {
+        Some(update) => {
+            let tiers = REWARD_TIERS.may_load(ctx.deps.storage)?.unwrap_or_default();
+            if tiers != msg.tiers {
+                return Err(RewardTiersUpdateInProgress);
+            }
+            update
+        }
+        None => {
+            validate_reward_tiers(&msg.tiers)?;
+
+            let old_minimum_weight = load_minimum_eligible_weight(ctx.deps.storage)?;
+
+            // users' tiers are checked against the new tiers from now on, including the users
+            // whose weights change before the update gets to them
+            REWARD_TIERS.save(ctx.deps.storage, &msg.tiers)?;
+
+            // users whose eligibility changes are moved to their new tiers right away
+            update_minimum_eligible_weight(
+                ctx.deps.branch(),
+                ctx.env.block.height,
+                old_minimum_weight,
+                msg.tiers[0].min_weight,
+            )?;
+
+            RewardTiersUpdate { last_synced: None }
+        }
+    };
+
+    let limit = msg
+        .limit
+        .unwrap_or(MAX_TIERS_UPDATE_LIMIT)
+        .min(MAX_TIERS_UPDATE_LIMIT) as usize;
+
+    let user_weights = EFFECTIVE_USER_WEIGHTS
+        .range(
+            ctx.deps.storage,
+            update.last_synced.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+
+    let mut moved: Vec<String> = vec![];
+
+    for (user, weight) in &user_weights {
+        let old_tier = USER_TIERS.may_load(ctx.deps.storage, user.clone())?;
+        let new_tier = tier_of(&msg.tiers, *weight);
+
+        if old_tier == new_tier {
+            continue;
+        }
+
+        settle_tier_rewards(ctx.deps.branch(), user.clone(), *weight)?;
+        remove_user_tier(ctx.deps.branch(), user.clone(), *weight)?;
+        assign_user_tier(ctx.deps.branch(), user.clone(), *weight)?;
+
+        moved.push(user.to_string());
+    }
+
+    let is_complete = user_weights.len() < limit;
+    if is_complete {
+        REWARD_TIERS_UPDATE.remove(ctx.deps.storage);
+    } else {
+        REWARD_TIERS_UPDATE.save(
+            ctx.deps.storage,
+            &RewardTiersUpdate {
+                last_synced: user_weights.last().map(|(user, _)| user.clone()),
+            },
+        )?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "update_reward_tiers")
+        .add_attribute("tiers", msg.tiers.len().to_string())
+        .add_attribute("moved", moved.join(","))
+        .add_attribute("complete", is_complete.to_string()))
+}
+
+/// Distributes new rewards for native assets, using funds found in MessageInfo, first across the
+/// reward tiers according to their shares, and then within each tier according to the users'
+/// weights.
+///
+/// Tiers without any weight are skipped, with the remaining tiers' shares scaled up accordingly.
+pub fn distribute_native_tiered(ctx: &mut Context) -> DistributorResult<Response> {
+    assert_authorized_distributor(ctx.deps.as_ref(), &ctx.info.sender)?;
+
+    // tiers' total weights are only accurate once all the users are moved to their new tiers
+    if REWARD_TIERS_UPDATE.exists(ctx.deps.storage) {
+        return Err(RewardTiersUpdateInProgress);
+    }
+
+    let (funds, rejected_funds) =
+        split_distribution_funds(ctx.deps.as_ref(), ctx.info.funds.clone())?;
+
+    let tiers = REWARD_TIERS.may_load(ctx.deps.storage)?.unwrap_or_default();
+
+    let mut tier_weights: Vec<(u32, Decimal, Uint128)> = vec![];
+    for (tier, reward_tier) in tiers.iter().enumerate() {
+        let tier = tier as u32;
+        let total_weight = TIER_TOTAL_WEIGHTS
+            .may_load(ctx.deps.storage, tier)?
+            .unwrap_or_default();
+
+        if !total_weight.is_zero() {
+            tier_weights.push((tier, reward_tier.share, total_weight));
+        }
+    }
+
+    let total_share: Decimal = tier_weights.iter().map(|(_, share, _)| *share).sum();
+    if total_share.is_zero() {
+        return Err(ZeroTotalWeight);
+    }
+
+    for fund in funds.clone() {
+        for (tier, share, total_weight) in &tier_weights {
+            let tier_amount = fund
+                .amount
+                .multiply_ratio(share.atomics(), total_share.atomics());
+
+            let tier_index = TIER_GLOBAL_INDICES
+                .may_load(ctx.deps.storage, (*tier, fund.denom.clone()))?
+                .unwrap_or_default();
+
+            let index_increment = Decimal::from_ratio(tier_amount, *total_weight);
+
+            TIER_GLOBAL_INDICES.save(
+                ctx.deps.storage,
+                (*tier, fund.denom.clone()),
+                &tier_index.add(index_increment),
+            )?;
+        }
+
+        ensure_native_denom_known(ctx.deps.storage, &fund.denom)?;
+    }
+
+    let total_weight = tier_weights
+        .iter()
+        .map(|(_, _, total_weight)| *total_weight)
+        .sum::<Uint128>();
+
+    let hook_submsgs = hook_submsgs(
+        ctx.deps.as_ref(),
+        FundsDistributorHookMsg::DistributionHook(DistributionHookMsg {
+            distributor: ctx.info.sender.to_string(),
+            funds,
+            total_weight,
+            snapshot_height: None,
+            group_id: None,
+            eligible_member_count: None,
+        }),
+    )?;
+
+    let response = Response::new()
+        .add_attribute("action", "distribute_native_tiered")
+        .add_attribute("total_weight", total_weight.to_string())
+        .add_submessages(hook_submsgs);
+
+    Ok(add_refund(response, &ctx.info.sender, rejected_funds))
+}
+
+/// Moves the user to the tier matching their new effective weight, updating the tiers' total
+/// weights.
+///
+/// Rewards accrued in the user's old tier are first moved into their pending rewards, using
+/// their old weight.
+pub fn update_user_tier(
+    mut deps: DepsMut,
+    user: Addr,
+    old_user_weight: Uint128,
+    new_user_weight: Uint128,
+) -> DistributorResult<()> {
+    if old_user_weight == new_user_weight {
+        return Ok(());
+    }
+
+    settle_tier_rewards(deps.branch(), user.clone(), old_user_weight)?;
+    remove_user_tier(deps.branch(), user.clone(), old_user_weight)?;
+    assign_user_tier(deps, user, new_user_weight)?;
+
+    Ok(())
+}
+
+/// Moves rewards accrued in the user's tier into their pending rewards.
+pub fn settle_user_tier_rewards(deps: DepsMut, user: Addr) -> DistributorResult<()> {
+    let user_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    settle_tier_rewards(deps, user, user_weight)
+}
+
+/// Calculates rewards accrued in the user's tier that were not yet moved into their pending
+/// rewards.
+pub fn unsettled_tier_rewards(deps: Deps, user: &Addr) -> DistributorResult<Vec<Coin>> {
+    let user_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load(deps.storage, user.clone())?
+        .unwrap_or_default();
+
+    let rewards = tier_rewards(deps, user, user_weight)?
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(denom, amount)| Coin { denom, amount })
+        .collect();
+
+    Ok(rewards)
+}
+
+/// Single tier holding every eligible user, for the given minimum eligible weight.
+pub fn default_reward_tiers(minimum_eligible_weight: Uint128) -> Vec<RewardTier> {
+    vec![RewardTier {
+        name: DEFAULT_TIER_NAME.to_string(),
+        min_weight: minimum_eligible_weight,
+        share: Decimal::one(),
+    }]
+}
+
+/// Replaces the stored minimum eligible weight with a single tier of that minimum weight, and
+/// puts every eligible user in it.
+pub fn migrate_minimum_eligible_weight_to_reward_tiers(deps: DepsMut) -> DistributorResult<()> {
+    if REWARD_TIERS.exists(deps.storage) {
+        return Ok(());
+    }
+
+    let minimum_eligible_weight = MINIMUM_ELIGIBLE_WEIGHT
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    MINIMUM_ELIGIBLE_WEIGHT.remove(deps.storage);
+
+    REWARD_TIERS.save(deps.storage, &default_reward_tiers(minimum_eligible_weight))?;
+
+    let user_weights = EFFECTIVE_USER_WEIGHTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+
+    let mut total_weight = Uint128::zero();
+    for (user, weight) in user_weights {
+        if weight.is_zero() {
+            continue;
+        }
+
+        USER_TIERS.save(deps.storage, user, &0)?;
+        total_weight += weight;
+    }
+    TIER_TOTAL_WEIGHTS.save(deps.storage, 0, &total_weight)?;
+
+    Ok(())
+}
+
+fn validate_reward_tiers(tiers: &[RewardTier]) -> DistributorResult<()> {
+    if tiers.is_empty() {
+        return Err(InvalidRewardTiers {
+            reason: "the lowest tier holds the minimum eligible weight, so one is required"
+                .to_string(),
+        });
+    }
+
+    let total_share: Decimal = tiers.iter().map(|tier| tier.share).sum();
+    if total_share != Decimal::one() {
+        return Err(InvalidRewardTiers {
+            reason: "tier shares must add up to 1".to_string(),
+        });
+    }
+
+    let increasing_minimums = tiers
+        .windows(2)
+        .all(|pair| pair[0].min_weight < pair[1].min_weight);
+    if !increasing_minimums {
+        return Err(InvalidRewardTiers {
+            reason: "tier minimum weights must be strictly increasing".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn settle_tier_rewards(
+    mut deps: DepsMut,
+    user: Addr,
+    user_weight: Uint128,
+) -> DistributorResult<()> {
+    let Some(tier) = USER_TIERS.may_load(deps.storage, user.clone())? else {
+        return Ok(());
+    };
+
+    for (denom, reward) in tier_rewards(deps.as_ref(), &user, user_weight)? {
+        if !reward.is_zero() {
+            add_user_pending_rewards(deps.branch(), user.clone(), denom, reward)?;
+        }
+    }
+
+    save_user_tier_indices(deps, &user, tier)?;
+
+    Ok(())
+}
+
+/// Removes the user from their tier, subtracting the weight they were in the tier with from its
+/// total weight.
+fn remove_user_tier(deps: DepsMut, user: Addr, user_weight: Uint128) -> DistributorResult<()> {
+    let Some(tier) = USER_TIERS.may_load(deps.storage, user.clone())? else {
+        return Ok(());
+    };
+
+    TIER_TOTAL_WEIGHTS.update(deps.storage, tier, |total_weight| -> StdResult<_> {
+        Ok(total_weight.unwrap_or_default() - user_weight)
+    })?;
+
+    let denoms = USER_TIER_INDICES
+        .prefix(user.clone())
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<String>>>()?;
+    for denom in denoms {
+        USER_TIER_INDICES.remove(deps.storage, (user.clone(), denom));
+    }
+
+    USER_TIERS.remove(deps.storage, user);
+
+    Ok(())
+}
+
+fn assign_user_tier(deps: DepsMut, user: Addr, user_weight: Uint128) -> DistributorResult<()> {
+    let tiers = REWARD_TIERS.may_load(deps.storage)?.unwrap_or_default();
+
+    let Some(tier) = tier_of(&tiers, user_weight) else {
+        return Ok(());
+    };
+
+    TIER_TOTAL_WEIGHTS.update(deps.storage, tier, |total_weight| -> StdResult<_> {
+        Ok(total_weight.unwrap_or_default() + user_weight)
+    })?;
+
+    USER_TIERS.save(deps.storage, user.clone(), &tier)?;
+
+    // users are only entitled to the tier's distributions from the moment they enter it
+    save_user_tier_indices(deps, &user, tier)?;
+
+    Ok(())
+}
+
+/// Position of the tier that users with the given effective weight belong to, if any.
+fn tier_of(tiers: &[RewardTier], user_weight: Uint128) -> Option<u32> {
+    // users under the minimum eligible weight have no effective weight
+    if user_weight.is_zero() {
+        return None;
+    }
+
+    tiers
+        .iter()
+        .rposition(|tier| tier.min_weight <= user_weight)
+        .map(|tier| tier as u32)
+}
+
+fn save_user_tier_indices(deps: DepsMut, user: &Addr, tier: u32) -> DistributorResult<()> {
+    let tier_indices = TIER_GLOBAL_INDICES
+        .prefix(tier)
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(String, Decimal)>>>()?;
+
+    for (denom, tier_index) in tier_indices {
+        USER_TIER_INDICES.save(deps.storage, (user.clone(), denom), &tier_index)?;
+    }
+
+    Ok(())
+}
+
+/// Calculates the user's rewards for each denom distributed to their tier, accrued since the
+/// last update to their tier indices.
+fn tier_rewards(
+    deps: Deps,
+    user: &Addr,
+    user_weight: Uint128,
+) -> StdResult<Vec<(String, Uint128)>> {
+    let Some(tier) = USER_TIERS.may_load(deps.storage, user.clone())? else {
+        return Ok(vec![]);
+    };
+
+    TIER_GLOBAL_INDICES
+        .prefix(tier)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|tier_index| {
+            let (denom, tier_index) = tier_index?;
+
+            let user_index = USER_TIER_INDICES
+                .may_load(deps.storage, (user.clone(), denom.clone()))?
+                .unwrap_or_default();
+
+            let reward = calculate_new_user_reward(tier_index, user_index, user_weight);
+
+            Ok((denom, reward))
+        })
+        .collect()
+}
+
+pub fn query_reward_tiers(qctx: QueryContext) -> DistributorResult<RewardTiersResponse> {
+    let tiers = REWARD_TIERS
+        .may_load(qctx.deps.storage)?
+        .unwrap_or_default()
+        .into_iter()
+        .enumerate()
+        .map(|(tier, reward_tier)| {
+            let total_weight = TIER_TOTAL_WEIGHTS
+                .may_load(qctx.deps.storage, tier as u32)?
+                .unwrap_or_default();
+
+            Ok(RewardTierResponse {
+                name: reward_tier.name,
+                min_weight: reward_tier.min_weight,
+                share: reward_tier.share,
+                total_weight,
+            })
+        })
+        .collect::<StdResult<Vec<RewardTierResponse>>>()?;
+
+    Ok(RewardTiersResponse { tiers })
+}
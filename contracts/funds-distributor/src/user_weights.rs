@@ -1,171 +1,313 @@
-use crate::eligibility::MINIMUM_ELIGIBLE_WEIGHT;
+use crate::eligibility::load_minimum_eligible_weight;
+use crate::equal_distributions::{update_user_member_eligibility, ELIGIBLE_MEMBER_COUNT};
+use crate::groups::update_user_group_weights;
 use crate::native_distributions;
 use crate::native_distributions::{NativeDistribution, NATIVE_DISTRIBUTIONS};
-use crate::state::{NATIVE_GLOBAL_INDICES, SQUAD_CONTRACT, TOTAL_WEIGHT};
-use common::cw::Context;
+use crate::snapshot_distributions::initialize_user_snapshot_cursor;
+use crate::state::{NATIVE_GLOBAL_INDICES, TOTAL_WEIGHT};
+use crate::tiers::update_user_tier;
+use crate::weight_sources::{WeightSourceConfig, SOURCE_USER_WEIGHTS, WEIGHT_SOURCES};
+use common::cw::{Context, QueryContext};
 use cosmwasm_std::Order::Ascending;
 use cosmwasm_std::{
-    to_binary, Addr, Decimal, Deps, QueryRequest, Response, StdResult, Uint128, WasmQuery,
+    to_json_binary, Addr, Deps, DepsMut, QueryRequest, Response, StdResult, Uint128, WasmQuery,
 };
-use cw_storage_plus::Map;
+use cw_storage_plus::{SnapshotMap, Strategy};
 
 use cw4::Cw4QueryMsg::ListMembers;
 use cw4::{Member, MemberChangedHookMsg, MemberListResponse};
+use funds_distributor_api::api::{
+    TotalWeightAtParams, TotalWeightResponse, UserWeightAtParams, UserWeightResponse,
+    WeightSourceKind,
+};
 use funds_distributor_api::error::DistributorError::Unauthorized;
 use funds_distributor_api::error::{DistributorError, DistributorResult};
-use native_distributions::update_user_native_distributions;
+use native_distributions::{save_user_checkpoint, update_user_native_distributions};
 use DistributorError::DuplicateInitialWeight;
 
-pub const USER_WEIGHTS: Map<Addr, Uint128> = Map::new("user_weights");
+/// User weights, with their history kept for every block that they changed in.
+pub const USER_WEIGHTS: SnapshotMap<Addr, Uint128> = SnapshotMap::new(
+    "user_weights",
+    "user_weights__checkpoints",
+    "user_weights__changelog",
+    Strategy::EveryBlock,
+);
 
 /// Effective user weights are their weights when taking into account minimum eligible weight
 /// for rewards.
 /// This weight will be the same as user's real weight if they're over the minimum eligible weight,
 /// or 0 if they are under the minimum.
-pub const EFFECTIVE_USER_WEIGHTS: Map<Addr, Uint128> = Map::new("effective_user_weights");
-
-/// Saves any initial weights given to the users.
+pub const EFFECTIVE_USER_WEIGHTS: SnapshotMap<Addr, Uint128> = SnapshotMap::new(
+    "effective_user_weights",
+    "effective_user_weights__checkpoints",
+    "effective_user_weights__changelog",
+    Strategy::EveryBlock,
+);
+
+/// Saves any initial weights given to the users by the given weight source.
 ///
 /// Should only be called when the contract is 'fresh'.
 /// Do *NOT* call after there have already been reward distributions.
 pub fn save_initial_weights(
     ctx: &mut Context,
+    source: Addr,
     initial_weights: Vec<Member>,
     minimum_eligible_weight: Uint128,
 ) -> DistributorResult<()> {
+    let height = ctx.env.block.height;
+
     let mut total_weight = TOTAL_WEIGHT.may_load(ctx.deps.storage)?.unwrap_or_default();
+    let mut eligible_member_count = ELIGIBLE_MEMBER_COUNT
+        .may_load(ctx.deps.storage)?
+        .unwrap_or_default();
 
     for user_weight in initial_weights {
         let user = ctx.deps.api.addr_validate(&user_weight.addr)?;
 
-        if USER_WEIGHTS.has(ctx.deps.storage, user.clone())
-            || EFFECTIVE_USER_WEIGHTS.has(ctx.deps.storage, user.clone())
+        if USER_WEIGHTS
+            .may_load(ctx.deps.storage, user.clone())?
+            .is_some()
+            || EFFECTIVE_USER_WEIGHTS
+                .may_load(ctx.deps.storage, user.clone())?
+                .is_some()
         {
             return Err(DuplicateInitialWeight);
         }
 
-        USER_WEIGHTS.save(
+        SOURCE_USER_WEIGHTS.save(
             ctx.deps.storage,
-            user.clone(),
+            (source.clone(), user.clone()),
             &Uint128::from(user_weight.weight),
         )?;
 
+        let user_weight =
+            calculate_user_weight(ctx.deps.as_ref(), user.clone())?.unwrap_or_default();
+        USER_WEIGHTS.save(ctx.deps.storage, user.clone(), &user_weight, height)?;
+
         let effective_user_weight =
-            calculate_effective_weight(Uint128::from(user_weight.weight), minimum_eligible_weight);
-        EFFECTIVE_USER_WEIGHTS.save(ctx.deps.storage, user, &effective_user_weight)?;
+            calculate_effective_weight(user_weight, minimum_eligible_weight);
+        EFFECTIVE_USER_WEIGHTS.save(
+            ctx.deps.storage,
+            user.clone(),
+            &effective_user_weight,
+            height,
+        )?;
+
+        update_user_tier(
+            ctx.deps.branch(),
+            user,
+            Uint128::zero(),
+            effective_user_weight,
+        )?;
 
         total_weight += effective_user_weight;
+        if !effective_user_weight.is_zero() {
+            eligible_member_count += 1;
+        }
     }
 
-    TOTAL_WEIGHT.save(ctx.deps.storage, &total_weight)?;
+    TOTAL_WEIGHT.save(ctx.deps.storage, &total_weight, height)?;
+    ELIGIBLE_MEMBER_COUNT.save(ctx.deps.storage, &eligible_member_count)?;
 
     Ok(())
 }
 
 pub fn get_initial_weights(deps: Deps, squad_contract: Addr) -> StdResult<Vec<Member>> {
-    let query_msg = ListMembers {
-        start_after: None,
-        limit: Some(30),
-    };
-
-    let mut query_response: MemberListResponse =
-        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: squad_contract.to_string(),
-            msg: to_binary(&query_msg)?,
-        }))?;
-
     let mut member_list: Vec<Member> = vec![];
 
-    while !query_response.members.is_empty() {
-        member_list.append(&mut query_response.members);
-        let query_msg = ListMembers {
-            start_after: Some(member_list.last().unwrap().addr.clone()),
-            limit: Some(30),
-        };
+    loop {
+        let start_after = member_list.last().map(|member| member.addr.clone());
+        let mut members = query_cw4_members(deps, &squad_contract, start_after, 30)?;
 
-        query_response = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-            contract_addr: squad_contract.to_string(),
-            msg: to_binary(&query_msg)?,
-        }))?;
+        if members.is_empty() {
+            break;
+        }
+
+        member_list.append(&mut members);
     }
 
     Ok(member_list)
 }
 
+/// Queries a page of the cw4 contract's members, ordered by address.
+pub fn query_cw4_members(
+    deps: Deps,
+    cw4_contract: &Addr,
+    start_after: Option<String>,
+    limit: u32,
+) -> StdResult<Vec<Member>> {
+    let query_msg = ListMembers {
+        start_after,
+        limit: Some(limit),
+    };
+
+    let query_response: MemberListResponse =
+        deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+            contract_addr: cw4_contract.to_string(),
+            msg: to_json_binary(&query_msg)?,
+        }))?;
+
+    Ok(query_response.members)
+}
+
 /// Updates the users' weights to new ones.
 /// Will calculate any accrued rewards since the last update to their rewards.
+///
+/// Only the sender's component of the users' weights is updated.
 pub fn update_user_weights(
     ctx: &mut Context,
     msg: MemberChangedHookMsg,
 ) -> DistributorResult<Response> {
-    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
-
-    if ctx.info.sender != squad_contract {
-        return Err(Unauthorized);
+    let source = ctx.info.sender.clone();
+
+    match WEIGHT_SOURCES.may_load(ctx.deps.storage, source.clone())? {
+        Some(WeightSourceConfig {
+            kind: WeightSourceKind::Cw4 { .. },
+            ..
+        }) => {}
+        _ => return Err(Unauthorized),
     }
 
-    let mut total_weight = TOTAL_WEIGHT.load(ctx.deps.storage)?;
+    let weight_changes = msg
+        .diffs
+        .into_iter()
+        .map(|diff| {
+            let user = ctx.deps.api.addr_validate(&diff.key)?;
+            Ok((user, diff.new.map(Uint128::from)))
+        })
+        .collect::<StdResult<Vec<(Addr, Option<Uint128>)>>>()?;
+
+    update_source_user_weights(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        source,
+        weight_changes,
+    )?;
 
-    let minimum_eligible_weight = MINIMUM_ELIGIBLE_WEIGHT.load(ctx.deps.storage)?;
+    Ok(Response::new().add_attribute("action", "update_user_weights"))
+}
+
+/// Updates the given source's component of the users' weights, and recalculates their total
+/// weights.
+/// Will calculate any accrued rewards since the last update to their rewards.
+pub fn update_source_user_weights(
+    mut deps: DepsMut,
+    height: u64,
+    source: Addr,
+    weight_changes: Vec<(Addr, Option<Uint128>)>,
+) -> DistributorResult<()> {
+    let mut total_weight = TOTAL_WEIGHT.load(deps.storage)?;
 
-    for user_weight_change in msg.diffs {
-        let user = ctx.deps.api.addr_validate(&user_weight_change.key)?;
+    let minimum_eligible_weight = load_minimum_eligible_weight(deps.storage)?;
 
+    for (user, new_source_weight) in weight_changes {
         let old_user_effective_weight =
-            EFFECTIVE_USER_WEIGHTS.may_load(ctx.deps.storage, user.clone())?;
+            EFFECTIVE_USER_WEIGHTS.may_load(deps.storage, user.clone())?;
 
         match old_user_effective_weight {
             None => {
                 // we have not encountered this user, so we need to ensure their distribution
                 // indices are set to current global indices
-                initialize_user_indices(ctx, user.clone())?;
+                initialize_user_indices(deps.branch(), user.clone())?;
             }
             Some(old_user_effective_weight) => {
                 // the user already had their weight previously, so we use that weight
                 // to calculate how many rewards for each asset they've accrued since we last
                 // calculated their pending rewards
                 update_user_native_distributions(
-                    ctx.deps.branch(),
+                    deps.branch(),
                     user.clone(),
                     old_user_effective_weight,
                 )?;
             }
         };
 
-        match user_weight_change.new {
-            None => {
-                USER_WEIGHTS.remove(ctx.deps.storage, user.clone());
+        match new_source_weight {
+            None => SOURCE_USER_WEIGHTS.remove(deps.storage, (source.clone(), user.clone())),
+            Some(new_source_weight) => SOURCE_USER_WEIGHTS.save(
+                deps.storage,
+                (source.clone(), user.clone()),
+                &new_source_weight,
+            )?,
+        }
 
-                EFFECTIVE_USER_WEIGHTS.remove(ctx.deps.storage, user);
+        let old_user_effective_weight = old_user_effective_weight.unwrap_or_default();
+
+        let new_user_effective_weight = match calculate_user_weight(deps.as_ref(), user.clone())? {
+            None => {
+                USER_WEIGHTS.remove(deps.storage, user.clone(), height)?;
 
-                let old_user_effective_weight = old_user_effective_weight.unwrap_or_default();
+                EFFECTIVE_USER_WEIGHTS.remove(deps.storage, user.clone(), height)?;
 
-                total_weight -= old_user_effective_weight;
+                Uint128::zero()
             }
 
             Some(new_user_weight) => {
-                USER_WEIGHTS.save(
-                    ctx.deps.storage,
+                USER_WEIGHTS.save(deps.storage, user.clone(), &new_user_weight, height)?;
+
+                let effective_user_weight =
+                    calculate_effective_weight(new_user_weight, minimum_eligible_weight);
+                EFFECTIVE_USER_WEIGHTS.save(
+                    deps.storage,
                     user.clone(),
-                    &Uint128::from(new_user_weight),
+                    &effective_user_weight,
+                    height,
                 )?;
 
-                let effective_user_weight = calculate_effective_weight(
-                    Uint128::from(new_user_weight),
-                    minimum_eligible_weight,
-                );
-                EFFECTIVE_USER_WEIGHTS.save(ctx.deps.storage, user, &effective_user_weight)?;
-
-                let old_user_effective_weight = old_user_effective_weight.unwrap_or_default();
-
-                total_weight = total_weight - old_user_effective_weight + effective_user_weight;
+                effective_user_weight
             }
         };
+
+        total_weight = total_weight - old_user_effective_weight + new_user_effective_weight;
+
+        update_user_group_weights(
+            deps.branch(),
+            user.clone(),
+            old_user_effective_weight,
+            new_user_effective_weight,
+        )?;
+
+        update_user_member_eligibility(
+            deps.branch(),
+            user.clone(),
+            old_user_effective_weight,
+            new_user_effective_weight,
+        )?;
+
+        update_user_tier(
+            deps.branch(),
+            user,
+            old_user_effective_weight,
+            new_user_effective_weight,
+        )?;
     }
 
-    TOTAL_WEIGHT.save(ctx.deps.storage, &total_weight)?;
+    TOTAL_WEIGHT.save(deps.storage, &total_weight, height)?;
 
-    Ok(Response::new().add_attribute("action", "update_user_weights"))
+    Ok(())
+}
+
+/// Calculates user's weight as the sum of their weights in each of the weight sources, scaled by
+/// the source's scaling factor.
+///
+/// Returns None if the user has no weight in any of the sources.
+fn calculate_user_weight(deps: Deps, user: Addr) -> StdResult<Option<Uint128>> {
+    let sources = WEIGHT_SOURCES
+        .range(deps.storage, None, None, Ascending)
+        .collect::<StdResult<Vec<(Addr, WeightSourceConfig)>>>()?;
+
+    let mut user_weight: Option<Uint128> = None;
+
+    for (source, config) in sources {
+        if let Some(source_weight) =
+            SOURCE_USER_WEIGHTS.may_load(deps.storage, (source, user.clone()))?
+        {
+            user_weight =
+                Some(user_weight.unwrap_or_default() + source_weight.mul_floor(config.scale));
+        }
+    }
+
+    Ok(user_weight)
 }
 
 /// Calculate user's effective rewards weight, given their actual weight and minimum weight for
@@ -178,32 +320,71 @@ fn calculate_effective_weight(weight: Uint128, minimum_eligible_weight: Uint128)
     }
 }
 
-/// Called for users that we did not encounter previously.
+/// Queries user's weight at the beginning of the given block height.
+pub fn query_user_weight_at(
+    qctx: QueryContext,
+    params: UserWeightAtParams,
+) -> DistributorResult<UserWeightResponse> {
+    let user = qctx.deps.api.addr_validate(&params.user)?;
+
+    let weight = USER_WEIGHTS
+        .may_load_at_height(qctx.deps.storage, user.clone(), params.height)?
+        .unwrap_or_default();
+    let effective_weight = EFFECTIVE_USER_WEIGHTS
+        .may_load_at_height(qctx.deps.storage, user.clone(), params.height)?
+        .unwrap_or_default();
+
+    Ok(UserWeightResponse {
+        user: user.to_string(),
+        weight,
+        effective_weight,
+    })
+}
+
+/// Queries total weight of all users eligible for rewards at the beginning of the given
+/// block height.
+pub fn query_total_weight_at(
+    qctx: QueryContext,
+    params: TotalWeightAtParams,
+) -> DistributorResult<TotalWeightResponse> {
+    let total_weight = TOTAL_WEIGHT
+        .may_load_at_height(qctx.deps.storage, params.height)?
+        .unwrap_or_default();
+
+    Ok(TotalWeightResponse { total_weight })
+}
+
+/// Called for users that currently have no weight.
 ///
-/// Will initialize all their rewards for assets with existing distributions to 0, and set
-/// their rewards indices to current global index for each asset.
-fn initialize_user_indices(ctx: &mut Context, user: Addr) -> DistributorResult<()> {
-    let native_global_indices = NATIVE_GLOBAL_INDICES
-        .range(ctx.deps.storage, None, None, Ascending)
-        .collect::<StdResult<Vec<(String, Decimal)>>>()?;
-
-    for (denom, global_index) in native_global_indices {
-        NATIVE_DISTRIBUTIONS().update(
-            ctx.deps.storage,
-            (user.clone(), denom.clone()),
-            |distribution| -> StdResult<NativeDistribution> {
-                match distribution {
-                    None => Ok(NativeDistribution {
-                        user: user.clone(),
-                        denom,
-                        user_index: global_index,
-                        pending_rewards: Uint128::zero(),
-                    }),
-                    Some(distribution) => Ok(distribution),
-                }
+/// Will set their reward indices for all assets to current global indices, keeping any pending
+/// rewards they got without having a weight, e.g. from before they left or delegate commissions.
+fn initialize_user_indices(deps: DepsMut, user: Addr) -> DistributorResult<()> {
+    let distributions = NATIVE_DISTRIBUTIONS()
+        .idx
+        .user
+        .prefix(user.clone())
+        .range(deps.storage, None, None, Ascending)
+        .map(|distribution| distribution.map(|(_, distribution)| distribution))
+        .collect::<StdResult<Vec<NativeDistribution>>>()?;
+
+    for distribution in distributions {
+        let global_index = NATIVE_GLOBAL_INDICES
+            .may_load(deps.storage, distribution.denom.clone())?
+            .unwrap_or_default();
+
+        NATIVE_DISTRIBUTIONS().save(
+            deps.storage,
+            (user.clone(), distribution.denom.clone()),
+            &NativeDistribution {
+                user_index: global_index,
+                ..distribution
             },
         )?;
     }
 
+    save_user_checkpoint(deps.storage, user.clone())?;
+
+    initialize_user_snapshot_cursor(deps.storage, user)?;
+
     Ok(())
 }
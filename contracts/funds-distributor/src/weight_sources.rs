@@ -0,0 +1,385 @@
+use crate::stake_weights::get_initial_stakes;
+use crate::state::SQUAD_CONTRACT;
+use crate::user_weights::{
+    get_initial_weights, query_cw4_members, update_source_user_weights, USER_WEIGHTS,
+};
+use common::cw::{Context, QueryContext};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Decimal, DepsMut, Order, Response, StdResult, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use funds_distributor_api::api::{
+    AddWeightSourceMsg, ChangeWeightSourceMsg, RemoveWeightSourceMsg, WeightSource,
+    WeightSourceKind, WeightSourcesResponse,
+};
+use funds_distributor_api::error::DistributorError::{
+    Unauthorized, WeightSourceAlreadyRegistered, WeightSourceChangeInProgress,
+    WeightSourceNotRegistered,
+};
+use funds_distributor_api::error::DistributorResult;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cw_serde]
+pub struct WeightSourceConfig {
+    pub kind: WeightSourceKind,
+    /// Factor that the source's weights are multiplied by before being added to users' weights
+    pub scale: Decimal,
+}
+
+/// Sources whose weights make up the users' weights, keyed by the address sending their updates.
+pub const WEIGHT_SOURCES: Map<Addr, WeightSourceConfig> = Map::new("weight_sources");
+
+/// Users' weights in each of the weight sources, before scaling, keyed by (source, user).
+pub const SOURCE_USER_WEIGHTS: Map<(Addr, Addr), Uint128> = Map::new("source_user_weights");
+
+/// Most members that cw4 contracts return in a single page.
+const MAX_MEMBERS_SYNC_LIMIT: u32 = 30;
+
+#[cw_serde]
+pub struct WeightSourceChange {
+    pub old_source: Addr,
+    pub new_source: Addr,
+    /// Last of the new source's members synced so far, in the order of addresses
+    pub last_synced: Option<Addr>,
+}
+
+/// Weight source change that is in progress, with its users not all synced yet.
+pub const WEIGHT_SOURCE_CHANGE: Item<WeightSourceChange> = Item::new("weight_source_change");
+
+/// Registers a new weight source, and adds its current members' weights to the users' weights.
+pub fn add_weight_source(
+    ctx: &mut Context,
+    msg: AddWeightSourceMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    // the source being changed to could be added again, before it was fully synced
+    if let Some(change) = WEIGHT_SOURCE_CHANGE.may_load(ctx.deps.storage)? {
+        return Err(WeightSourceChangeInProgress {
+            old_source: change.old_source.to_string(),
+            new_source: change.new_source.to_string(),
+        });
+    }
+
+    let source = match &msg.source {
+        WeightSourceKind::Cw4 { addr } | WeightSourceKind::Cw20Stake { addr } => {
+            ctx.deps.api.addr_validate(addr)?
+        }
+        WeightSourceKind::NativeStaking { .. } => ctx.env.contract.address.clone(),
+    };
+
+    if WEIGHT_SOURCES.has(ctx.deps.storage, source.clone()) {
+        return Err(WeightSourceAlreadyRegistered {
+            addr: source.to_string(),
+        });
+    }
+
+    WEIGHT_SOURCES.save(
+        ctx.deps.storage,
+        source.clone(),
+        &WeightSourceConfig {
+            kind: msg.source.clone(),
+            scale: msg.scale,
+        },
+    )?;
+
+    // native staking delegations cannot be listed, so they only get synced through snapshots
+    let initial_weights = match msg.source {
+        WeightSourceKind::Cw4 { .. } => get_initial_weights(ctx.deps.as_ref(), source.clone())?
+            .into_iter()
+            .map(|member| (member.addr, Uint128::from(member.weight)))
+            .collect(),
+        WeightSourceKind::Cw20Stake { .. } => {
+            get_initial_stakes(ctx.deps.as_ref(), source.clone())?
+        }
+        WeightSourceKind::NativeStaking { .. } => vec![],
+    };
+
+    let weight_changes = initial_weights
+        .into_iter()
+        .map(|(user, weight)| {
+            let user = ctx.deps.api.addr_validate(&user)?;
+            Ok((user, Some(weight)))
+        })
+        .collect::<StdResult<Vec<(Addr, Option<Uint128>)>>>()?;
+
+    update_source_user_weights(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        source.clone(),
+        weight_changes,
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_weight_source")
+        .add_attribute("source", source.to_string())
+        .add_attribute("scale", msg.scale.to_string()))
+}
+
+/// Unregisters a weight source, and removes its members' weights from the users' weights.
+pub fn remove_weight_source(
+    ctx: &mut Context,
+    msg: RemoveWeightSourceMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let source = ctx.deps.api.addr_validate(&msg.addr)?;
+
+    if !WEIGHT_SOURCES.has(ctx.deps.storage, source.clone()) {
+        return Err(WeightSourceNotRegistered {
+            addr: source.to_string(),
+        });
+    }
+
+    let weight_changes = SOURCE_USER_WEIGHTS
+        .prefix(source.clone())
+        .keys(ctx.deps.storage, None, None, Order::Ascending)
+        .map(|user| user.map(|user| (user, None)))
+        .collect::<StdResult<Vec<(Addr, Option<Uint128>)>>>()?;
+
+    update_source_user_weights(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        source.clone(),
+        weight_changes,
+    )?;
+
+    WEIGHT_SOURCES.remove(ctx.deps.storage, source.clone());
+
+    Ok(Response::new()
+        .add_attribute("action", "remove_weight_source")
+        .add_attribute("source", source.to_string()))
+}
+
+/// Replaces a weight source with a cw4 contract, and syncs the next page of the new source's
+/// members.
+///
+/// The old source stops counting towards the users' weights right away, but the users keep their
+/// weights until they are synced, which settles their rewards accrued until then.
+/// The first message starts the change, and the following ones continue it until all the new
+/// source's members and all the old source's users are synced.
+pub fn change_weight_source(
+    ctx: &mut Context,
+    msg: ChangeWeightSourceMsg,
+) -> DistributorResult<Response> {
+    let squad_contract = SQUAD_CONTRACT.load(ctx.deps.storage)?;
+
+    if ctx.info.sender != squad_contract {
+        return Err(Unauthorized);
+    }
+
+    let old_source = ctx.deps.api.addr_validate(&msg.old_source)?;
+    let new_source = ctx.deps.api.addr_validate(&msg.new_source)?;
+
+    let change = match WEIGHT_SOURCE_CHANGE.may_load(ctx.deps.storage)? {
+        Some(change) => {
+            if change.old_source != old_source || change.new_source != new_source {
+                return Err(WeightSourceChangeInProgress {
+                    old_source: change.old_source.to_string(),
+                    new_source: change.new_source.to_string(),
+                });
+            }
+            change
+        }
+        None => start_weight_source_change(ctx.deps.branch(), old_source, new_source)?,
+    };
+
+    let limit = msg
+        .limit
+        .unwrap_or(MAX_MEMBERS_SYNC_LIMIT)
+        .min(MAX_MEMBERS_SYNC_LIMIT);
+
+    let members = query_cw4_members(
+        ctx.deps.as_ref(),
+        &change.new_source,
+        change.last_synced.as_ref().map(|user| user.to_string()),
+        limit,
+    )?
+    .into_iter()
+    .map(|member| {
+        let user = ctx.deps.api.addr_validate(&member.addr)?;
+        Ok((user, Uint128::from(member.weight)))
+    })
+    .collect::<StdResult<BTreeMap<Addr, Uint128>>>()?;
+
+    // on the last page, the rest of the old source's users have to be synced as well
+    let is_last_page = members.len() < limit as usize;
+    let last_synced = if is_last_page {
+        None
+    } else {
+        members.keys().next_back().cloned()
+    };
+
+    let old_weights = SOURCE_USER_WEIGHTS
+        .prefix(change.old_source.clone())
+        .range(
+            ctx.deps.storage,
+            change.last_synced.clone().map(Bound::exclusive),
+            last_synced.clone().map(Bound::inclusive),
+            Order::Ascending,
+        )
+        .collect::<StdResult<BTreeMap<Addr, Uint128>>>()?;
+
+    let mut added: Vec<String> = vec![];
+    let mut removed: Vec<String> = vec![];
+    let mut changed: Vec<String> = vec![];
+
+    let users = members
+        .keys()
+        .chain(old_weights.keys())
+        .cloned()
+        .collect::<BTreeSet<Addr>>();
+
+    for user in &users {
+        match (old_weights.get(user), members.get(user)) {
+            (None, Some(_)) => added.push(user.to_string()),
+            (Some(_), None) => removed.push(user.to_string()),
+            (Some(old_weight), Some(new_weight)) if old_weight != new_weight => {
+                changed.push(user.to_string())
+            }
+            _ => {}
+        }
+
+        SOURCE_USER_WEIGHTS.remove(ctx.deps.storage, (change.old_source.clone(), user.clone()));
+    }
+
+    let weight_changes = users
+        .into_iter()
+        .map(|user| {
+            let weight = members.get(&user).copied();
+            (user, weight)
+        })
+        .collect();
+
+    update_source_user_weights(
+        ctx.deps.branch(),
+        ctx.env.block.height,
+        change.new_source.clone(),
+        weight_changes,
+    )?;
+
+    match last_synced {
+        None => WEIGHT_SOURCE_CHANGE.remove(ctx.deps.storage),
+        Some(last_synced) => WEIGHT_SOURCE_CHANGE.save(
+            ctx.deps.storage,
+            &WeightSourceChange {
+                last_synced: Some(last_synced),
+                ..change.clone()
+            },
+        )?,
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "change_weight_source")
+        .add_attribute("old_source", change.old_source.to_string())
+        .add_attribute("new_source", change.new_source.to_string())
+        .add_attribute("added", added.join(","))
+        .add_attribute("removed", removed.join(","))
+        .add_attribute("changed", changed.join(","))
+        .add_attribute("complete", is_last_page.to_string()))
+}
+
+/// Registers the new source in place of the old one, with the same scale.
+///
+/// Users' weights are not synced from the new source yet, `ChangeWeightSource` has to be sent
+/// until the change is complete for that.
+pub fn start_weight_source_change(
+    deps: DepsMut,
+    old_source: Addr,
+    new_source: Addr,
+) -> DistributorResult<WeightSourceChange> {
+    if let Some(change) = WEIGHT_SOURCE_CHANGE.may_load(deps.storage)? {
+        return Err(WeightSourceChangeInProgress {
+            old_source: change.old_source.to_string(),
+            new_source: change.new_source.to_string(),
+        });
+    }
+
+    let config = WEIGHT_SOURCES
+        .may_load(deps.storage, old_source.clone())?
+        .ok_or_else(|| WeightSourceNotRegistered {
+            addr: old_source.to_string(),
+        })?;
+
+    if WEIGHT_SOURCES.has(deps.storage, new_source.clone()) {
+        return Err(WeightSourceAlreadyRegistered {
+            addr: new_source.to_string(),
+        });
+    }
+
+    WEIGHT_SOURCES.remove(deps.storage, old_source.clone());
+    WEIGHT_SOURCES.save(
+        deps.storage,
+        new_source.clone(),
+        &WeightSourceConfig {
+            kind: WeightSourceKind::Cw4 {
+                addr: new_source.to_string(),
+            },
+            scale: config.scale,
+        },
+    )?;
+
+    let change = WeightSourceChange {
+        old_source,
+        new_source,
+        last_synced: None,
+    };
+    WEIGHT_SOURCE_CHANGE.save(deps.storage, &change)?;
+
+    Ok(change)
+}
+
+/// Registers the hook source as the only weight source, and attributes all the existing user
+/// weights to it.
+///
+/// Used when migrating from a version that only supported a single weight source.
+pub fn migrate_to_weight_sources(deps: DepsMut) -> DistributorResult<()> {
+    if !WEIGHT_SOURCES.is_empty(deps.storage) {
+        return Ok(());
+    }
+
+    let hook_source = SQUAD_CONTRACT.load(deps.storage)?;
+
+    WEIGHT_SOURCES.save(
+        deps.storage,
+        hook_source.clone(),
+        &WeightSourceConfig {
+            kind: WeightSourceKind::Cw4 {
+                addr: hook_source.to_string(),
+            },
+            scale: Decimal::one(),
+        },
+    )?;
+
+    let user_weights = USER_WEIGHTS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+
+    for (user, weight) in user_weights {
+        SOURCE_USER_WEIGHTS.save(deps.storage, (hook_source.clone(), user), &weight)?;
+    }
+
+    Ok(())
+}
+
+pub fn query_weight_sources(qctx: QueryContext) -> DistributorResult<WeightSourcesResponse> {
+    let sources = WEIGHT_SOURCES
+        .range(qctx.deps.storage, None, None, Order::Ascending)
+        .map(|source| {
+            source.map(|(addr, config)| WeightSource {
+                addr: addr.to_string(),
+                kind: config.kind,
+                scale: config.scale,
+            })
+        })
+        .collect::<StdResult<Vec<WeightSource>>>()?;
+
+    Ok(WeightSourcesResponse { sources })
+}
@@ -1,7 +1,8 @@
 #![allow(unused_imports)]
 
 use cosmwasm_std::{
-    to_binary, Addr, CosmosMsg, CustomQuery, Querier, QuerierWrapper, StdResult, WasmMsg, WasmQuery,
+    to_json_binary, Addr, CosmosMsg, CustomQuery, Querier, QuerierWrapper, StdResult, WasmMsg,
+    WasmQuery,
 };
 use cw20_base::msg::ExecuteMsg;
 use schemars::JsonSchema;
@@ -18,7 +19,7 @@ impl CwTemplateContract {
     }
 
     pub fn call<T: Serialize + ?Sized>(&self, msg: &T) -> StdResult<CosmosMsg> {
-        let msg = to_binary(msg)?;
+        let msg = to_json_binary(msg)?;
         Ok(WasmMsg::Execute {
             contract_addr: self.addr().into(),
             msg,
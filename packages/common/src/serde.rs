@@ -1,6 +1,6 @@
 use std::any::type_name;
 
-use cosmwasm_std::{from_slice, Binary, StdError, StdResult};
+use cosmwasm_std::{from_json, Binary, StdError, StdResult};
 use serde::{de::DeserializeOwned, Serialize};
 
 pub trait SerdeExt {
@@ -25,7 +25,7 @@ pub trait DeserdeExt {
 
 impl DeserdeExt for Binary {
     fn to_t<T: DeserializeOwned>(&self) -> StdResult<T> {
-        from_slice(self.as_slice())
+        from_json(self.as_slice())
     }
 }
 
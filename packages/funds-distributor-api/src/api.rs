@@ -1,6 +1,7 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128};
 use cw4::MemberDiff;
+use cw_utils::Expiration;
 
 #[cw_serde]
 pub struct UpdateUserWeightsMsg {
@@ -14,6 +15,166 @@ pub struct UpdateMinimumEligibleWeightMsg {
     pub minimum_eligible_weight: Uint128,
 }
 
+#[cw_serde]
+pub struct AddWeightSourceMsg {
+    pub source: WeightSourceKind,
+    /// Factor that the source's weights are multiplied by before being added to users' weights
+    pub scale: Decimal,
+}
+
+#[cw_serde]
+pub enum WeightSourceKind {
+    /// cw4 contract, reporting weight changes through `MemberChangedHook`
+    Cw4 { addr: String },
+    /// cw20-stake style staking contract, reporting stake changes through `StakeChangeHook`
+    Cw20Stake { addr: String },
+    /// Users' native staking delegations, optionally counting only delegations to the given
+    /// validators.
+    /// Registered under the distributor's own address, and updated through
+    /// `UpdateNativeStakeWeights` snapshots.
+    NativeStaking { validators: Option<Vec<String>> },
+}
+
+/// Hook message sent by cw20-stake style staking contracts.
+#[cw_serde]
+pub enum StakeChangedHookMsg {
+    Stake { addr: Addr, amount: Uint128 },
+    Unstake { addr: Addr, amount: Uint128 },
+}
+
+#[cw_serde]
+pub struct UpdateNativeStakeWeightsMsg {
+    /// Users whose weights will be set to their current native staking delegations
+    pub users: Vec<String>,
+}
+
+#[cw_serde]
+pub struct RemoveWeightSourceMsg {
+    pub addr: String,
+}
+
+#[cw_serde]
+pub struct ChangeWeightSourceMsg {
+    /// Registered weight source to be replaced
+    pub old_source: String,
+    /// cw4 contract whose members' weights replace the old source's, with the same scale
+    pub new_source: String,
+    /// Maximum number of the new source's members to sync in this message
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct UpdateGroupMembersMsg {
+    pub group_id: String,
+    /// Users to be added to the group
+    pub add: Vec<String>,
+    /// Users to be removed from the group
+    pub remove: Vec<String>,
+}
+
+#[cw_serde]
+pub struct UpdateRewardTiersMsg {
+    /// New reward tiers, ordered by strictly increasing minimum weights, with shares adding up
+    /// to 1. The lowest tier's minimum weight is the minimum eligible weight, so there has to be
+    /// at least one tier
+    pub tiers: Vec<RewardTier>,
+    /// Maximum number of users to go through in this message
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct RewardTier {
+    pub name: String,
+    /// Minimum effective weight that users need to be in this tier, up to the next tier's minimum
+    pub min_weight: Uint128,
+    /// Share of each tiered distribution that goes to this tier's users
+    pub share: Decimal,
+}
+
+#[cw_serde]
+pub struct UpdateDenomAllowlistMsg {
+    /// Whether distributions should be restricted to the allowlisted denoms. Unchanged if not set
+    pub enabled: Option<bool>,
+    /// Denoms to be added to the allowlist, or to have their minimum amounts updated
+    pub add: Vec<AllowedDenom>,
+    /// Denoms to be removed from the allowlist
+    pub remove: Vec<String>,
+}
+
+#[cw_serde]
+pub struct AllowedDenom {
+    pub denom: String,
+    /// Minimum amount of the denom that has to be distributed at once
+    pub min_amount: Uint128,
+}
+
+#[cw_serde]
+pub struct RetireDenomMsg {
+    pub denom: String,
+    /// Maximum number of users (or index history entries) to go through in this call
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct UpdateAuthorizedDistributorsMsg {
+    /// Addresses to be allowed to fund distributions
+    pub add: Vec<String>,
+    /// Addresses to no longer be allowed to fund distributions
+    pub remove: Vec<String>,
+}
+
+#[cw_serde]
+pub struct UpdateProtocolFeesMsg {
+    /// Address that receives the protocol fees. Unchanged if not set
+    pub fee_collector: Option<String>,
+    /// Denoms to have their fees set or updated
+    pub set: Vec<ProtocolFee>,
+    /// Denoms to no longer be charged a fee
+    pub remove: Vec<String>,
+}
+
+#[cw_serde]
+pub struct ProtocolFee {
+    pub denom: String,
+    /// Part of each `DistributeNative` deposit of the denom sent to the fee collector, in basis
+    /// points
+    pub fee_bps: u16,
+}
+
+#[cw_serde]
+pub struct RegisterMerkleDistributionMsg {
+    /// Hex-encoded root of the Merkle tree of (index, address, amount) entries
+    pub root: String,
+    pub denom: String,
+    /// Total amount of all the entries, which has to be sent along with the message
+    pub total: Uint128,
+    /// After this, no more claims are possible and the unclaimed funds can be swept
+    pub expiry: Expiration,
+}
+
+#[cw_serde]
+pub struct ClaimMerkleMsg {
+    /// ID of the Merkle distribution
+    pub id: u64,
+    /// Index of the sender's entry in the Merkle tree
+    pub index: u32,
+    pub amount: Uint128,
+    /// Hex-encoded sibling hashes, from the leaf towards the root
+    pub proof: Vec<String>,
+}
+
+#[cw_serde]
+pub struct SweepMerkleDistributionMsg {
+    pub id: u64,
+}
+
+#[cw_serde]
+pub struct ImportStateMsg {
+    /// Users to be credited their rewards, as returned by `ExportState` on the old contract.
+    /// Their weights are not imported, those come from the contract's weight sources
+    pub users: Vec<ExportedUser>,
+}
+
 #[cw_serde]
 pub struct UserWeight {
     pub user: String,
@@ -25,6 +186,135 @@ pub struct ClaimRewardsMsg {
     pub user: String,
     /// Native denominations to be claimed
     pub native_denoms: Option<Vec<String>>,
+    /// If set, claimed rewards are sent along with an execute message to the given contract,
+    /// instead of being sent to the user.
+    /// Only the user can claim in this mode, and the contract has to be an allowed compounding target.
+    pub compound_to: Option<CompoundTarget>,
+}
+
+#[cw_serde]
+pub struct CompoundTarget {
+    /// Contract to execute with the claimed rewards as funds
+    pub contract: String,
+    /// Execute message to send to the contract, e.g. a bond or stake message
+    pub msg: Binary,
+}
+
+#[cw_serde]
+pub struct UpdateCompoundingTargetsMsg {
+    /// Contracts to be added to the allowed compounding targets
+    pub add: Vec<String>,
+    /// Contracts to be removed from the allowed compounding targets
+    pub remove: Vec<String>,
+}
+
+#[cw_serde]
+pub struct GrantClaimOperatorMsg {
+    /// Address that will be allowed to claim rewards and change the reward recipient for the sender
+    pub operator: String,
+    /// When the operator's permission expires. Never expires if not set
+    pub expires: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct RevokeClaimOperatorMsg {
+    pub operator: String,
+}
+
+#[cw_serde]
+pub struct SetRewardRecipientMsg {
+    pub user: String,
+    /// Address that will receive the user's claimed rewards. Resets to the user if not set
+    pub recipient: Option<String>,
+}
+
+#[cw_serde]
+pub struct SetRewardSplitMsg {
+    pub user: String,
+    /// Recipients that the user's claimed rewards are split between, with shares adding up to 1.
+    /// Claimed rewards go to the user's reward recipient again if empty
+    pub splits: Vec<RewardSplit>,
+}
+
+#[cw_serde]
+pub struct RewardSplit {
+    pub recipient: String,
+    pub share: Decimal,
+}
+
+#[cw_serde]
+pub struct SetDelegateMsg {
+    pub user: String,
+    /// Delegate to earn a commission on the user's rewards. Removes the delegate if not set
+    pub delegate: Option<DelegateCommission>,
+}
+
+#[cw_serde]
+pub struct DelegateCommission {
+    pub delegate: String,
+    /// Part of the rewards the user accrues through their weight credited to the delegate, in
+    /// basis points. Only applies to regular distributions, not to snapshot, group, equal split
+    /// or tiered ones
+    pub commission_bps: u16,
+}
+
+#[cw_serde]
+pub enum HookFailurePolicy {
+    /// A failing hook fails the whole distribution or claim
+    Abort,
+    /// A failing hook is ignored, and its failure only recorded in the response attributes
+    Ignore,
+}
+
+#[cw_serde]
+pub struct AddHookMsg {
+    pub addr: String,
+    pub on_failure: HookFailurePolicy,
+}
+
+#[cw_serde]
+pub struct RemoveHookMsg {
+    pub addr: String,
+}
+
+/// Messages sent to the registered hooks.
+#[cw_serde]
+pub enum FundsDistributorHookMsg {
+    DistributionHook(DistributionHookMsg),
+    ClaimHook(ClaimHookMsg),
+}
+
+#[cw_serde]
+pub struct DistributionHookMsg {
+    pub distributor: String,
+    /// Funds that were distributed
+    pub funds: Vec<Coin>,
+    /// Total weight of all users eligible for rewards at the time of the distribution.
+    /// Zero for equal split distributions
+    pub total_weight: Uint128,
+    /// Height whose weights the funds were split by, if distributed against a past snapshot
+    pub snapshot_height: Option<u64>,
+    /// Group whose members the funds were distributed to, if not distributed to all users
+    pub group_id: Option<String>,
+    /// Number of users the funds were split equally between, for equal split distributions
+    pub eligible_member_count: Option<u64>,
+}
+
+#[cw_serde]
+pub struct ClaimHookMsg {
+    pub user: String,
+    /// Addresses that received the claimed funds, with a single entry unless the user split
+    /// their rewards between multiple recipients
+    pub recipients: Vec<ClaimRecipient>,
+    /// All the funds that were claimed
+    pub funds: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct ClaimRecipient {
+    pub recipient: String,
+    /// Part of the claimed funds that the recipient received
+    pub funds: Vec<Coin>,
 }
 
 #[cw_serde]
@@ -37,6 +327,165 @@ pub struct UserRewardsParams {
 #[cw_serde]
 pub struct UserRewardsResponse {
     pub native_rewards: Vec<NativeReward>,
+    /// Whether the user has more unsettled snapshot distributions than are included in the
+    /// rewards. Claiming settles them, a batch at a time
+    pub snapshot_settlement_pending: bool,
+}
+
+#[cw_serde]
+pub struct UserWeightAtParams {
+    pub user: String,
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct UserWeightResponse {
+    pub user: String,
+    /// User's weight, summed across all the weight sources
+    pub weight: Uint128,
+    /// User's weight as counted for rewards, taking into account the minimum eligible weight
+    pub effective_weight: Uint128,
+}
+
+#[cw_serde]
+pub struct TotalWeightAtParams {
+    pub height: u64,
+}
+
+#[cw_serde]
+pub struct TotalWeightResponse {
+    /// Total weight of all users eligible for rewards
+    pub total_weight: Uint128,
+}
+
+#[cw_serde]
+pub struct EligibleMemberCountResponse {
+    /// Number of users with a non-zero weight eligible for rewards
+    pub eligible_member_count: u64,
+}
+
+#[cw_serde]
+pub struct GroupMembersParams {
+    pub group_id: String,
+    pub start_after: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct GroupMembersResponse {
+    pub group_id: String,
+    /// Total weight of the group's members eligible for rewards
+    pub total_weight: Uint128,
+    pub members: Vec<String>,
+}
+
+#[cw_serde]
+pub struct RewardTiersResponse {
+    pub tiers: Vec<RewardTierResponse>,
+}
+
+#[cw_serde]
+pub struct RewardTierResponse {
+    pub name: String,
+    pub min_weight: Uint128,
+    pub share: Decimal,
+    /// Total weight of the users currently in the tier
+    pub total_weight: Uint128,
+}
+
+#[cw_serde]
+pub struct DenomAllowlistResponse {
+    /// Whether distributions are restricted to the allowlisted denoms
+    pub enabled: bool,
+    pub denoms: Vec<AllowedDenom>,
+}
+
+#[cw_serde]
+pub struct AuthorizedDistributorsResponse {
+    /// Addresses allowed to fund distributions. Anyone can fund distributions if empty
+    pub distributors: Vec<String>,
+}
+
+#[cw_serde]
+pub struct ProtocolFeesResponse {
+    pub fee_collector: Option<String>,
+    pub fees: Vec<ProtocolFee>,
+}
+
+#[cw_serde]
+pub struct DistributionStatsParams {
+    /// Native denominations to be queried for stats. All distributed denoms if not set
+    pub native_denoms: Option<Vec<String>>,
+}
+
+#[cw_serde]
+pub struct DistributionStatsResponse {
+    pub stats: Vec<DenomDistributionStats>,
+}
+
+#[cw_serde]
+pub struct DenomDistributionStats {
+    pub denom: String,
+    /// Total amount distributed to the users through `DistributeNative`, after protocol fees
+    pub distributed: Uint128,
+    /// Total amount sent to the fee collector
+    pub protocol_fees: Uint128,
+}
+
+#[cw_serde]
+pub struct MerkleDistributionParams {
+    pub id: u64,
+}
+
+#[cw_serde]
+pub struct MerkleDistributionResponse {
+    pub id: u64,
+    pub root: String,
+    pub denom: String,
+    pub total: Uint128,
+    /// Total amount claimed so far
+    pub claimed: Uint128,
+    pub expiry: Expiration,
+    /// Whether the unclaimed funds were already swept
+    pub swept: bool,
+}
+
+#[cw_serde]
+pub struct MerkleClaimedParams {
+    pub id: u64,
+    pub index: u32,
+}
+
+#[cw_serde]
+pub struct MerkleClaimedResponse {
+    pub claimed: bool,
+}
+
+#[cw_serde]
+pub struct ExportStateParams {
+    pub start_after: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct ExportStateResponse {
+    /// Users with a weight or rewards, ordered by address
+    pub users: Vec<ExportedUser>,
+}
+
+#[cw_serde]
+pub struct ExportedUser {
+    pub user: String,
+    /// All of the user's rewards, after their delegate's commission
+    pub rewards: Vec<NativeReward>,
+    /// Commission on the user's rewards not credited to their delegate yet, if they have one
+    pub delegate_commission: Option<ExportedCommission>,
+}
+
+#[cw_serde]
+pub struct ExportedCommission {
+    pub delegate: String,
+    pub commission: Vec<NativeReward>,
 }
 
 #[cw_serde]
@@ -44,6 +493,82 @@ pub struct MinimumEligibleWeightResponse {
     pub minimum_eligible_weight: Uint128,
 }
 
+#[cw_serde]
+pub struct CompoundingTargetsResponse {
+    pub targets: Vec<String>,
+}
+
+#[cw_serde]
+pub struct ClaimOperatorsParams {
+    pub user: String,
+    pub start_after: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[cw_serde]
+pub struct ClaimOperatorsResponse {
+    pub operators: Vec<ClaimOperator>,
+}
+
+#[cw_serde]
+pub struct ClaimOperator {
+    pub operator: String,
+    pub expires: Expiration,
+}
+
+#[cw_serde]
+pub struct RewardRecipientParams {
+    pub user: String,
+}
+
+#[cw_serde]
+pub struct RewardRecipientResponse {
+    pub recipient: String,
+}
+
+#[cw_serde]
+pub struct RewardSplitParams {
+    pub user: String,
+}
+
+#[cw_serde]
+pub struct RewardSplitResponse {
+    pub splits: Vec<RewardSplit>,
+}
+
+#[cw_serde]
+pub struct DelegateParams {
+    pub user: String,
+}
+
+#[cw_serde]
+pub struct DelegateResponse {
+    pub delegate: Option<DelegateCommission>,
+}
+
+#[cw_serde]
+pub struct HooksResponse {
+    pub hooks: Vec<Hook>,
+}
+
+#[cw_serde]
+pub struct Hook {
+    pub addr: String,
+    pub on_failure: HookFailurePolicy,
+}
+
+#[cw_serde]
+pub struct WeightSourcesResponse {
+    pub sources: Vec<WeightSource>,
+}
+
+#[cw_serde]
+pub struct WeightSource {
+    pub addr: String,
+    pub kind: WeightSourceKind,
+    pub scale: Decimal,
+}
+
 #[cw_serde]
 pub struct NativeReward {
     pub denom: String,
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 pub type DistributorResult<T> = Result<T, DistributorError>;
@@ -14,8 +14,135 @@ pub enum DistributorError {
     #[error("Cannot distribute - total weight of all users is 0")]
     ZeroTotalWeight,
 
+    #[error("Cannot distribute - there are no users eligible for rewards")]
+    NoEligibleMembers,
+
+    #[error("Cannot distribute - no funds to distribute were sent")]
+    NoFundsToDistribute,
+
     #[error("Duplicate initial user weight found")]
     DuplicateInitialWeight,
+
+    #[error("Contract {contract} is not an allowed compounding target")]
+    CompoundingTargetNotAllowed { contract: String },
+
+    #[error("Invalid expiration - already expired")]
+    InvalidExpiration,
+
+    #[error("Hook {addr} is already registered")]
+    HookAlreadyRegistered { addr: String },
+
+    #[error("Hook {addr} is not registered")]
+    HookNotRegistered { addr: String },
+
+    #[error("Weight source {addr} is already registered")]
+    WeightSourceAlreadyRegistered { addr: String },
+
+    #[error("Weight source {addr} is not registered")]
+    WeightSourceNotRegistered { addr: String },
+
+    #[error("Weight source {old_source} is already being changed to {new_source}")]
+    WeightSourceChangeInProgress {
+        old_source: String,
+        new_source: String,
+    },
+
+    #[error("Native staking weight source is not registered")]
+    NativeStakingSourceNotRegistered,
+
+    #[error("Cannot unstake more than the staked amount")]
+    InsufficientStake,
+
+    #[error("Invalid snapshot height {height} - cannot be in the future")]
+    InvalidSnapshotHeight { height: u64 },
+
+    #[error("Invalid reward tiers - {reason}")]
+    InvalidRewardTiers { reason: String },
+
+    #[error("Reward tiers are being updated, and have to finish updating first")]
+    RewardTiersUpdateInProgress,
+
+    #[error("Denom {denom} was never distributed")]
+    DenomNotDistributed { denom: String },
+
+    #[error("Cannot retire denom {denom} - some of its rewards are not claimed yet")]
+    DenomNotFullyClaimed { denom: String },
+
+    #[error("Cannot retire another denom while {denom} is being retired")]
+    DenomRetirementInProgress { denom: String },
+
+    #[error("Invalid protocol fee of {fee_bps} basis points - cannot exceed 10000")]
+    InvalidProtocolFee { fee_bps: u16 },
+
+    #[error("Protocol fees cannot be set without a fee collector")]
+    FeeCollectorNotSet,
+
+    #[error("Invalid reward split - {reason}")]
+    InvalidRewardSplit { reason: String },
+
+    #[error("Invalid delegate commission of {commission_bps} basis points - cannot exceed 10000")]
+    InvalidDelegateCommission { commission_bps: u16 },
+
+    #[error("Users cannot be their own delegates")]
+    SelfDelegation,
+
+    #[error("Invalid Merkle root - must be a hex-encoded 32-byte hash")]
+    InvalidMerkleRoot,
+
+    #[error("Invalid Merkle proof")]
+    InvalidMerkleProof,
+
+    #[error("Merkle distribution must be funded with exactly {total}{denom}")]
+    InvalidMerkleDistributionFunds { denom: String, total: Uint128 },
+
+    #[error("Merkle distribution {id} not found")]
+    MerkleDistributionNotFound { id: u64 },
+
+    #[error("Merkle distribution {id} has expired")]
+    MerkleDistributionExpired { id: u64 },
+
+    #[error("Merkle distribution {id} has not expired yet")]
+    MerkleDistributionNotExpired { id: u64 },
+
+    #[error("Merkle distribution {id} was already swept")]
+    MerkleDistributionSwept { id: u64 },
+
+    #[error("Entry {index} of Merkle distribution {id} was already claimed")]
+    MerkleAlreadyClaimed { id: u64, index: u32 },
+
+    #[error("Claims exceed the total of Merkle distribution {id}")]
+    MerkleDistributionExceeded { id: u64 },
+
+    #[error("State can only be imported into a contract instantiated for it, until the import is closed")]
+    StateImportClosed,
+
+    #[error("Cannot distribute funds until the state import is closed")]
+    StateImportOpen,
+
+    #[error("State of user {user} was already imported")]
+    UserAlreadyImported { user: String },
+
+    #[error("User {user} has snapshot distributions to be settled first, by claiming for them")]
+    SnapshotSettlementPending { user: String },
+
+    #[error("Cannot import state - owing {owed}{denom} with a balance of only {balance}{denom}")]
+    InsolventStateImport {
+        denom: String,
+        owed: Uint128,
+        balance: Uint128,
+    },
+
+    #[error("Cannot migrate from contract {contract}")]
+    InvalidMigrationContract { contract: String },
+
+    #[error("Cannot migrate from version {from} to older version {to}")]
+    MigrationDowngrade { from: String, to: String },
+
+    #[error("Invalid contract version {version}")]
+    InvalidContractVersion { version: String },
+
+    #[error("Unknown reply ID: {id}")]
+    UnknownReplyId { id: u64 },
 }
 
 impl DistributorError {
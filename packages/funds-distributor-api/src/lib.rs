@@ -1,3 +1,4 @@
 pub mod api;
 pub mod error;
+pub mod merkle;
 pub mod msg;
@@ -0,0 +1,105 @@
+//! Hashing used by Merkle distributions, shared between the contract and the off-chain tree
+//! builder.
+//!
+//! Leaves are `sha256(0x00 || index || address length || address || amount)`, with the index and
+//! the address length as big-endian u32, and the amount as big-endian u128. Inner nodes are
+//! `sha256(0x01 || left || right)`, so that a leaf can never be passed off as an inner node.
+//! At each level of the tree, the node's index decides whether it is hashed as the left or the
+//! right child of its parent.
+
+use cosmwasm_std::Uint128;
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Hashes a single entry of a Merkle distribution.
+pub fn leaf_hash(index: u32, address: &str, amount: Uint128) -> Hash {
+    Sha256::new()
+        .chain_update([LEAF_PREFIX])
+        .chain_update(index.to_be_bytes())
+        .chain_update((address.len() as u32).to_be_bytes())
+        .chain_update(address.as_bytes())
+        .chain_update(amount.u128().to_be_bytes())
+        .finalize()
+        .into()
+}
+
+/// Hashes two sibling nodes into their parent node.
+pub fn parent_hash(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new()
+        .chain_update([NODE_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+/// Calculates the root of the tree containing the given leaf at the given index.
+pub fn root_from_proof(leaf: Hash, index: u32, proof: &[Hash]) -> Hash {
+    let mut node = leaf;
+    let mut index = index;
+
+    for sibling in proof {
+        node = if index & 1 == 0 {
+            parent_hash(&node, sibling)
+        } else {
+            parent_hash(sibling, &node)
+        };
+        index >>= 1;
+    }
+
+    node
+}
+
+/// Decodes a hex-encoded hash, returning None if it is not a valid 32-byte hash.
+pub fn decode_hash(hash: &str) -> Option<Hash> {
+    let mut decoded = [0u8; 32];
+    hex::decode_to_slice(hash, &mut decoded).ok()?;
+    Some(decoded)
+}
+
+pub fn encode_hash(hash: &Hash) -> String {
+    hex::encode(hash)
+}
+
+/// Builds all the levels of a Merkle tree from its leaves, from the leaves up to the root.
+///
+/// A node without a sibling on its level is paired with itself.
+pub fn build_tree(leaves: Vec<Hash>) -> Vec<Vec<Hash>> {
+    let mut levels = vec![leaves];
+
+    while levels.last().is_some_and(|level| level.len() > 1) {
+        let level = levels.last().unwrap();
+
+        let parents = level
+            .chunks(2)
+            .map(|pair| parent_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+
+        levels.push(parents);
+    }
+
+    levels
+}
+
+/// Root of a tree built by `build_tree`, or None if the tree has no leaves.
+pub fn tree_root(levels: &[Vec<Hash>]) -> Option<Hash> {
+    levels.last().and_then(|root| root.first()).copied()
+}
+
+/// Sibling hashes proving the leaf at the given index, from the leaf towards the root.
+pub fn tree_proof(levels: &[Vec<Hash>], index: u32) -> Vec<Hash> {
+    let mut index = index as usize;
+    let mut proof = vec![];
+
+    for level in &levels[..levels.len().saturating_sub(1)] {
+        let sibling = level.get(index ^ 1).unwrap_or(&level[index]);
+        proof.push(*sibling);
+        index >>= 1;
+    }
+
+    proof
+}
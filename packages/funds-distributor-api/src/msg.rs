@@ -1,6 +1,20 @@
 use crate::api::{
-    ClaimRewardsMsg, MinimumEligibleWeightResponse, UpdateMinimumEligibleWeightMsg,
-    UserRewardsParams, UserRewardsResponse,
+    AddHookMsg, AddWeightSourceMsg, AuthorizedDistributorsResponse, ChangeWeightSourceMsg,
+    ClaimMerkleMsg, ClaimOperatorsParams, ClaimOperatorsResponse, ClaimRewardsMsg,
+    CompoundingTargetsResponse, DelegateParams, DelegateResponse, DenomAllowlistResponse,
+    DistributionStatsParams, DistributionStatsResponse, EligibleMemberCountResponse,
+    ExportStateParams, ExportStateResponse, GrantClaimOperatorMsg, GroupMembersParams,
+    GroupMembersResponse, HooksResponse, ImportStateMsg, MerkleClaimedParams,
+    MerkleClaimedResponse, MerkleDistributionParams, MerkleDistributionResponse,
+    MinimumEligibleWeightResponse, ProtocolFeesResponse, RegisterMerkleDistributionMsg,
+    RemoveHookMsg, RemoveWeightSourceMsg, RetireDenomMsg, RevokeClaimOperatorMsg,
+    RewardRecipientParams, RewardRecipientResponse, RewardSplitParams, RewardSplitResponse,
+    RewardTiersResponse, SetDelegateMsg, SetRewardRecipientMsg, SetRewardSplitMsg,
+    StakeChangedHookMsg, SweepMerkleDistributionMsg, TotalWeightAtParams, TotalWeightResponse,
+    UpdateAuthorizedDistributorsMsg, UpdateCompoundingTargetsMsg, UpdateDenomAllowlistMsg,
+    UpdateGroupMembersMsg, UpdateMinimumEligibleWeightMsg, UpdateNativeStakeWeightsMsg,
+    UpdateProtocolFeesMsg, UpdateRewardTiersMsg, UserRewardsParams, UserRewardsResponse,
+    UserWeightAtParams, UserWeightResponse, WeightSourcesResponse,
 };
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::Uint128;
@@ -8,17 +22,85 @@ use cw4::MemberChangedHookMsg;
 
 #[cw_serde]
 pub struct InstantiateMsg {
+    /// Admin of the contract, also registered as the initial weight source
     pub squad_contract: String,
-    /// Optional minimum weight that the user must have to be eligible for rewards distributions
+    /// Optional minimum weight that the user must have to be eligible for rewards distributions,
+    /// which becomes the minimum weight of the single initial reward tier
     pub minimum_eligible_weight: Option<Uint128>,
+    /// Whether rewards exported from another contract are going to be imported. Funds cannot be
+    /// distributed until the admin closes the import with `CloseStateImport`
+    pub state_import: Option<bool>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     MemberChangedHook(MemberChangedHookMsg),
+    StakeChangeHook(StakeChangedHookMsg),
+    UpdateNativeStakeWeights(UpdateNativeStakeWeightsMsg),
+    /// Changes the minimum weight of the lowest reward tier, which is the minimum eligible weight.
+    /// Has to stay under the next tier's minimum weight
     UpdateMinimumEligibleWeight(UpdateMinimumEligibleWeightMsg),
     DistributeNative {},
+    /// Distributes the sent funds according to the users' weights at the beginning of the given
+    /// block height, instead of their current weights
+    DistributeNativeAtHeight {
+        height: u64,
+    },
+    /// Distributes the sent funds in equal amounts to each user eligible for rewards, regardless
+    /// of their weights
+    DistributeNativeEqually {},
+    /// Distributes the sent funds across the reward tiers according to their shares, and then
+    /// within each tier according to the users' weights
+    DistributeNativeTiered {},
+    /// Distributes the sent funds only to the members of the given group, according to their
+    /// weights
+    DistributeNativeToGroup {
+        group_id: String,
+    },
     ClaimRewards(ClaimRewardsMsg),
+    UpdateCompoundingTargets(UpdateCompoundingTargetsMsg),
+    GrantClaimOperator(GrantClaimOperatorMsg),
+    RevokeClaimOperator(RevokeClaimOperatorMsg),
+    SetRewardRecipient(SetRewardRecipientMsg),
+    /// Splits the user's claimed rewards between multiple recipients
+    SetRewardSplit(SetRewardSplitMsg),
+    /// Sets a delegate to be credited a commission on the user's rewards from `DistributeNative`
+    /// distributions. Rewards from the other kinds of distributions are not subject to it
+    SetDelegate(SetDelegateMsg),
+    AddHook(AddHookMsg),
+    RemoveHook(RemoveHookMsg),
+    AddWeightSource(AddWeightSourceMsg),
+    RemoveWeightSource(RemoveWeightSourceMsg),
+    /// Replaces a weight source with a cw4 contract, and resyncs the users' weights from it one
+    /// page of members per message, until the response reports the change as complete
+    ChangeWeightSource(ChangeWeightSourceMsg),
+    UpdateGroupMembers(UpdateGroupMembersMsg),
+    /// Replaces the reward tiers. Moves the users whose tiers change a page at a time, so has to
+    /// be sent again with the same tiers until the response reports the update as complete.
+    /// The lowest tier's minimum weight is the minimum eligible weight, and the users whose
+    /// eligibility it changes are updated right away. Tier shares only split
+    /// `DistributeNativeTiered` distributions
+    UpdateRewardTiers(UpdateRewardTiersMsg),
+    UpdateDenomAllowlist(UpdateDenomAllowlistMsg),
+    /// Removes all the state kept for a denom whose rewards were fully claimed.
+    /// Goes through the users a page at a time, so has to be called again until the response's
+    /// `retired` attribute is true
+    RetireDenom(RetireDenomMsg),
+    /// Restricts who can fund distributions. Anyone can fund them while no distributors are set
+    UpdateAuthorizedDistributors(UpdateAuthorizedDistributorsMsg),
+    /// Configures the fees taken from `DistributeNative` deposits and where they are sent
+    UpdateProtocolFees(UpdateProtocolFeesMsg),
+    RegisterMerkleDistribution(RegisterMerkleDistributionMsg),
+    ClaimMerkle(ClaimMerkleMsg),
+    /// Sends the unclaimed funds of an expired Merkle distribution back to the admin
+    SweepMerkleDistribution(SweepMerkleDistributionMsg),
+    /// Credits users the rewards exported from another contract. Only possible if the contract
+    /// was instantiated for importing state, until the admin closes the import, and the contract
+    /// has to hold enough funds to pay out all the rewards it owes
+    ImportState(ImportStateMsg),
+    /// Ends the phase in which state can be imported, allowing funds to be distributed. Only the
+    /// admin can close it
+    CloseStateImport {},
 }
 
 #[cw_serde]
@@ -28,9 +110,56 @@ pub enum QueryMsg {
     UserRewards(UserRewardsParams),
     #[returns(MinimumEligibleWeightResponse)]
     MinimumEligibleWeight {},
+    #[returns(CompoundingTargetsResponse)]
+    CompoundingTargets {},
+    #[returns(ClaimOperatorsResponse)]
+    ClaimOperators(ClaimOperatorsParams),
+    #[returns(RewardRecipientResponse)]
+    RewardRecipient(RewardRecipientParams),
+    #[returns(RewardSplitResponse)]
+    RewardSplit(RewardSplitParams),
+    #[returns(DelegateResponse)]
+    Delegate(DelegateParams),
+    #[returns(HooksResponse)]
+    Hooks {},
+    #[returns(WeightSourcesResponse)]
+    WeightSources {},
+    /// User's weight at the beginning of the given block height
+    #[returns(UserWeightResponse)]
+    UserWeightAt(UserWeightAtParams),
+    /// Total weight at the beginning of the given block height
+    #[returns(TotalWeightResponse)]
+    TotalWeightAt(TotalWeightAtParams),
+    #[returns(EligibleMemberCountResponse)]
+    EligibleMemberCount {},
+    #[returns(GroupMembersResponse)]
+    GroupMembers(GroupMembersParams),
+    #[returns(RewardTiersResponse)]
+    RewardTiers {},
+    #[returns(DenomAllowlistResponse)]
+    DenomAllowlist {},
+    #[returns(AuthorizedDistributorsResponse)]
+    AuthorizedDistributors {},
+    #[returns(ProtocolFeesResponse)]
+    ProtocolFees {},
+    /// Totals distributed and taken as protocol fees through `DistributeNative`, per denom
+    #[returns(DistributionStatsResponse)]
+    DistributionStats(DistributionStatsParams),
+    #[returns(MerkleDistributionResponse)]
+    MerkleDistribution(MerkleDistributionParams),
+    #[returns(MerkleClaimedResponse)]
+    MerkleClaimed(MerkleClaimedParams),
+    /// Page of the users' rewards, to be imported into another contract. Weights are not
+    /// exported, the other contract gets them from its own weight sources
+    #[returns(ExportStateResponse)]
+    ExportState(ExportStateParams),
 }
 
 #[cw_serde]
 pub struct MigrateMsg {
-    pub new_hook_src: String,
+    /// New admin and hook source of the contract, if changing it.
+    /// Starts changing the old admin's weight source to this cw4 contract, which the new admin
+    /// completes by sending `ChangeWeightSource` from the old admin to this contract until the
+    /// change is complete
+    pub new_hook_src: Option<String>,
 }
@@ -0,0 +1,9 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ModelError {
+    #[error("Cannot distribute with zero total weight")]
+    ZeroTotalWeight,
+}
+
+pub type ModelResult<T> = Result<T, ModelError>;
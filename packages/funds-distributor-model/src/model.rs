@@ -0,0 +1,244 @@
+use crate::error::ModelError::ZeroTotalWeight;
+use crate::error::ModelResult;
+use cosmwasm_std::{Coin, Decimal256, Uint128, Uint256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// State of a single user's rewards.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct UserState {
+    /// User's weight, if they are a member
+    weight: Option<Uint128>,
+    /// User's exact unclaimed rewards, per denom
+    rewards: BTreeMap<String, Decimal256>,
+}
+
+/// In-memory model of the funds distributor, for a single weight source.
+///
+/// Splits every distribution between the users pro rata to their effective weights at the time
+/// it happens, without any reward indices. The contract's rewards are never above the model's,
+/// and are under them by less than one unit for every time the contract settled them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DistributorModel {
+    minimum_eligible_weight: Uint128,
+    users: BTreeMap<String, UserState>,
+    /// Every denom that was ever distributed
+    denoms: BTreeSet<String>,
+}
+
+impl DistributorModel {
+    pub fn new(minimum_eligible_weight: Uint128) -> Self {
+        DistributorModel {
+            minimum_eligible_weight,
+            ..DistributorModel::default()
+        }
+    }
+
+    /// Creates a model with the given initial members, as when instantiating the contract.
+    pub fn with_members(
+        members: impl IntoIterator<Item = (String, Uint128)>,
+        minimum_eligible_weight: Uint128,
+    ) -> Self {
+        let mut model = DistributorModel::new(minimum_eligible_weight);
+
+        for (user, weight) in members {
+            model.update_user_weight(&user, Some(weight));
+        }
+
+        model
+    }
+
+    pub fn minimum_eligible_weight(&self) -> Uint128 {
+        self.minimum_eligible_weight
+    }
+
+    pub fn user_weight(&self, user: &str) -> Uint128 {
+        self.users
+            .get(user)
+            .and_then(|state| state.weight)
+            .unwrap_or_default()
+    }
+
+    /// User's weight as counted for rewards, which is zero under the minimum eligible weight.
+    pub fn effective_weight(&self, user: &str) -> Uint128 {
+        let weight = self.user_weight(user);
+
+        if weight >= self.minimum_eligible_weight {
+            weight
+        } else {
+            Uint128::zero()
+        }
+    }
+
+    /// Total effective weight of all the users.
+    pub fn total_weight(&self) -> Uint128 {
+        self.users
+            .keys()
+            .map(|user| self.effective_weight(user))
+            .sum()
+    }
+
+    /// Changes the user's weight, or removes them from the members if not given.
+    pub fn update_user_weight(&mut self, user: &str, weight: Option<Uint128>) {
+        self.users.entry(user.to_string()).or_default().weight = weight;
+    }
+
+    pub fn update_minimum_eligible_weight(&mut self, minimum_eligible_weight: Uint128) {
+        self.minimum_eligible_weight = minimum_eligible_weight;
+    }
+
+    /// Distributes the funds to all the users, according to their effective weights.
+    pub fn distribute(&mut self, funds: &[Coin]) -> ModelResult<()> {
+        let total_weight = self.total_weight();
+        if total_weight.is_zero() {
+            return Err(ZeroTotalWeight);
+        }
+
+        let shares = self
+            .users
+            .keys()
+            .map(|user| (user.clone(), self.effective_weight(user)))
+            .filter(|(_, weight)| !weight.is_zero())
+            .collect::<Vec<(String, Uint128)>>();
+
+        for fund in funds {
+            self.denoms.insert(fund.denom.clone());
+
+            for (user, weight) in &shares {
+                let share = Decimal256::from_ratio(
+                    Uint256::from(fund.amount) * Uint256::from(*weight),
+                    Uint256::from(total_weight),
+                );
+
+                let state = self.users.entry(user.clone()).or_default();
+                *state.rewards.entry(fund.denom.clone()).or_default() += share;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// User's exact unclaimed rewards for the denom, to 18 decimal places.
+    pub fn exact_user_reward(&self, user: &str, denom: &str) -> Decimal256 {
+        self.users
+            .get(user)
+            .and_then(|state| state.rewards.get(denom))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// User's currently available rewards for every denom that was ever distributed, in whole
+    /// units.
+    pub fn user_rewards(&self, user: &str) -> Vec<Coin> {
+        self.denoms
+            .iter()
+            .map(|denom| Coin::new(whole_units(self.exact_user_reward(user, denom)), denom))
+            .collect()
+    }
+
+    /// Claims the whole units of the user's rewards, keeping the fractions for later claims.
+    pub fn claim(&mut self, user: &str) -> Vec<Coin> {
+        let Some(state) = self.users.get_mut(user) else {
+            return vec![];
+        };
+
+        state
+            .rewards
+            .iter_mut()
+            .filter_map(|(denom, reward)| {
+                let claimed = whole_units(*reward);
+                if claimed.is_zero() {
+                    return None;
+                }
+
+                *reward -= Decimal256::from_ratio(claimed, 1u8);
+
+                Some(Coin::new(claimed, denom))
+            })
+            .collect()
+    }
+}
+
+/// Rounds the reward down to whole units.
+fn whole_units(reward: Decimal256) -> Uint128 {
+    Uint128::try_from(reward.to_uint_floor()).unwrap_or(Uint128::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::coins;
+
+    #[test]
+    fn rewards_follow_weight_changes() {
+        let mut model = DistributorModel::with_members(
+            vec![
+                ("user1".to_string(), Uint128::new(1)),
+                ("user2".to_string(), Uint128::new(3)),
+            ],
+            Uint128::zero(),
+        );
+
+        model.distribute(&coins(400, "uluna")).unwrap();
+
+        model.update_user_weight("user1", None);
+        model.update_user_weight("user3", Some(Uint128::new(4)));
+
+        model.distribute(&coins(700, "uluna")).unwrap();
+
+        assert_eq!(model.user_rewards("user1"), coins(100, "uluna"));
+        assert_eq!(model.user_rewards("user2"), coins(600, "uluna"));
+        assert_eq!(model.user_rewards("user3"), coins(400, "uluna"));
+
+        assert_eq!(model.claim("user2"), coins(600, "uluna"));
+        assert_eq!(model.user_rewards("user2"), coins(0, "uluna"));
+    }
+
+    #[test]
+    fn fractions_of_rewards_are_kept_for_later_claims() {
+        let mut model = DistributorModel::with_members(
+            vec![
+                ("user1".to_string(), Uint128::new(1)),
+                ("user2".to_string(), Uint128::new(1)),
+            ],
+            Uint128::zero(),
+        );
+
+        model.distribute(&coins(3, "uluna")).unwrap();
+        assert_eq!(model.claim("user1"), coins(1, "uluna"));
+        assert_eq!(
+            model.exact_user_reward("user1", "uluna"),
+            Decimal256::percent(50)
+        );
+
+        model.distribute(&coins(1, "uluna")).unwrap();
+        assert_eq!(model.claim("user1"), coins(1, "uluna"));
+        assert_eq!(model.user_rewards("user2"), coins(2, "uluna"));
+    }
+
+    #[test]
+    fn users_under_minimum_eligible_weight_get_no_rewards() {
+        let mut model = DistributorModel::with_members(
+            vec![
+                ("user1".to_string(), Uint128::new(1)),
+                ("user2".to_string(), Uint128::new(4)),
+            ],
+            Uint128::new(2),
+        );
+
+        model.distribute(&coins(400, "uluna")).unwrap();
+        assert_eq!(model.user_rewards("user1"), coins(0, "uluna"));
+
+        model.update_minimum_eligible_weight(Uint128::new(1));
+
+        model.distribute(&coins(500, "uluna")).unwrap();
+        assert_eq!(model.user_rewards("user1"), coins(100, "uluna"));
+        assert_eq!(model.user_rewards("user2"), coins(800, "uluna"));
+    }
+
+    #[test]
+    fn distribute_with_zero_total_weight_fails() {
+        let mut model = DistributorModel::new(Uint128::zero());
+
+        assert_eq!(model.distribute(&coins(100, "uluna")), Err(ZeroTotalWeight));
+    }
+}